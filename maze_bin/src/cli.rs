@@ -0,0 +1,422 @@
+//! Parsing the command line into `Args`, without pulling in an argument parsing crate.
+
+use maze_lib::code::MazeCode;
+use maze_lib::grid::Wrap;
+use maze_lib::heatmap::{self, ColorRamp};
+use maze_lib::mazegen::{Algo, ParseAlgoError};
+use maze_lib::pathfinding::solver::{Solver, UnknownSolver};
+use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+
+pub const USAGE: &str = "\
+Usage: maze_bin <command> [options]
+
+Commands:
+  generate        carve a maze and write it out
+  solve           find a path through a maze and draw it
+  render          draw a maze that was written out before
+  stats           count the dead-ends each algorithm leaves, and time them
+  demo <kind>     draw one of the other kinds of maze: polar, hex, triangle, weave, animate
+  help            show this message
+
+Maze options (generate, solve, stats):
+  -w, --width N         cells across [default: 20]
+  -H, --height N        cells down [default: 20]
+      --depth N         floors [default: 1]
+      --wrap WRAP       none, cylinder or torus [default: none]
+  -a, --algo NAME       algorithm to carve with [default: RecursiveBacktracker]
+      --seed TEXT       carve the same maze every time for the same text [default: a random seed]
+      --braid P         remove each dead-end with probability P after carving, to the nearest percent
+  -c, --code CODE       carve the maze that was printed with this code, instead of giving the options above
+                        (generate, solve, render)
+  -i, --input FILE      read the maze from a saved maze or a text drawing instead of carving one (solve, render)
+
+Solve options:
+      --start N         cell to start from [default: the saved start, or the first cell]
+      --goal N          cell to find a path to [default: the saved goal, or the last cell]
+  -s, --solver NAME     search to find the path with, from the solvers below [default: AStar]
+
+Render options:
+      --heatmap RAMP    color cells by distance with green, grayscale, fire or rainbow (svg, png, ppm)
+      --from N          cell the heatmap measures distance from [default: the first cell]
+
+Stats options:
+      --samples N       mazes to carve with each algorithm [default: 100]
+
+Output options (generate, solve, render):
+  -o, --output FILE     where to write the maze [default: standard output]
+  -f, --format FORMAT   text, unicode, svg, png, ppm or maze (the binary save format)
+                        [default: taken from the extension of the output, or text]
+      --cell-size N     pixels across each cell (png, ppm) [default: 4]
+      --wall-size N     pixels across each wall (png, ppm) [default: 1]
+
+Algorithms:
+  BinaryTree, Sidewinder, AldousBroder, Wilson, HuntAndKill, RecursiveBacktracker, Kruskal, Eller,
+  RecursiveDivision, PrimSimplified, PrimTrue, GrowingTreeNewest, GrowingTreeOldest, GrowingTreeRandom,
+  GrowingTreeMiddle, GrowingTreeNewestRandom, Empty,
+  or GrowingTree:N,O,R,M to weigh how often the growing tree grows from the newest, oldest, a random or the
+  middle cell
+
+Solvers:
+  UniformCostSearch, AStar, GreedyBestFirst, DepthFirstSearch, BreadthFirstSearch,
+  BidirectionalBreadthFirstSearch, JumpPointSearch, IterativeDeepeningAStar, BeamSearch
+";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+   Generate,
+   Solve,
+   Render,
+   Stats,
+   Demo(Demo),
+   Help,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Demo {
+   Polar,
+   Hex,
+   Triangle,
+   Weave,
+   Animate,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+   Text,
+   Unicode,
+   Svg,
+   Png,
+   Ppm,
+   Maze,
+}
+
+impl Format {
+   fn from_name(name: &str) -> Option<Format> {
+      match name {
+         "text" | "txt" => Some(Format::Text),
+         "unicode" => Some(Format::Unicode),
+         "svg" => Some(Format::Svg),
+         "png" => Some(Format::Png),
+         "ppm" => Some(Format::Ppm),
+         "maze" => Some(Format::Maze),
+         _ => None,
+      }
+   }
+
+   /// The format to write `path` in when none is given, going by its extension
+   pub fn from_path(path: &Path) -> Format {
+      path
+         .extension()
+         .and_then(|x| x.to_str())
+         .and_then(|x| Format::from_name(&x.to_ascii_lowercase()))
+         .unwrap_or(Format::Text)
+   }
+}
+
+/// A command line that doesn't make sense, with what is wrong with it
+#[derive(Debug, PartialEq, Eq)]
+pub struct UsageError(pub String);
+
+impl Display for UsageError {
+   fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+      f.write_str(&self.0)
+   }
+}
+
+impl std::error::Error for UsageError {}
+
+pub struct Args {
+   pub command: Command,
+   pub width: usize,
+   pub height: usize,
+   pub depth: usize,
+   pub wrap: Wrap,
+   pub algo: Algo,
+   pub seed: Option<String>,
+   pub braid: Option<f64>,
+   pub code: Option<MazeCode>,
+   pub input: Option<PathBuf>,
+   pub start: Option<usize>,
+   pub goal: Option<usize>,
+   pub solver: Solver,
+   pub heatmap: Option<ColorRamp>,
+   pub from: Option<usize>,
+   pub samples: usize,
+   pub output: Option<PathBuf>,
+   pub format: Format,
+   pub cell_size: usize,
+   pub wall_size: usize,
+}
+
+impl Args {
+   fn new(command: Command) -> Args {
+      Args {
+         command,
+         width: 20,
+         height: 20,
+         depth: 1,
+         wrap: Wrap::NONE,
+         algo: Algo::RecursiveBacktracker,
+         seed: None,
+         braid: None,
+         code: None,
+         input: None,
+         start: None,
+         goal: None,
+         solver: Solver::AStar,
+         heatmap: None,
+         from: None,
+         samples: 100,
+         output: None,
+         format: Format::Text,
+         cell_size: 4,
+         wall_size: 1,
+      }
+   }
+}
+
+/// Which commands take an option
+fn applies_to(option: &str, command: Command) -> bool {
+   use Command::*;
+   match option {
+      "--width" | "--height" | "--algo" | "--seed" | "--braid" => matches!(command, Generate | Solve | Stats),
+      "--depth" | "--wrap" => matches!(command, Generate | Solve),
+      "--code" => matches!(command, Generate | Solve | Render),
+      "--input" => matches!(command, Solve | Render),
+      "--start" | "--goal" | "--solver" => command == Solve,
+      "--heatmap" | "--from" => command == Render,
+      "--samples" => command == Stats,
+      "--output" | "--format" | "--cell-size" | "--wall-size" => matches!(command, Generate | Solve | Render),
+      _ => false,
+   }
+}
+
+fn long_name(option: &str) -> &str {
+   match option {
+      "-w" => "--width",
+      "-H" => "--height",
+      "-a" => "--algo",
+      "-c" => "--code",
+      "-i" => "--input",
+      "-s" => "--solver",
+      "-o" => "--output",
+      "-f" => "--format",
+      _ => option,
+   }
+}
+
+fn number(option: &str, value: &str) -> Result<usize, UsageError> {
+   value
+      .parse()
+      .map_err(|_| UsageError(format!("{} expects a whole number, got {:?}", option, value)))
+}
+
+fn positive(option: &str, value: &str) -> Result<usize, UsageError> {
+   match number(option, value)? {
+      0 => Err(UsageError(format!("{} must be at least 1", option))),
+      x => Ok(x),
+   }
+}
+
+/// Parses the arguments after the program name
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, UsageError> {
+   let mut args = args.into_iter();
+   let command = match args.next().as_deref() {
+      None | Some("help" | "--help" | "-h") => return Ok(Args::new(Command::Help)),
+      Some("generate") => Command::Generate,
+      Some("solve") => Command::Solve,
+      Some("render") => Command::Render,
+      Some("stats") => Command::Stats,
+      Some("demo") => Command::Demo(match args.next().as_deref() {
+         Some("polar") => Demo::Polar,
+         Some("hex") => Demo::Hex,
+         Some("triangle") => Demo::Triangle,
+         Some("weave") => Demo::Weave,
+         Some("animate") => Demo::Animate,
+         Some(x) => return Err(UsageError(format!("unknown demo {:?}", x))),
+         None => return Err(UsageError("demo needs a kind of maze to draw".to_string())),
+      }),
+      Some(x) => return Err(UsageError(format!("unknown command {:?}", x))),
+   };
+
+   let mut parsed = Args::new(command);
+   let mut format = None;
+   // the first option that describes the maze to carve, which can't be combined with any other way to get a maze
+   let mut maze_option = None;
+   while let Some(arg) = args.next() {
+      if arg == "--help" || arg == "-h" {
+         return Ok(Args::new(Command::Help));
+      }
+      // both `--width 10` and `--width=10`
+      let (option, inline_value) = match arg.split_once('=') {
+         Some((option, value)) if arg.starts_with("--") => (option, Some(value.to_string())),
+         _ => (arg.as_str(), None),
+      };
+      let option = long_name(option);
+      if !option.starts_with('-') {
+         return Err(UsageError(format!("unexpected argument {:?}", option)));
+      }
+      if !applies_to(option, command) {
+         return Err(
+            if applies_to(option, Command::Generate)
+               || applies_to(option, Command::Render)
+               || applies_to(option, Command::Solve)
+               || applies_to(option, Command::Stats)
+            {
+               UsageError(format!("{} can't be used with this command", option))
+            } else {
+               UsageError(format!("unknown option {:?}", option))
+            },
+         );
+      }
+      let value = inline_value
+         .or_else(|| args.next())
+         .ok_or_else(|| UsageError(format!("{} needs a value", option)))?;
+      if matches!(
+         option,
+         "--width" | "--height" | "--depth" | "--wrap" | "--algo" | "--seed" | "--braid"
+      ) {
+         maze_option.get_or_insert(option.to_string());
+      }
+      match option {
+         "--width" => parsed.width = positive(option, &value)?,
+         "--height" => parsed.height = positive(option, &value)?,
+         "--depth" => parsed.depth = positive(option, &value)?,
+         "--wrap" => {
+            parsed.wrap = match value.as_str() {
+               "none" => Wrap::NONE,
+               "cylinder" => Wrap::CYLINDER,
+               "torus" => Wrap::TORUS,
+               _ => return Err(UsageError(format!("unknown wrap {:?}", value))),
+            }
+         }
+         "--algo" => parsed.algo = value.parse().map_err(|e: ParseAlgoError| UsageError(e.to_string()))?,
+         "--seed" => parsed.seed = Some(value),
+         "--braid" => {
+            parsed.braid = match value.parse::<f64>() {
+               Ok(p) if (0.0..=1.0).contains(&p) => Some(p),
+               _ => {
+                  return Err(UsageError(format!(
+                     "--braid expects a probability from 0 to 1, got {:?}",
+                     value
+                  )));
+               }
+            }
+         }
+         "--code" => {
+            parsed.code = Some(
+               MazeCode::decode(&value).map_err(|e| UsageError(format!("--code {:?} is not valid: {}", value, e)))?,
+            )
+         }
+         "--input" => parsed.input = Some(PathBuf::from(value)),
+         "--start" => parsed.start = Some(number(option, &value)?),
+         "--goal" => parsed.goal = Some(number(option, &value)?),
+         "--solver" => parsed.solver = value.parse().map_err(|e: UnknownSolver| UsageError(e.to_string()))?,
+         "--heatmap" => {
+            parsed.heatmap = Some(
+               heatmap::ramp_from_name(&value).ok_or_else(|| UsageError(format!("unknown color ramp {:?}", value)))?,
+            )
+         }
+         "--from" => parsed.from = Some(number(option, &value)?),
+         "--samples" => parsed.samples = positive(option, &value)?,
+         "--output" => parsed.output = Some(PathBuf::from(value)),
+         "--format" => {
+            format = Some(Format::from_name(&value).ok_or_else(|| UsageError(format!("unknown format {:?}", value)))?)
+         }
+         "--cell-size" => parsed.cell_size = positive(option, &value)?,
+         "--wall-size" => parsed.wall_size = number(option, &value)?,
+         _ => unreachable!(),
+      }
+   }
+
+   parsed.format = format.unwrap_or_else(|| parsed.output.as_deref().map_or(Format::Text, Format::from_path));
+   if command == Command::Render && parsed.input.is_none() && parsed.code.is_none() {
+      return Err(UsageError(
+         "render needs a maze to draw, given with --input or --code".to_string(),
+      ));
+   }
+   let source = match (parsed.input.is_some(), parsed.code.is_some()) {
+      (true, true) => return Err(UsageError("--code can't be used with --input".to_string())),
+      (true, false) => Some("--input"),
+      (false, true) => Some("--code"),
+      (false, false) => None,
+   };
+   if let (Some(source), Some(option)) = (source, maze_option) {
+      return Err(UsageError(format!("{} can't be used with {}", option, source)));
+   }
+   if parsed.from.is_some() && parsed.heatmap.is_none() {
+      return Err(UsageError("--from needs --heatmap".to_string()));
+   }
+   Ok(parsed)
+}
+
+#[cfg(test)]
+mod test {
+   use super::{Command, Demo, Format, USAGE, UsageError, parse};
+   use maze_lib::code::MazeCode;
+   use maze_lib::grid::Wrap;
+   use maze_lib::mazegen::{ALGO_NAMES, Algo};
+   use maze_lib::pathfinding::solver::{SOLVERS, Solver};
+
+   fn parse_str(args: &str) -> Result<super::Args, UsageError> {
+      parse(args.split_whitespace().map(String::from))
+   }
+
+   #[test]
+   fn options() {
+      let args =
+         parse_str("solve -w 30 --height=10 --wrap torus --seed abc --goal 7 -s DepthFirstSearch -o x.png").unwrap();
+      assert_eq!(args.command, Command::Solve);
+      assert_eq!((args.width, args.height, args.depth), (30, 10, 1));
+      assert_eq!(args.wrap, Wrap::TORUS);
+      assert_eq!(args.seed.as_deref(), Some("abc"));
+      assert_eq!((args.start, args.goal), (None, Some(7)));
+      assert_eq!(args.solver, Solver::DepthFirstSearch);
+      assert_eq!(args.format, Format::Png);
+      assert_eq!(parse_str("generate -o maze.out -f svg").unwrap().format, Format::Svg);
+      assert_eq!(parse_str("generate").unwrap().format, Format::Text);
+      let code = MazeCode::new(Algo::Wilson, 5, 4, 99);
+      let args = parse_str(&format!("render --code {} -f svg", code.to_string().to_lowercase())).unwrap();
+      assert_eq!(args.code, Some(code));
+      assert_eq!(parse_str("demo weave").unwrap().command, Command::Demo(Demo::Weave));
+      assert_eq!(parse_str("").unwrap().command, Command::Help);
+      assert_eq!(parse_str("stats --help").unwrap().command, Command::Help);
+   }
+
+   #[test]
+   fn usage_errors() {
+      let error = |args| parse_str(args).err().unwrap().0;
+      assert_eq!(error("carve"), "unknown command \"carve\"");
+      assert_eq!(error("generate --frobnicate 3"), "unknown option \"--frobnicate\"");
+      assert_eq!(error("generate --width"), "--width needs a value");
+      assert_eq!(
+         error("generate --width ten"),
+         "--width expects a whole number, got \"ten\""
+      );
+      assert_eq!(error("generate --width 0"), "--width must be at least 1");
+      assert_eq!(error("generate -s AStar"), "--solver can't be used with this command");
+      assert_eq!(error("generate -a Wilsons"), "unknown algorithm \"Wilsons\"");
+      assert_eq!(error("generate 20"), "unexpected argument \"20\"");
+      assert_eq!(
+         error("render -o x.svg"),
+         "render needs a maze to draw, given with --input or --code"
+      );
+      assert_eq!(error("solve -i a.maze --width 3"), "--width can't be used with --input");
+      assert_eq!(
+         error("generate --code 0000"),
+         "--code \"0000\" is not valid: maze code is mistyped"
+      );
+      assert_eq!(error("demo"), "demo needs a kind of maze to draw");
+   }
+
+   #[test]
+   fn usage_lists_every_name() {
+      for (name, _) in ALGO_NAMES {
+         assert!(USAGE.contains(name), "{}", name);
+      }
+      for solver in SOLVERS {
+         assert!(USAGE.contains(solver.id()), "{}", solver);
+      }
+   }
+}
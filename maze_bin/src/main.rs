@@ -1,95 +1,396 @@
 #![allow(clippy::uninlined_format_args)] // I'm an old man and I like the way it was before
 
-use maze_lib::grid::Grid;
+mod cli;
+
+use cli::{Args, Command, Demo, Format};
+use maze_lib::code::{self, MazeCode};
+use maze_lib::grid::{Grid, Wrap};
+use maze_lib::heatmap::{self, Heatmap};
+use maze_lib::hex::HexGrid;
 use maze_lib::mazegen;
+use maze_lib::pathfinding::algos;
+use maze_lib::pathfinding::diagnostic_map::FinalizedDiagMap;
+use maze_lib::pathfinding::heuristics;
+use maze_lib::polar::PolarGrid;
+use maze_lib::raster::Raster;
+use maze_lib::save;
+use maze_lib::triangle::TriangleGrid;
+use maze_lib::unicode::UnicodeMaze;
+use maze_lib::weave::WeaveGrid;
 use rand::SeedableRng;
 use rand_xorshift::XorShiftRng;
+use std::error::Error;
 use std::fs::File;
 use std::io::{self, BufWriter, Write};
-use std::time::Instant;
-
-fn init_svg(name: &'static str, grid: &Grid) -> io::Result<BufWriter<File>> {
-   let mut destination = BufWriter::new(File::create(format!("{}.svg", name)).unwrap());
-   writeln!(
-      destination,
-      "<svg viewBox=\"-3 -3 {} {}\" xmlns=\"http://www.w3.org/2000/svg\">",
-      (grid.width * 3) + 6,
-      (grid.height * 3) + 6
-   )?;
-   Ok(destination)
-}
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
 fn main() {
-   let mut rng = XorShiftRng::from_os_rng();
-   if std::env::args().any(|x| x == "--dead-ends") {
-      const DEADEND_WIDTH: usize = 20;
-      const DEADEND_HEIGHT: usize = 20;
-      const DEADEND_SIZE: usize = DEADEND_WIDTH * DEADEND_HEIGHT;
-      let mut grid = Grid::new(DEADEND_WIDTH, DEADEND_HEIGHT);
-      const DEADEND_SAMPLES: usize = 100;
-      let avg_fmt_width = format!("{}", DEADEND_SIZE).len();
-      let mut averages = Vec::with_capacity(mazegen::ALGOS.len());
-      for algo in mazegen::ALGOS.iter() {
-         println!("Running {}...", algo);
-         let mut deadend_counts: Vec<usize> = Vec::with_capacity(DEADEND_SAMPLES);
-         for _ in 0..DEADEND_SAMPLES {
-            grid.reset();
-            mazegen::carve_maze(&mut grid, &mut rng, *algo);
-            deadend_counts.push(grid.dead_ends().count());
-         }
-         let total_deadends: usize = deadend_counts.iter().sum();
-         let avg_deadends = total_deadends as f64 / DEADEND_SAMPLES as f64;
-         averages.push((*algo, avg_deadends));
+   let args = match cli::parse(std::env::args().skip(1)) {
+      Ok(x) => x,
+      Err(e) => {
+         eprintln!("error: {}", e);
+         eprintln!("run `maze_bin help` to see the commands and their options");
+         std::process::exit(2);
       }
-      println!();
-      println!(
-         "Average dead-ends per {}x{} maze ({} total cells):",
-         DEADEND_WIDTH, DEADEND_HEIGHT, DEADEND_SIZE
-      );
-      println!();
-      averages.sort_unstable_by(|x, y| y.1.partial_cmp(&x.1).unwrap());
-      for (algo, avg) in averages {
-         let pct = avg * 100.0 / (DEADEND_SIZE as f64);
-         println!(
-            "{:>23} : {:>width$} ({}%)",
-            format!("{}", algo),
-            avg.round(),
-            pct.round(),
-            width = avg_fmt_width
-         );
+   };
+   let result = match args.command {
+      Command::Generate => generate(&args),
+      Command::Solve => solve(&args),
+      Command::Render => render(&args),
+      Command::Stats => stats(&args),
+      Command::Demo(demo) => run_demo(demo),
+      Command::Help => {
+         print!("{}", cli::USAGE);
+         Ok(())
       }
-      return;
+   };
+   if let Err(e) = result {
+      eprintln!("error: {}", e);
+      std::process::exit(1);
+   }
+}
+
+/// The maze given with `--code`, or else the one described by the maze options, with a random seed if there is none
+fn maze_code(args: &Args) -> Result<MazeCode> {
+   if let Some(code) = args.code {
+      return Ok(code);
+   }
+   let code = MazeCode {
+      algo: args.algo,
+      width: args.width,
+      height: args.height,
+      depth: args.depth,
+      wrap: args.wrap,
+      braid_percent: args.braid.map_or(0, |p| (p * 100.0).round() as u8),
+      seed: args.seed.as_deref().map_or_else(rand::random, code::seed_from_text),
+   };
+   code.check()?;
+   Ok(code)
+}
+
+/// Carves a maze as described by the maze options, printing its code so that it can be carved again
+fn carve(args: &Args) -> Result<(Grid, save::Metadata)> {
+   let code = maze_code(args)?;
+   let start_time = Instant::now();
+   let grid = code.carve();
+   eprintln!(
+      "carved a {}x{}x{} maze with {} in {}s, leaving {} dead-ends",
+      grid.width,
+      grid.height,
+      grid.depth,
+      code.algo,
+      start_time.elapsed().as_secs_f64(),
+      grid.dead_ends().count()
+   );
+   eprintln!("maze code: {}", code);
+   let metadata = save::Metadata {
+      algorithm: Some(code.algo.to_string()),
+      seed: Some(code.seed),
+      ..save::Metadata::default()
+   };
+   Ok((grid, metadata))
+}
+
+/// Reads a maze written with `--format maze`, or drawn like `--format text`
+fn read_maze(path: &Path) -> Result<(Grid, save::Metadata)> {
+   let with_path = |e: &dyn Error| format!("{}: {}", path.display(), e);
+   let data = std::fs::read(path).map_err(|e| with_path(&e))?;
+   if data.starts_with(b"MAZE") {
+      save::from_bytes(&data).map_err(|e| with_path(&e).into())
+   } else {
+      let text = String::from_utf8(data).map_err(|e| with_path(&e))?;
+      let grid = Grid::from_ascii(&text).map_err(|e| with_path(&e))?;
+      Ok((grid, save::Metadata::default()))
+   }
+}
+
+fn check_cell(grid: &Grid, option: &str, index: usize) -> Result<()> {
+   if index >= grid.size() {
+      Err(
+         format!(
+            "{} {} is outside of the maze, which has {} cells",
+            option,
+            index,
+            grid.size()
+         )
+         .into(),
+      )
+   } else if !grid.is_enabled(index) {
+      Err(format!("{} {} is masked off", option, index).into())
+   } else {
+      Ok(())
+   }
+}
+
+/// What to draw on top of the maze
+#[derive(Default)]
+struct Overlay<'a> {
+   path: &'a [usize],
+   /// What the search explored, if it kept track
+   diag: Option<&'a FinalizedDiagMap>,
+   start: Option<usize>,
+   goal: Option<usize>,
+   heatmap: Option<Heatmap<'a>>,
+}
+
+fn write_maze(args: &Args, grid: &Grid, metadata: &save::Metadata, overlay: &Overlay) -> Result<()> {
+   if args.format == Format::Svg && !overlay.path.is_empty() && grid.depth > 1 {
+      return Err("paths through mazes with several floors can't be drawn as svg".into());
    }
-   let mut grid = Grid::new(10_000, 10_000);
-   // mazegen
-   {
-      let start_time = Instant::now();
-      //mazegen::binary_tree(&mut grid, &mut rng);
-      //mazegen::sidewinder(&mut grid, &mut rng);
-      //mazegen::aldous_broder(&mut grid, &mut rng);
-      //mazegen::wilson(&mut grid, &mut rng);
-      //mazegen::hunt_and_kill(&mut grid, &mut rng);
-      mazegen::recursive_backtracker(&mut grid, &mut rng);
-      //mazegen::kruskal(&mut grid, &mut rng);
-      //mazegen::recursive_division(&mut grid, &mut rng);
-      //mazegen::eller(&mut grid, &mut rng);
-      //mazegen::prim_simplified(&mut grid, &mut rng);
-      println!("mazegen elapsed: {}", start_time.elapsed().as_secs_f64());
-      println!("{} dead-ends", grid.dead_ends().count());
+   let mut dest: BufWriter<Box<dyn Write>> = BufWriter::new(match args.output {
+      Some(ref path) => Box::new(File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?),
+      None => Box::new(io::stdout().lock()),
+   });
+   match args.format {
+      Format::Text => write!(dest, "{}", grid)?,
+      Format::Unicode => write!(
+         dest,
+         "{}",
+         UnicodeMaze {
+            grid,
+            path: overlay.path,
+            start: overlay.start,
+            goal: overlay.goal,
+            diag: overlay.diag,
+         }
+      )?,
+      Format::Svg => {
+         let (width, height) = grid.svg_size();
+         writeln!(
+            dest,
+            "<svg viewBox=\"-3 -3 {} {}\" xmlns=\"http://www.w3.org/2000/svg\">",
+            width + 6,
+            height + 6
+         )?;
+         if let Some(ref heatmap) = overlay.heatmap {
+            heatmap.write_to_svg(grid, &mut dest)?;
+         }
+         algos::write_path_to_svg(overlay.path, grid.width, &mut dest)?;
+         writeln!(dest, "<g stroke=\"black\" stroke-width=\"0.5\">")?;
+         grid.write_maze_as_svg(&mut dest)?;
+         writeln!(dest, "</g>")?;
+         writeln!(dest, "</svg>")?;
+      }
+      Format::Png | Format::Ppm => {
+         let raster = Raster {
+            cell_size: args.cell_size,
+            wall_size: args.wall_size,
+            path: overlay.path,
+            diag: overlay.diag,
+            distances: overlay.heatmap.as_ref().map(|x| x.distances),
+            ramp: overlay.heatmap.as_ref().map_or(heatmap::green, |x| x.ramp),
+            ..Raster::new(grid)
+         };
+         if args.format == Format::Png {
+            raster.write_png(&mut dest)?;
+         } else {
+            raster.write_ppm(&mut dest)?;
+         }
+      }
+      Format::Maze => save::save(grid, metadata, &mut dest)?,
    }
+   dest.flush()?;
+   Ok(())
+}
+
+fn generate(args: &Args) -> Result<()> {
+   let (grid, metadata) = carve(args)?;
+   write_maze(args, &grid, &metadata, &Overlay::default())
+}
+
+fn solve(args: &Args) -> Result<()> {
+   let (grid, mut metadata) = match args.input {
+      Some(ref path) => read_maze(path)?,
+      None => carve(args)?,
+   };
+   let start = args.start.or(metadata.start).unwrap_or(0);
+   let goal = args.goal.or(metadata.goal).unwrap_or(grid.size() - 1);
+   check_cell(&grid, "--start", start)?;
+   check_cell(&grid, "--goal", goal)?;
+   metadata.start = Some(start);
+   metadata.goal = Some(goal);
+
    let start_time = Instant::now();
-   //let pf_data = maze_lib::pathfinding::algos::a_star(&grid, maze_lib::pathfinding::heuristics::manhattan_h, 0, grid.size() - 1, false).unwrap();
-   let pf_data = maze_lib::pathfinding::algos::dfs(&grid, 0, grid.size() - 1).unwrap();
-   println!("pathfinding elapsed: {}", start_time.elapsed().as_secs_f64());
+   let h = |i, goal| {
+      if grid.wrap == Wrap::NONE {
+         heuristics::manhattan_3d_h(i, goal, grid.width, grid.height)
+      } else if grid.depth == 1 {
+         heuristics::wrapping_manhattan_h(i, goal, grid.width, grid.height, grid.wrap)
+      } else {
+         // no heuristic takes both wrapping and floors into account
+         heuristics::null_h(i, goal)
+      }
+   };
+   let solution = args.solver.solve(&grid, h, start, goal);
+   let Some(solution) = solution else {
+      return Err(format!("there is no path from {} to {}", start, goal).into());
+   };
+   eprintln!(
+      "found a path of {} cells in {}s, generating {} nodes and expanding {}",
+      solution.path().len(),
+      start_time.elapsed().as_secs_f64(),
+      solution.nodes_generated(),
+      solution.nodes_expanded()
+   );
+   let overlay = Overlay {
+      path: solution.path(),
+      diag: solution.diag(),
+      start: Some(start),
+      goal: Some(goal),
+      heatmap: None,
+   };
+   write_maze(args, &grid, &metadata, &overlay)
+}
+
+fn render(args: &Args) -> Result<()> {
+   let (grid, metadata) = match args.input {
+      Some(ref path) => read_maze(path)?,
+      None => carve(args)?,
+   };
+   let distances = match args.heatmap {
+      Some(_) => {
+         let from = args.from.unwrap_or(0);
+         check_cell(&grid, "--from", from)?;
+         algos::djikstra(&grid, from)
+      }
+      None => Box::new([]),
+   };
+   let overlay = Overlay {
+      start: metadata.start,
+      goal: metadata.goal,
+      heatmap: args.heatmap.map(|ramp| Heatmap::new(&distances, ramp)),
+      ..Overlay::default()
+   };
+   write_maze(args, &grid, &metadata, &overlay)
+}
+
+fn stats(args: &Args) -> Result<()> {
+   let mut rng = match args.seed {
+      Some(ref seed) => XorShiftRng::seed_from_u64(code::seed_from_text(seed)),
+      None => XorShiftRng::from_os_rng(),
+   };
+   let size = args.width * args.height;
+   let mut grid = Grid::new(args.width, args.height);
+   let braid = args.braid.unwrap_or(0.5);
+   let avg_fmt_width = format!("{}", size).len();
+   let mut averages = Vec::with_capacity(mazegen::ALGOS.len());
+   for algo in mazegen::ALGOS.iter() {
+      eprintln!("Running {}...", algo);
+      let mut total_deadends = 0;
+      let mut total_braided = 0;
+      let mut total_time = Duration::ZERO;
+      for _ in 0..args.samples {
+         grid.reset();
+         let start_time = Instant::now();
+         mazegen::carve_maze(&mut grid, &mut rng, *algo);
+         total_time += start_time.elapsed();
+         total_deadends += grid.dead_ends().count();
+         mazegen::braid(&mut grid, &mut rng, braid);
+         total_braided += grid.dead_ends().count();
+      }
+      let samples = args.samples as f64;
+      averages.push((
+         *algo,
+         total_deadends as f64 / samples,
+         total_braided as f64 / samples,
+         total_time.as_secs_f64() / samples,
+      ));
+   }
    println!(
-      "nodes expanded, generated: {} {}",
-      pf_data.nodes_expanded, pf_data.nodes_generated
+      "Average dead-ends per {}x{} maze ({} total cells), before and after braiding with p = {}, and time to carve:",
+      args.width, args.height, size, braid
    );
-   println!("path length: {}", pf_data.path.len());
-   // write the maze clean
-   if false {
-      let mut dest = init_svg("maze", &grid).unwrap();
-      grid.write_maze_as_svg(&mut dest).unwrap();
-      writeln!(dest, "</svg>").unwrap();
+   println!();
+   averages.sort_unstable_by(|x, y| y.1.partial_cmp(&x.1).unwrap());
+   for (algo, avg, avg_braided, avg_time) in averages {
+      let pct = avg * 100.0 / (size as f64);
+      let pct_braided = avg_braided * 100.0 / (size as f64);
+      println!(
+         "{:>23} : {:>width$} ({}%) -> {:>width$} ({}%) in {:.3}ms",
+         format!("{}", algo),
+         avg.round(),
+         pct.round(),
+         avg_braided.round(),
+         pct_braided.round(),
+         avg_time * 1000.0,
+         width = avg_fmt_width
+      );
+   }
+   Ok(())
+}
+
+/// The kinds of maze that only draw themselves as svg, each written to a file named after it
+fn run_demo(demo: Demo) -> Result<()> {
+   let mut rng = XorShiftRng::from_os_rng();
+   let svg_header = |dest: &mut BufWriter<File>, width: f64, height: f64| {
+      writeln!(
+         dest,
+         "<svg viewBox=\"-3 -3 {} {}\" xmlns=\"http://www.w3.org/2000/svg\" stroke=\"black\" stroke-width=\"0.5\">",
+         width + 6.0,
+         height + 6.0
+      )
+   };
+   match demo {
+      Demo::Polar => {
+         let mut grid = PolarGrid::new(12);
+         mazegen::recursive_backtracker(&mut grid, &mut rng);
+         println!("{} dead-ends", grid.dead_ends().count());
+         let diameter = (grid.rows * 3 * 2) as f64;
+         let mut dest = BufWriter::new(File::create("polar.svg")?);
+         svg_header(&mut dest, diameter, diameter)?;
+         grid.write_maze_as_svg(&mut dest)?;
+         writeln!(dest, "</svg>")?;
+      }
+      Demo::Hex => {
+         let mut grid = HexGrid::new(30, 20);
+         mazegen::recursive_backtracker(&mut grid, &mut rng);
+         println!("{} dead-ends", grid.dead_ends().count());
+         let (width, height) = grid.svg_size();
+         let mut dest = BufWriter::new(File::create("hex.svg")?);
+         svg_header(&mut dest, width, height)?;
+         grid.write_maze_as_svg(&mut dest)?;
+         writeln!(dest, "</svg>")?;
+      }
+      Demo::Triangle => {
+         let mut grid = TriangleGrid::new(40, 20);
+         mazegen::recursive_backtracker(&mut grid, &mut rng);
+         println!("{} dead-ends", grid.dead_ends().count());
+         let (width, height) = grid.svg_size();
+         let mut dest = BufWriter::new(File::create("triangle.svg")?);
+         svg_header(&mut dest, width, height)?;
+         grid.write_maze_as_svg(&mut dest)?;
+         writeln!(dest, "</svg>")?;
+      }
+      Demo::Weave => {
+         let mut grid = WeaveGrid::new(30, 30);
+         mazegen::kruskal_weave(&mut grid, &mut rng, 0.2);
+         println!("{} crossings", grid.under_cells().len());
+         let (width, height) = grid.grid().svg_size();
+         let mut dest = BufWriter::new(File::create("weave.svg")?);
+         svg_header(&mut dest, width as f64, height as f64)?;
+         grid.write_maze_as_svg(&mut dest)?;
+         writeln!(dest, "</svg>")?;
+      }
+      Demo::Animate => {
+         let mut grid = Grid::new(16, 8);
+         let steps = mazegen::carve_maze_steps(&mut grid, &mut rng, mazegen::Algo::RecursiveBacktracker);
+         let mut replay = Grid::new(grid.width, grid.height);
+         for step in steps {
+            match step {
+               mazegen::CarveEvent::Connect(a, b) => replay.connect_neighbors(a, b),
+               mazegen::CarveEvent::Disconnect(a, b) => replay.disconnect_neighbors(a, b),
+               _ => continue,
+            }
+            // clear the terminal and move the cursor home before drawing the next frame
+            print!("\x1b[2J\x1b[H{}", replay);
+            io::stdout().flush()?;
+            thread::sleep(Duration::from_millis(30));
+         }
+      }
    }
+   Ok(())
 }
@@ -0,0 +1,253 @@
+//! Compares `a_star` and `djikstra` with the versions they replaced, which kept the whole path to every cell on the
+//! open list and cloned it at every fork. `a_star` now keeps the parent of every cell and `djikstra` only the length of
+//! the path. Open grids fork at nearly every cell, which is where the cloning hurt the most.
+
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use maze_lib::grid::Grid;
+use maze_lib::mazegen;
+use maze_lib::pathfinding::algos;
+use maze_lib::pathfinding::heuristics::manhattan_h;
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+/// The searches as they were before they stopped carrying whole paths around
+mod path_cloning {
+   use maze_lib::graph::MazeGraph;
+   use maze_lib::grid::Grid;
+   use maze_lib::pathfinding::algos::PathData;
+   use maze_lib::pathfinding::diagnostic_map::DiagMap;
+   use std::cmp::{Ord, Ordering, PartialOrd, Reverse};
+   use std::collections::BinaryHeap;
+
+   #[derive(Clone, PartialEq, Eq)]
+   struct PriorityNode {
+      priority: usize,
+      i: usize,
+      path: Vec<usize>,
+   }
+
+   impl PartialOrd for PriorityNode {
+      fn partial_cmp(&self, other: &PriorityNode) -> Option<Ordering> {
+         Some(self.cmp(other))
+      }
+   }
+
+   impl Ord for PriorityNode {
+      fn cmp(&self, other: &PriorityNode) -> Ordering {
+         self
+            .priority
+            .cmp(&other.priority)
+            .then(self.path.len().cmp(&other.path.len()))
+      }
+   }
+
+   #[derive(Clone, PartialEq, Eq)]
+   struct Node {
+      i: usize,
+      path: Vec<usize>,
+   }
+
+   impl PartialOrd for Node {
+      fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
+         Some(self.cmp(other))
+      }
+   }
+
+   impl Ord for Node {
+      fn cmp(&self, other: &Node) -> Ordering {
+         self.path.len().cmp(&other.path.len())
+      }
+   }
+
+   pub fn a_star<G, F>(grid: &G, h: F, start: usize, goal: usize, greedy: bool) -> Option<PathData>
+   where
+      G: MazeGraph,
+      F: Fn(usize, usize) -> usize,
+   {
+      if !grid.is_enabled(start) || !grid.is_enabled(goal) {
+         return None;
+      }
+      let mut nodes_generated = 0;
+      let mut nodes_expanded = 0;
+      let mut diag_map = DiagMap::new(grid.size());
+      let mut open: BinaryHeap<Reverse<PriorityNode>> = BinaryHeap::new();
+      open.push(Reverse(PriorityNode {
+         priority: h(start, goal),
+         i: start,
+         path: vec![],
+      }));
+      let mut closed: Box<[usize]> = vec![usize::MAX; grid.size()].into_boxed_slice();
+      let mut neighbors_to_generate = Vec::with_capacity(4);
+      while let Some(Reverse(mut cur_node)) = open.pop() {
+         if cur_node.i == goal {
+            let mut final_path = cur_node.path.to_vec();
+            final_path.push(goal);
+            return Some(PathData {
+               path: final_path.into_boxed_slice(),
+               diag: diag_map.into(),
+               nodes_generated,
+               nodes_expanded,
+            });
+         }
+
+         let cur_path_len = cur_node.path.len() + 1;
+
+         if closed[cur_node.i] <= cur_node.path.len() {
+            continue;
+         }
+
+         // Expand
+         {
+            let new_path_len = cur_path_len + 1;
+            cur_node.path.reserve_exact(1);
+            cur_node.path.push(cur_node.i);
+            neighbors_to_generate.clear();
+            // fill neighbors_to_generate with our neighbors
+            grid.links(cur_node.i, &mut neighbors_to_generate);
+            neighbors_to_generate.retain(|i| closed[*i] > new_path_len);
+            // actually do expansion
+            {
+               let g = new_path_len * !greedy as usize;
+               // first, we generate every node other than the first neighbor
+               neighbors_to_generate.iter().skip(1).for_each(|i| {
+                  open.push(Reverse(PriorityNode {
+                     priority: g + h(*i, goal),
+                     i: *i,
+                     path: cur_node.path.clone(),
+                  }));
+                  nodes_generated += 1;
+                  diag_map.mark_generated(*i);
+                  closed[*i] = new_path_len;
+               });
+               // now, we generate the first neighbor
+               // the vast vast majority of cells have only one neighbor
+               // in mazes, so avoiding a clone is a huge optimization
+               if let Some(i) = neighbors_to_generate.first() {
+                  open.push(Reverse(PriorityNode {
+                     priority: g + h(*i, goal),
+                     i: *i,
+                     path: cur_node.path,
+                  }));
+                  nodes_generated += 1;
+                  diag_map.mark_generated(*i);
+                  closed[*i] = new_path_len;
+               }
+            }
+         }
+         nodes_expanded += 1;
+         diag_map.mark_expanded(cur_node.i);
+      }
+      None
+   }
+
+   /// Copied from before `djikstra` was made generic, so it still reads the walls of the `Grid` itself
+   pub fn djikstra(grid: &Grid, start: usize) -> Box<[usize]> {
+      let mut best_paths = vec![usize::MAX; grid.size()].into_boxed_slice();
+      let mut open: BinaryHeap<Reverse<Node>> = BinaryHeap::new();
+      let mut neighbors_to_generate = Vec::with_capacity(4);
+      open.push(Reverse(Node { i: start, path: vec![] }));
+      while let Some(Reverse(mut cur_node)) = open.pop() {
+         let cur_path_len = cur_node.path.len() + 1;
+         if best_paths[cur_node.i] <= cur_node.path.len() {
+            continue;
+         }
+         // expand
+         {
+            let new_path_len = cur_path_len + 1;
+            cur_node.path.reserve_exact(1);
+            cur_node.path.push(cur_node.i);
+            // wrapping sub is ok because we only use
+            // i.e. i_north when we know it is north connected
+            let i_north = cur_node.i.wrapping_sub(grid.width);
+            let i_south = cur_node.i + grid.width;
+            let i_east = cur_node.i + 1;
+            let i_west = cur_node.i.wrapping_sub(1);
+            neighbors_to_generate.clear();
+            // fill neighbors_to_generate with our neighbors
+            {
+               // N
+               if grid.cell(cur_node.i).north_connected && best_paths[i_north] > new_path_len {
+                  neighbors_to_generate.push(i_north);
+               }
+               // S
+               if grid.cell(cur_node.i).south_connected && best_paths[i_south] > new_path_len {
+                  neighbors_to_generate.push(i_south);
+               }
+               // E
+               if grid.cell(cur_node.i).east_connected && best_paths[i_east] > new_path_len {
+                  neighbors_to_generate.push(i_east);
+               }
+               // W
+               if grid.cell(cur_node.i).west_connected && best_paths[i_west] > new_path_len {
+                  neighbors_to_generate.push(i_west);
+               }
+            }
+            // actually do expansion
+            {
+               // first, we generate every node other than the first neighbor
+               neighbors_to_generate.iter().skip(1).for_each(|i| {
+                  open.push(Reverse(Node {
+                     i: *i,
+                     path: cur_node.path.clone(),
+                  }));
+                  best_paths[*i] = new_path_len;
+               });
+               // now, we generate the first neighbor
+               // the vast vast majority of cells have only one neighbor
+               // in mazes, so avoiding a clone is a huge optimization
+               if let Some(i) = neighbors_to_generate.first() {
+                  open.push(Reverse(Node {
+                     i: *i,
+                     path: cur_node.path,
+                  }));
+                  best_paths[*i] = new_path_len;
+               }
+            }
+         }
+      }
+      best_paths
+   }
+}
+
+fn open_grid(width: usize, height: usize) -> Grid {
+   let mut grid = Grid::new(width, height);
+   mazegen::empty(&mut grid);
+   grid
+}
+
+fn braided_grid(width: usize, height: usize) -> Grid {
+   let mut rng = XorShiftRng::seed_from_u64(fxhash::hash64("criterion"));
+   let mut grid = Grid::new(width, height);
+   mazegen::recursive_backtracker(&mut grid, &mut rng);
+   mazegen::braid(&mut grid, &mut rng, 1.0);
+   grid
+}
+
+fn a_star_bench(c: &mut Criterion) {
+   for (name, grid) in [("open", open_grid(100, 100)), ("braided", braided_grid(300, 300))] {
+      let h = |i, goal| manhattan_h(i, goal, grid.width);
+      let goal = grid.size() - 1;
+      let mut group = c.benchmark_group(format!("a_star {}", name));
+      group.bench_function("path cloning", |b| {
+         b.iter(|| path_cloning::a_star(&grid, h, black_box(0), black_box(goal), false))
+      });
+      group.bench_function("parent pointers", |b| {
+         b.iter(|| algos::a_star(&grid, h, black_box(0), black_box(goal), false))
+      });
+      group.finish();
+   }
+}
+
+fn djikstra_bench(c: &mut Criterion) {
+   for (name, grid) in [("open", open_grid(100, 100)), ("braided", braided_grid(300, 300))] {
+      let mut group = c.benchmark_group(format!("djikstra {}", name));
+      group.bench_function("path cloning", |b| {
+         b.iter(|| path_cloning::djikstra(&grid, black_box(0)))
+      });
+      group.bench_function("path lengths", |b| b.iter(|| algos::djikstra(&grid, black_box(0))));
+      group.finish();
+   }
+}
+
+criterion_group!(benches, a_star_bench, djikstra_bench);
+criterion_main!(benches);
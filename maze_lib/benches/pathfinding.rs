@@ -1,6 +1,7 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use maze_lib::grid::Grid;
 use maze_lib::mazegen;
+use maze_lib::pathfinding::heuristics::manhattan_h;
 use rand::SeedableRng;
 use rand_xorshift::XorShiftRng;
 
@@ -9,9 +10,10 @@ fn dfs_bench(c: &mut Criterion) {
    let mut rng = XorShiftRng::seed_from_u64(seed_u64);
    let mut grid = Grid::new(1_000, 1_000);
    mazegen::recursive_backtracker(&mut grid, &mut rng);
-   //let pf_data = maze_lib::pathfinding::algos::a_star(&grid, maze_lib::pathfinding::heuristics::manhattan_h, 0, grid.size() - 1, false).unwrap();
+   //let pf_data = maze_lib::pathfinding::algos::a_star(&grid, |i, goal| manhattan_h(i, goal, grid.width), 0, grid.size() - 1, false).unwrap();
    c.bench_function("dfs", move |b| {
-      b.iter(|| maze_lib::pathfinding::algos::dfs(&grid, black_box(0), black_box(grid.size() - 1)))
+      let h = |i, goal| manhattan_h(i, goal, grid.width);
+      b.iter(|| maze_lib::pathfinding::algos::dfs(&grid, h, black_box(0), black_box(grid.size() - 1)))
    });
 }
 
@@ -0,0 +1,349 @@
+//! Reading mazes back from the text drawn by `impl Display for Grid`.
+
+use crate::grid::{Grid, Wrap};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum ParseError {
+   Empty,
+   /// A line is not as long as the first line of the maze
+   RaggedRow {
+      line: usize,
+      expected: usize,
+      found: usize,
+   },
+   UnexpectedChar {
+      line: usize,
+      column: usize,
+      found: char,
+   },
+   /// A floor stops halfway through a row, or has a different number of rows than the first floor
+   MissingRow {
+      line: usize,
+   },
+   /// An opening in the outer wall without a matching opening on the opposite edge, or stairs without matching
+   /// stairs on the next floor
+   InconsistentWall {
+      line: usize,
+      column: usize,
+   },
+}
+
+impl Display for ParseError {
+   fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+      match self {
+         ParseError::Empty => write!(f, "maze has no cells"),
+         ParseError::RaggedRow { line, expected, found } => write!(
+            f,
+            "line {}: expected a line of {} characters, found {}",
+            line, expected, found
+         ),
+         ParseError::UnexpectedChar { line, column, found } => {
+            write!(f, "line {}, column {}: unexpected character {:?}", line, column, found)
+         }
+         ParseError::MissingRow { line } => write!(f, "line {}: floor is missing a row", line),
+         ParseError::InconsistentWall { line, column } => write!(
+            f,
+            "line {}, column {}: wall does not match the cell on the other side",
+            line, column
+         ),
+      }
+   }
+}
+
+impl Error for ParseError {}
+
+/// A line of the drawing along with its 1-based line number
+type Line = (usize, Vec<char>);
+
+impl Grid {
+   /// Reads a maze drawn in the format of `impl Display for Grid`, such as
+   ///
+   /// ```text
+   /// +---+---+---+
+   /// |       |   |
+   /// +---+   +   +
+   /// |           |
+   /// +---+---+---+
+   /// ```
+   ///
+   /// Several floors are separated by a blank line, and openings in the outer wall must be matched by an opening on
+   /// the opposite edge, which makes the grid wrap around. Masked grids can't be read back.
+   pub fn from_ascii(text: &str) -> Result<Grid, ParseError> {
+      let mut floors: Vec<Vec<Line>> = vec![Vec::new()];
+      for (line_i, line) in text.lines().enumerate() {
+         let line = line.trim_end_matches('\r');
+         if line.is_empty() {
+            if !floors.last().unwrap().is_empty() {
+               floors.push(Vec::new());
+            }
+         } else {
+            floors.last_mut().unwrap().push((line_i + 1, line.chars().collect()));
+         }
+      }
+      if floors.last().unwrap().is_empty() {
+         floors.pop();
+      }
+      let first_line = floors.first().and_then(|x| x.first()).ok_or(ParseError::Empty)?;
+      let line_len = first_line.1.len();
+      if line_len < 5 || line_len % 4 != 1 {
+         return Err(ParseError::RaggedRow {
+            line: first_line.0,
+            expected: line_len.max(5).div_ceil(4) * 4 + 1,
+            found: line_len,
+         });
+      }
+      let width = (line_len - 1) / 4;
+      let num_lines = floors[0].len();
+      let height = (num_lines - 1) / 2;
+      for floor in floors.iter() {
+         for (line, chars) in floor.iter() {
+            if chars.len() != line_len {
+               return Err(ParseError::RaggedRow {
+                  line: *line,
+                  expected: line_len,
+                  found: chars.len(),
+               });
+            }
+         }
+         if floor.len() != num_lines || num_lines.is_multiple_of(2) || height == 0 {
+            let line = floor[floor.len().min(num_lines) - 1].0;
+            return Err(ParseError::MissingRow { line });
+         }
+      }
+
+      let mut grid = Grid::new_3d(width, height, floors.len());
+      let mut wrap = Wrap::NONE;
+      for (floor_i, floor) in floors.iter().enumerate() {
+         let start = floor_i * grid.floor_size();
+         // horizontal walls, from the north edge down to the south edge
+         let mut north_edge = Vec::with_capacity(width);
+         for (row, (line, chars)) in floor.iter().step_by(2).enumerate() {
+            for col in 0..width {
+               expect(*line, chars, col * 4, &['+'])?;
+               let open = wall(*line, chars, col * 4 + 1, '-')?;
+               if row == 0 {
+                  north_edge.push(open);
+               } else if row == height {
+                  if open != north_edge[col] {
+                     return Err(ParseError::InconsistentWall {
+                        line: *line,
+                        column: col * 4 + 2,
+                     });
+                  }
+                  if open {
+                     wrap.north_south = true;
+                     grid.connect_cell_south(start + (height - 1) * width + col);
+                  }
+               } else if open {
+                  grid.connect_cell_south(start + (row - 1) * width + col);
+               }
+            }
+            expect(*line, chars, width * 4, &['+'])?;
+         }
+         // vertical walls and stairs
+         for (row, (line, chars)) in floor.iter().skip(1).step_by(2).enumerate() {
+            let west_open = wall(*line, chars, 0, '|')?;
+            for col in 0..width {
+               let i = start + row * width + col;
+               let (up, down) = match chars[col * 4 + 1..col * 4 + 4] {
+                  [' ', ' ', ' '] => (false, false),
+                  [' ', '^', ' '] => (true, false),
+                  [' ', 'v', ' '] => (false, true),
+                  ['^', ' ', 'v'] => (true, true),
+                  _ => {
+                     let offset = chars[col * 4 + 1..col * 4 + 4]
+                        .iter()
+                        .position(|x| !matches!(x, ' ' | '^' | 'v'))
+                        .unwrap_or(0);
+                     return Err(ParseError::UnexpectedChar {
+                        line: *line,
+                        column: col * 4 + 2 + offset,
+                        found: chars[col * 4 + 1 + offset],
+                     });
+                  }
+               };
+               if up {
+                  let above = floors.get(floor_i + 1).map(|x| &x[row * 2 + 1].1);
+                  if !above.is_some_and(|x| matches!(x[col * 4 + 1..col * 4 + 4], [' ', 'v', ' '] | ['^', ' ', 'v'])) {
+                     return Err(ParseError::InconsistentWall {
+                        line: *line,
+                        column: col * 4 + 2,
+                     });
+                  }
+                  grid.connect_cell_up(i);
+               }
+               if down {
+                  let below = floor_i.checked_sub(1).map(|x| &floors[x][row * 2 + 1].1);
+                  if !below.is_some_and(|x| matches!(x[col * 4 + 1..col * 4 + 4], [' ', '^', ' '] | ['^', ' ', 'v'])) {
+                     return Err(ParseError::InconsistentWall {
+                        line: *line,
+                        column: col * 4 + 2,
+                     });
+                  }
+               }
+
+               let east_open = wall(*line, chars, col * 4 + 4, '|')?;
+               if col + 1 < width {
+                  if east_open {
+                     grid.connect_cell_east(i);
+                  }
+               } else {
+                  if east_open != west_open {
+                     return Err(ParseError::InconsistentWall {
+                        line: *line,
+                        column: width * 4 + 1,
+                     });
+                  }
+                  if east_open {
+                     wrap.east_west = true;
+                     grid.connect_cell_east(i);
+                  }
+               }
+            }
+         }
+      }
+      if (wrap.east_west && width < 3) || (wrap.north_south && height < 3) {
+         // too small to wrap, so the openings lead nowhere
+         return Err(ParseError::InconsistentWall { line: 1, column: 1 });
+      }
+      grid.wrap = wrap;
+      Ok(grid)
+   }
+}
+
+impl FromStr for Grid {
+   type Err = ParseError;
+
+   fn from_str(s: &str) -> Result<Grid, ParseError> {
+      Grid::from_ascii(s)
+   }
+}
+
+fn expect(line: usize, chars: &[char], pos: usize, allowed: &[char]) -> Result<(), ParseError> {
+   if allowed.contains(&chars[pos]) {
+      Ok(())
+   } else {
+      Err(ParseError::UnexpectedChar {
+         line,
+         column: pos + 1,
+         found: chars[pos],
+      })
+   }
+}
+
+/// Whether there is an opening in the wall at `pos`, which is drawn with one character for a vertical wall and three
+/// for a horizontal wall
+fn wall(line: usize, chars: &[char], pos: usize, wall_char: char) -> Result<bool, ParseError> {
+   let len = if wall_char == '-' { 3 } else { 1 };
+   let open = chars[pos] == ' ';
+   let expected = if open { ' ' } else { wall_char };
+   for offset in 0..len {
+      expect(line, chars, pos + offset, &[expected])?;
+   }
+   Ok(open)
+}
+
+#[cfg(test)]
+mod test {
+   use super::ParseError;
+   use crate::grid::{Grid, Wrap};
+   use crate::mazegen;
+   use crate::pathfinding::algos;
+   use crate::pathfinding::heuristics::manhattan_h;
+   use rand::SeedableRng;
+   use rand_xorshift::XorShiftRng;
+
+   #[test]
+   fn round_trips() {
+      let mut rng = XorShiftRng::seed_from_u64(13);
+      for mut grid in [
+         Grid::new(7, 5),
+         Grid::new(1, 1),
+         Grid::new_3d(4, 3, 3),
+         Grid::new_wrapping(5, 4, Wrap::TORUS),
+      ] {
+         mazegen::wilson(&mut grid, &mut rng);
+         mazegen::braid(&mut grid, &mut rng, 0.5);
+         let text = grid.to_string();
+         let parsed: Grid = text.parse().unwrap();
+         assert_eq!(
+            (parsed.width, parsed.height, parsed.depth),
+            (grid.width, grid.height, grid.depth)
+         );
+         assert_eq!(parsed.to_string(), text);
+         for i in 0..grid.size() {
+            for j in 0..grid.size() {
+               assert_eq!(
+                  parsed.check_if_neighbors_and_connected(i, j),
+                  grid.check_if_neighbors_and_connected(i, j)
+               );
+            }
+         }
+      }
+   }
+
+   #[test]
+   fn reports_position_of_errors() {
+      assert!(matches!(Grid::from_ascii("\n\n"), Err(ParseError::Empty)));
+      assert!(matches!(
+         Grid::from_ascii("+---+---+\n|       |\n+---+--+\n"),
+         Err(ParseError::RaggedRow {
+            line: 3,
+            expected: 9,
+            found: 8
+         })
+      ));
+      assert!(matches!(
+         Grid::from_ascii("+---+---+\n|   *   |\n+---+---+\n"),
+         Err(ParseError::UnexpectedChar {
+            line: 2,
+            column: 5,
+            found: '*'
+         })
+      ));
+      assert!(matches!(
+         Grid::from_ascii("+---+---+\n|   |   |\n+- -+---+\n"),
+         Err(ParseError::UnexpectedChar {
+            line: 3,
+            column: 3,
+            found: ' '
+         })
+      ));
+      assert!(matches!(
+         Grid::from_ascii("+---+---+\n|   |   |\n+---+---+\n|   |   |\n"),
+         Err(ParseError::MissingRow { line: 4 })
+      ));
+      // an opening in the south wall without one in the north wall
+      assert!(matches!(
+         Grid::from_ascii("+---+---+---+\n|           |\n+---+   +---+\n"),
+         Err(ParseError::InconsistentWall { line: 3, column: 6 })
+      ));
+      // stairs up from the top floor
+      assert!(matches!(
+         Grid::from_ascii("+---+\n| ^ |\n+---+\n"),
+         Err(ParseError::InconsistentWall { line: 2, column: 2 })
+      ));
+   }
+
+   #[test]
+   fn hand_drawn_maze_can_be_solved() {
+      let grid = Grid::from_ascii(
+         "\
++---+---+---+---+
+|       |       |
++---+   +   +   +
+|       |   |   |
++   +---+   +   +
+|           |   |
++---+---+---+---+
+",
+      )
+      .unwrap();
+      let h = |i, goal| manhattan_h(i, goal, grid.width);
+      let pf_data = algos::a_star(&grid, h, 0, 3, false).unwrap();
+      assert_eq!(*pf_data.path, [0, 1, 5, 4, 8, 9, 10, 6, 2, 3]);
+   }
+}
@@ -0,0 +1,33 @@
+/// A fixed number of bits, packed 64 to a word
+pub(crate) struct BitSet {
+   words: Box<[u64]>,
+}
+
+impl BitSet {
+   pub fn new(len: usize) -> BitSet {
+      BitSet {
+         words: vec![0; len.div_ceil(64)].into_boxed_slice(),
+      }
+   }
+
+   #[inline]
+   pub fn get(&self, i: usize) -> bool {
+      self.words[i / 64] & (1 << (i % 64)) != 0
+   }
+
+   #[inline]
+   pub fn insert(&mut self, i: usize) {
+      self.words[i / 64] |= 1 << (i % 64);
+   }
+
+   #[inline]
+   pub fn remove(&mut self, i: usize) {
+      self.words[i / 64] &= !(1 << (i % 64));
+   }
+
+   pub fn clear(&mut self) {
+      for x in self.words.iter_mut() {
+         *x = 0;
+      }
+   }
+}
@@ -0,0 +1,415 @@
+//! Maze codes: short strings that pin down everything needed to carve a maze again, so a maze can be shared by
+//! passing its code around.
+//!
+//! A code packs the format version, the algorithm, the dimensions, which edges wrap, how much to braid and the seed
+//! into a few bytes, followed by a check byte. The bytes are written in Crockford's base 32 and split into groups of
+//! four, like `05BM-40M0-...`. Reading a code ignores case and dashes, and takes `O` for `0` and `I` or `L` for `1`.
+//!
+//! Carving is deterministic: the same code carves the same maze with every front end and on every platform.
+
+use crate::grid::{Grid, Wrap};
+use crate::mazegen::{self, Algo, CarveObserver, CellSelection};
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+pub const CODE_VERSION: u8 = 1;
+
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+const FLAG_WRAP_EAST_WEST: u8 = 1 << 0;
+const FLAG_WRAP_NORTH_SOUTH: u8 = 1 << 1;
+
+/// The numbers algorithms are stored as. Growing Tree is followed by the four weights of its cell selection.
+const ALGO_IDS: [(u8, Algo); 12] = [
+   (0, Algo::BinaryTree),
+   (1, Algo::Sidewinder),
+   (2, Algo::AldousBroder),
+   (3, Algo::Wilson),
+   (4, Algo::HuntAndKill),
+   (5, Algo::RecursiveBacktracker),
+   (6, Algo::Kruskal),
+   (7, Algo::Eller),
+   (8, Algo::RecursiveDivision),
+   (9, Algo::PrimSimplified),
+   (10, Algo::PrimTrue),
+   (12, Algo::Empty),
+];
+const GROWING_TREE_ID: u8 = 11;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CodeError {
+   /// A character that isn't part of the alphabet, at a 0-based position in the code
+   BadChar {
+      position: usize,
+      found: char,
+   },
+   Truncated,
+   TrailingData,
+   /// The code was mistyped, or made up
+   BadChecksum,
+   UnsupportedVersion(u8),
+   UnknownAlgorithm(u8),
+   /// A dimension of zero, a maze too big to address, or a wrapped edge less than 3 cells long
+   BadDimensions,
+   /// A braid chance over 100 percent
+   BadBraid(u8),
+}
+
+impl Display for CodeError {
+   fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+      match self {
+         CodeError::BadChar { position, found } => {
+            write!(
+               f,
+               "unexpected character {:?} at position {} of maze code",
+               found,
+               position + 1
+            )
+         }
+         CodeError::Truncated => write!(f, "maze code is too short"),
+         CodeError::TrailingData => write!(f, "maze code is too long"),
+         CodeError::BadChecksum => write!(f, "maze code is mistyped"),
+         CodeError::UnsupportedVersion(version) => write!(
+            f,
+            "maze code has version {}, but only versions up to {} are supported",
+            version, CODE_VERSION
+         ),
+         CodeError::UnknownAlgorithm(id) => write!(f, "maze code has unknown algorithm {}", id),
+         CodeError::BadDimensions => write!(f, "maze code has invalid dimensions"),
+         CodeError::BadBraid(percent) => write!(f, "maze code braids {}% of dead-ends", percent),
+      }
+   }
+}
+
+impl Error for CodeError {}
+
+/// The seed for a maze named by some text, as typed into the seed box of the web front end or given to `--seed`
+pub fn seed_from_text(text: &str) -> u64 {
+   fxhash::hash64(text)
+}
+
+/// Everything needed to carve a maze
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MazeCode {
+   pub algo: Algo,
+   pub width: usize,
+   pub height: usize,
+   pub depth: usize,
+   pub wrap: Wrap,
+   /// The chance, in percent, that `mazegen::braid` removes each dead-end after carving
+   pub braid_percent: u8,
+   pub seed: u64,
+}
+
+impl MazeCode {
+   /// A single floor that doesn't wrap and isn't braided
+   pub fn new(algo: Algo, width: usize, height: usize, seed: u64) -> MazeCode {
+      MazeCode {
+         algo,
+         width,
+         height,
+         depth: 1,
+         wrap: Wrap::NONE,
+         braid_percent: 0,
+         seed,
+      }
+   }
+
+   /// Whether a grid can be made with these dimensions and wrapping, and the braid chance makes sense
+   pub fn check(&self) -> Result<(), CodeError> {
+      let size = self
+         .width
+         .checked_mul(self.height)
+         .and_then(|x| x.checked_mul(self.depth))
+         .ok_or(CodeError::BadDimensions)?;
+      if size == 0 || (self.wrap.east_west && self.width < 3) || (self.wrap.north_south && self.height < 3) {
+         return Err(CodeError::BadDimensions);
+      }
+      if self.braid_percent > 100 {
+         return Err(CodeError::BadBraid(self.braid_percent));
+      }
+      Ok(())
+   }
+
+   /// The random number generator that carving starts from
+   pub fn rng(&self) -> XorShiftRng {
+      XorShiftRng::seed_from_u64(self.seed)
+   }
+
+   pub fn carve(&self) -> Grid {
+      self.carve_observed(&mut ())
+   }
+
+   /// Carves the maze like `carve` would, passing every step the algorithm takes to `obs`. Braiding isn't observed.
+   pub fn carve_observed<O: CarveObserver>(&self, obs: &mut O) -> Grid {
+      let mut grid = Grid::new_3d(self.width, self.height, self.depth);
+      grid.wrap = self.wrap;
+      let mut rng = self.rng();
+      mazegen::carve_maze_observed(&mut grid, &mut rng, self.algo, obs);
+      if self.braid_percent > 0 {
+         mazegen::braid(&mut grid, &mut rng, f64::from(self.braid_percent) / 100.0);
+      }
+      grid
+   }
+
+   pub fn encode(&self) -> String {
+      let mut bytes = vec![CODE_VERSION];
+      match self.algo {
+         Algo::GrowingTree(selection) => bytes.extend_from_slice(&[
+            GROWING_TREE_ID,
+            selection.newest,
+            selection.oldest,
+            selection.random,
+            selection.middle,
+         ]),
+         algo => bytes.push(ALGO_IDS.iter().find(|x| x.1 == algo).unwrap().0),
+      }
+      for x in [self.width, self.height, self.depth] {
+         write_varint(&mut bytes, x as u64);
+      }
+      let mut flags = 0;
+      if self.wrap.east_west {
+         flags |= FLAG_WRAP_EAST_WEST;
+      }
+      if self.wrap.north_south {
+         flags |= FLAG_WRAP_NORTH_SOUTH;
+      }
+      bytes.push(flags);
+      bytes.push(self.braid_percent);
+      bytes.extend_from_slice(&self.seed.to_le_bytes());
+      bytes.push(check_byte(&bytes));
+
+      let mut code = String::new();
+      let mut bits = 0u32;
+      let mut bit_count = 0;
+      let push_char = |code: &mut String, x: u32| {
+         if code.len() % 5 == 4 {
+            code.push('-');
+         }
+         code.push(char::from(ALPHABET[x as usize & 31]));
+      };
+      for b in bytes {
+         bits = (bits << 8) | u32::from(b);
+         bit_count += 8;
+         while bit_count >= 5 {
+            bit_count -= 5;
+            push_char(&mut code, bits >> bit_count);
+         }
+      }
+      if bit_count > 0 {
+         push_char(&mut code, bits << (5 - bit_count));
+      }
+      code
+   }
+
+   pub fn decode(code: &str) -> Result<MazeCode, CodeError> {
+      let mut bytes = Vec::with_capacity(code.len() * 5 / 8);
+      let mut bits = 0u32;
+      let mut bit_count = 0;
+      for (position, c) in code.trim().chars().enumerate() {
+         let c = match c.to_ascii_uppercase() {
+            '-' => continue,
+            'O' => '0',
+            'I' | 'L' => '1',
+            c => c,
+         };
+         let value = ALPHABET
+            .iter()
+            .position(|x| char::from(*x) == c)
+            .ok_or(CodeError::BadChar { position, found: c })?;
+         bits = (bits << 5) | value as u32;
+         bit_count += 5;
+         if bit_count >= 8 {
+            bit_count -= 8;
+            bytes.push((bits >> bit_count) as u8);
+         }
+      }
+      let (check, bytes) = bytes.split_last().ok_or(CodeError::Truncated)?;
+      if *check != check_byte(bytes) {
+         return Err(CodeError::BadChecksum);
+      }
+
+      let mut reader = Reader { bytes };
+      let version = reader.u8()?;
+      if version == 0 || version > CODE_VERSION {
+         return Err(CodeError::UnsupportedVersion(version));
+      }
+      let algo = match reader.u8()? {
+         GROWING_TREE_ID => Algo::GrowingTree(CellSelection {
+            newest: reader.u8()?,
+            oldest: reader.u8()?,
+            random: reader.u8()?,
+            middle: reader.u8()?,
+         }),
+         id => {
+            ALGO_IDS
+               .iter()
+               .find(|x| x.0 == id)
+               .ok_or(CodeError::UnknownAlgorithm(id))?
+               .1
+         }
+      };
+      let mut dimension = || usize::try_from(reader.varint()?).map_err(|_| CodeError::BadDimensions);
+      let (width, height, depth) = (dimension()?, dimension()?, dimension()?);
+      let flags = reader.u8()?;
+      let braid_percent = reader.u8()?;
+      let seed = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+      if !reader.bytes.is_empty() {
+         return Err(CodeError::TrailingData);
+      }
+      let code = MazeCode {
+         algo,
+         width,
+         height,
+         depth,
+         wrap: Wrap {
+            east_west: flags & FLAG_WRAP_EAST_WEST != 0,
+            north_south: flags & FLAG_WRAP_NORTH_SOUTH != 0,
+         },
+         braid_percent,
+         seed,
+      };
+      code.check()?;
+      Ok(code)
+   }
+}
+
+impl Display for MazeCode {
+   fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+      f.write_str(&self.encode())
+   }
+}
+
+impl FromStr for MazeCode {
+   type Err = CodeError;
+
+   fn from_str(s: &str) -> Result<MazeCode, CodeError> {
+      MazeCode::decode(s)
+   }
+}
+
+/// Catches a mistyped character, or two characters swapped
+fn check_byte(bytes: &[u8]) -> u8 {
+   bytes
+      .iter()
+      .enumerate()
+      .fold(0x5a, |acc: u8, (i, b)| acc.rotate_left(3) ^ b.wrapping_add(i as u8))
+}
+
+/// 7 bits at a time, least significant first, with the top bit set on every byte but the last
+fn write_varint(bytes: &mut Vec<u8>, mut x: u64) {
+   while x >= 0x80 {
+      bytes.push(x as u8 | 0x80);
+      x >>= 7;
+   }
+   bytes.push(x as u8);
+}
+
+struct Reader<'a> {
+   bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+   fn take(&mut self, len: usize) -> Result<&'a [u8], CodeError> {
+      if self.bytes.len() < len {
+         return Err(CodeError::Truncated);
+      }
+      let (taken, rest) = self.bytes.split_at(len);
+      self.bytes = rest;
+      Ok(taken)
+   }
+
+   fn u8(&mut self) -> Result<u8, CodeError> {
+      Ok(self.take(1)?[0])
+   }
+
+   fn varint(&mut self) -> Result<u64, CodeError> {
+      let mut x = 0u64;
+      for shift in (0..64).step_by(7) {
+         let b = self.u8()?;
+         x |= u64::from(b & 0x7f) << shift;
+         if b & 0x80 == 0 {
+            return Ok(x);
+         }
+      }
+      Err(CodeError::BadDimensions)
+   }
+}
+
+#[cfg(test)]
+mod test {
+   use super::{CodeError, MazeCode};
+   use crate::grid::Wrap;
+   use crate::mazegen::{ALGOS, Algo, CellSelection};
+
+   #[test]
+   fn round_trips() {
+      let mut codes: Vec<MazeCode> = ALGOS
+         .iter()
+         .map(|algo| MazeCode::new(*algo, 12, 7, super::seed_from_text("round trip")))
+         .collect();
+      codes.push(MazeCode {
+         algo: Algo::GrowingTree(CellSelection {
+            newest: 3,
+            oldest: 0,
+            random: 1,
+            middle: 0,
+         }),
+         width: 300,
+         height: 20_000,
+         depth: 4,
+         wrap: Wrap::TORUS,
+         braid_percent: 50,
+         seed: u64::MAX,
+      });
+      for code in codes {
+         let text = code.to_string();
+         assert_eq!(text.parse::<MazeCode>(), Ok(code));
+         assert_eq!(MazeCode::decode(&text.to_lowercase().replace('-', "")), Ok(code));
+      }
+   }
+
+   #[test]
+   fn same_code_same_maze() {
+      let code = MazeCode {
+         wrap: Wrap::CYLINDER,
+         braid_percent: 30,
+         ..MazeCode::new(Algo::Wilson, 10, 6, 42)
+      };
+      let decoded = MazeCode::decode(&code.encode()).unwrap();
+      assert_eq!(decoded.carve().to_string(), code.carve().to_string());
+      let other_seed = MazeCode { seed: 43, ..code };
+      assert_ne!(other_seed.carve().to_string(), code.carve().to_string());
+   }
+
+   #[test]
+   fn rejects_bad_codes() {
+      let code = MazeCode::new(Algo::Kruskal, 5, 5, 7).encode();
+      // one character changed
+      let mut chars: Vec<char> = code.chars().collect();
+      chars[5] = if chars[5] == 'Z' { 'Y' } else { 'Z' };
+      let mistyped: String = chars.into_iter().collect();
+      assert_eq!(MazeCode::decode(&mistyped), Err(CodeError::BadChecksum));
+      assert_eq!(
+         MazeCode::decode("05Q!"),
+         Err(CodeError::BadChar {
+            position: 3,
+            found: '!'
+         })
+      );
+      assert_eq!(MazeCode::decode(""), Err(CodeError::Truncated));
+      assert_eq!(MazeCode::decode(&code[..code.len() - 4]), Err(CodeError::BadChecksum));
+      assert_eq!(
+         MazeCode::decode(&MazeCode::new(Algo::Kruskal, 0, 5, 7).encode()),
+         Err(CodeError::BadDimensions)
+      );
+      let torus = MazeCode {
+         wrap: Wrap::TORUS,
+         ..MazeCode::new(Algo::Kruskal, 2, 5, 7)
+      };
+      assert_eq!(MazeCode::decode(&torus.encode()), Err(CodeError::BadDimensions));
+   }
+}
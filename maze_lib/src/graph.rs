@@ -0,0 +1,139 @@
+use crate::grid::Grid;
+
+/// Any arrangement of cells that a maze can be carved into, independent of the shape of the cells.
+///
+/// Cells are identified by their index, from `0` up to (but not including) `size()`.
+pub trait MazeGraph {
+   /// The number of cells, including any that are not part of the maze
+   fn size(&self) -> usize;
+
+   /// Pushes every cell that shares a wall with the given cell onto `buf`, whether or not there is a passage
+   fn neighbors(&self, index: usize, buf: &mut Vec<usize>);
+
+   /// Carves a passage between two neighboring cells
+   fn link(&mut self, i1: usize, i2: usize);
+
+   /// Puts back the wall between two neighboring cells
+   fn unlink(&mut self, i1: usize, i2: usize);
+
+   /// Whether there is a passage between the two cells. Cells that are not neighbors are never linked
+   fn is_linked(&self, i1: usize, i2: usize) -> bool;
+
+   /// Pushes every cell that has a passage to the given cell onto `buf`
+   fn links(&self, index: usize, buf: &mut Vec<usize>) {
+      let already_in_buf = buf.len();
+      self.neighbors(index, buf);
+      // `retain` goes through `buf` in order, so this only ever drops the neighbors just pushed
+      let mut seen = 0;
+      buf.retain(|x| {
+         seen += 1;
+         seen <= already_in_buf || self.is_linked(index, *x)
+      });
+   }
+
+   /// Whether the cell is part of the maze
+   fn is_enabled(&self, _index: usize) -> bool {
+      true
+   }
+
+   /// The number of cells that are part of the maze
+   fn num_enabled(&self) -> usize {
+      self.size()
+   }
+}
+
+impl MazeGraph for Grid {
+   fn size(&self) -> usize {
+      Grid::size(self)
+   }
+
+   fn neighbors(&self, index: usize, buf: &mut Vec<usize>) {
+      Grid::neighbors(self, index, buf)
+   }
+
+   fn link(&mut self, i1: usize, i2: usize) {
+      self.connect_neighbors(i1, i2)
+   }
+
+   fn unlink(&mut self, i1: usize, i2: usize) {
+      self.disconnect_neighbors(i1, i2)
+   }
+
+   fn is_linked(&self, i1: usize, i2: usize) -> bool {
+      self.check_if_neighbors_and_connected(i1, i2)
+   }
+
+   fn links(&self, index: usize, buf: &mut Vec<usize>) {
+      let cell = self.cell(index);
+      if cell.north_connected {
+         buf.push(self.north(index));
+      }
+      if cell.south_connected {
+         buf.push(self.south(index));
+      }
+      if cell.east_connected {
+         buf.push(self.east(index));
+      }
+      if cell.west_connected {
+         buf.push(self.west(index));
+      }
+      if cell.up_connected {
+         buf.push(self.up(index));
+      }
+      if cell.down_connected {
+         buf.push(self.down(index));
+      }
+   }
+
+   fn is_enabled(&self, index: usize) -> bool {
+      Grid::is_enabled(self, index)
+   }
+
+   fn num_enabled(&self) -> usize {
+      Grid::num_enabled(self)
+   }
+}
+
+/// Asserts that the maze carved into the graph is perfect: a spanning tree of the enabled cells, where every one of
+/// them can be reached and there are exactly `num_enabled - 1` passages
+#[cfg(test)]
+pub(crate) fn assert_perfect<G: MazeGraph>(graph: &G) {
+   let mut passages = 0;
+   let mut seen: Vec<bool> = (0..graph.size()).map(|i| !graph.is_enabled(i)).collect();
+   let start = seen.iter().position(|x| !x).unwrap();
+   let mut stack = vec![start];
+   let mut links = Vec::new();
+   seen[start] = true;
+   while let Some(i) = stack.pop() {
+      links.clear();
+      graph.links(i, &mut links);
+      for n in links.iter().copied() {
+         assert!(graph.is_linked(i, n) && graph.is_linked(n, i));
+         passages += 1;
+         if !seen[n] {
+            seen[n] = true;
+            stack.push(n);
+         }
+      }
+   }
+   assert!(seen.iter().all(|x| *x));
+   // every passage was counted from both sides
+   assert_eq!(passages / 2, graph.num_enabled() - 1);
+}
+
+#[cfg(test)]
+mod test {
+   use super::MazeGraph;
+   use crate::hex::HexGrid;
+
+   #[test]
+   fn links_are_added_after_what_is_in_the_buffer() {
+      let mut g = HexGrid::new(4, 4);
+      let mut neighbors = Vec::new();
+      g.neighbors(5, &mut neighbors);
+      g.link(5, neighbors[0]);
+      let mut buf = vec![100, 101];
+      g.links(5, &mut buf);
+      assert_eq!(buf, [100, 101, neighbors[0]]);
+   }
+}
@@ -1,62 +1,157 @@
+use crate::bit_set::BitSet;
+use crate::mask::Mask;
 use std::fmt::{self, Display, Formatter};
 use std::io::{self, Write};
-use std::ops::{Index, IndexMut};
 
+/// The connections of a single cell of a `Grid`, as returned by `Grid::cell`
 #[derive(Copy, Clone, Default)]
 pub struct Cell {
    pub north_connected: bool,
    pub south_connected: bool,
    pub east_connected: bool,
    pub west_connected: bool,
+   /// Connected to the cell at the same position on the floor above
+   pub up_connected: bool,
+   /// Connected to the cell at the same position on the floor below
+   pub down_connected: bool,
 }
 
 impl Cell {
    pub fn num_connections(self) -> u8 {
-      self.north_connected as u8 + self.south_connected as u8 + self.east_connected as u8 + self.west_connected as u8
+      self.north_connected as u8
+         + self.south_connected as u8
+         + self.east_connected as u8
+         + self.west_connected as u8
+         + self.up_connected as u8
+         + self.down_connected as u8
    }
 }
 
+/// Which edges of a `Grid` wrap around to the opposite edge
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Wrap {
+   /// The east edge wraps around to the west edge, rolling the grid into a cylinder
+   pub east_west: bool,
+   /// The south edge wraps around to the north edge. Together with `east_west`, this makes a torus
+   pub north_south: bool,
+}
+
+impl Wrap {
+   pub const NONE: Wrap = Wrap {
+      east_west: false,
+      north_south: false,
+   };
+   pub const CYLINDER: Wrap = Wrap {
+      east_west: true,
+      north_south: false,
+   };
+   pub const TORUS: Wrap = Wrap {
+      east_west: true,
+      north_south: true,
+   };
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Direction {
+   North,
+   South,
+   East,
+   West,
+   Up,
+   Down,
+}
+
+impl Direction {
+   /// The directions within a floor
+   pub(crate) const ALL: [Direction; 4] = [Direction::North, Direction::South, Direction::East, Direction::West];
+
+   pub(crate) fn is_vertical(self) -> bool {
+      matches!(self, Direction::North | Direction::South)
+   }
+}
+
+/// A rectangular grid of cells, optionally stacked into several floors.
+///
+/// Cells are indexed row by row, and floor by floor starting from the bottom one: the cell at column `col`, row `row`
+/// of floor `floor` has index `(floor * height + row) * width + col`.
+///
+/// Only the south, east and up connections are stored, one bit each, and the north, west and down connections are
+/// read off the neighboring cell. A flat grid costs 2 bits per cell.
 pub struct Grid {
-   pub inner: Box<[Cell]>,
+   south: BitSet,
+   east: BitSet,
+   /// Not stored for the top floor, which has nothing above it
+   up: BitSet,
    pub width: usize,
    pub height: usize,
+   /// The number of floors
+   pub depth: usize,
+   /// Cells that are masked off are not part of the maze
+   pub mask: Option<Mask>,
+   pub wrap: Wrap,
 }
 
 impl Display for Grid {
    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+      // floors are drawn one after another, from the bottom one up
+      for floor in 0..self.depth {
+         if floor > 0 {
+            writeln!(f)?;
+         }
+         self.fmt_floor(f, floor)?;
+      }
+      Ok(())
+   }
+}
+
+impl Grid {
+   fn fmt_floor(&self, f: &mut Formatter, floor: usize) -> fmt::Result {
+      let start = floor * self.floor_size();
       // top
-      write!(f, "+")?;
-      for _ in 0..self.width {
-         write!(f, "---+")?;
+      f.write_str(self.corner(0, 0))?;
+      for col in 0..self.width {
+         if self.is_enabled(start + col) && !self.cell(start + col).north_connected {
+            write!(f, "---")?;
+         } else {
+            write!(f, "   ")?;
+         }
+         f.write_str(self.corner(0, col + 1))?;
       }
       writeln!(f)?;
 
       let mut top_buf = String::with_capacity((self.width * 3) + 1);
       let mut bot_buf = String::with_capacity((self.width * 3) + 1);
-      top_buf.push('|');
-      bot_buf.push('+');
-      for (i, cell) in self.inner.iter().enumerate() {
-         if cell.east_connected {
-            top_buf.push_str("    ");
-         } else {
-            top_buf.push_str("   |");
+      for i in start..(start + self.floor_size()) {
+         let row = (i - start) / self.width;
+         let col = i % self.width;
+
+         // start of row
+         if col == 0 {
+            top_buf.push(if self.is_enabled(i) && !self.cell(i).west_connected {
+               '|'
+            } else {
+               ' '
+            });
+            bot_buf.push_str(self.corner(row + 1, 0));
          }
-         if cell.south_connected {
-            bot_buf.push_str("   +")
+
+         top_buf.push_str(self.stairs(i));
+         top_buf.push(if self.has_east_wall(i) { '|' } else { ' ' });
+         if self.has_south_wall(i) {
+            bot_buf.push_str("---");
          } else {
-            bot_buf.push_str("---+")
+            bot_buf.push_str("   ");
          }
+         bot_buf.push_str(self.corner(row + 1, col + 1));
 
          // end of row
-         if (i + 1) % self.width == 0 {
+         if col + 1 == self.width {
             f.write_str(&top_buf)?;
             writeln!(f)?;
             f.write_str(&bot_buf)?;
             writeln!(f)?;
             top_buf.clear();
             bot_buf.clear();
-            top_buf.push('|');
-            bot_buf.push('+');
          }
       }
 
@@ -64,161 +159,408 @@ impl Display for Grid {
    }
 }
 
-impl Index<usize> for Grid {
-   type Output = Cell;
-
-   fn index(&self, index: usize) -> &Cell {
-      &self.inner[index]
-   }
-}
-
-impl IndexMut<usize> for Grid {
-   fn index_mut(&mut self, index: usize) -> &mut Cell {
-      &mut self.inner[index]
-   }
-}
-
 impl Grid {
    pub fn new(width: usize, height: usize) -> Grid {
+      Grid::new_3d(width, height, 1)
+   }
+
+   /// A grid of several floors, each `width` by `height` cells, where cells are also neighbors of the cells directly
+   /// above and below them
+   pub fn new_3d(width: usize, height: usize, depth: usize) -> Grid {
       Grid {
-         inner: vec![Cell::default(); width * height].into_boxed_slice(),
+         south: BitSet::new(width * height * depth),
+         east: BitSet::new(width * height * depth),
+         up: BitSet::new(width * height * depth.saturating_sub(1)),
          width,
          height,
+         depth,
+         mask: None,
+         wrap: Wrap::NONE,
       }
    }
 
-   pub fn reset(&mut self) {
-      for x in self.inner.iter_mut() {
-         *x = Cell::default();
+   /// A grid where cells along the wrapped edges are neighbors of the cells on the opposite edge.
+   /// Wrapped edges must be at least 3 cells apart, so that no two cells are neighbors twice over.
+   pub fn new_wrapping(width: usize, height: usize, wrap: Wrap) -> Grid {
+      assert!(!wrap.east_west || width >= 3);
+      assert!(!wrap.north_south || height >= 3);
+      let mut grid = Grid::new(width, height);
+      grid.wrap = wrap;
+      grid
+   }
+
+   /// A grid the size of the mask, where only the enabled cells of the mask are part of the maze
+   pub fn new_masked(mask: Mask) -> Grid {
+      let mut grid = Grid::new(mask.width, mask.height);
+      grid.mask = Some(mask);
+      grid
+   }
+
+   /// The mask (if any) applies to every floor alike
+   pub fn is_enabled(&self, index: usize) -> bool {
+      match self.mask {
+         None => true,
+         Some(ref mask) => mask.is_enabled(index % self.floor_size()),
       }
    }
 
-   pub fn dead_ends(&self) -> impl Iterator<Item = &Cell> {
-      self.inner.iter().filter(|x| x.num_connections() == 1)
+   /// The number of cells that are part of the maze
+   pub fn num_enabled(&self) -> usize {
+      match self.mask {
+         None => self.size(),
+         Some(ref mask) => mask.num_enabled() * self.depth,
+      }
    }
 
-   pub fn get(&mut self, index: usize) -> Option<&Cell> {
-      self.inner.get(index)
+   /// The number of cells on each floor
+   pub fn floor_size(&self) -> usize {
+      self.width * self.height
    }
 
-   pub fn get_mut(&mut self, index: usize) -> Option<&mut Cell> {
-      self.inner.get_mut(index)
+   /// The floor the cell is on, counting up from 0
+   pub fn floor(&self, index: usize) -> usize {
+      index / self.floor_size()
    }
 
-   pub fn check_if_neighbors_and_connected(&self, i1: usize, i2: usize) -> bool {
-      if i2 + self.width == i1 {
-         self.inner[i1].north_connected
-      } else if i2 == i1 + self.width {
-         self.inner[i1].south_connected
-      } else if i2 == i1 + 1 {
-         self.inner[i1].east_connected
-      } else if i2 + 1 == i1 {
-         self.inner[i1].west_connected
+   fn on_north_edge(&self, index: usize) -> bool {
+      index % self.floor_size() < self.width
+   }
+
+   fn on_south_edge(&self, index: usize) -> bool {
+      index % self.floor_size() >= (self.width * (self.height - 1))
+   }
+
+   pub fn reset(&mut self) {
+      self.south.clear();
+      self.east.clear();
+      self.up.clear();
+   }
+
+   pub fn dead_ends(&self) -> impl Iterator<Item = Cell> + '_ {
+      (0..self.size())
+         .map(|i| self.cell(i))
+         .filter(|x| x.num_connections() == 1)
+   }
+
+   /// The connections of the cell, gathered from the cell and its neighbors
+   pub fn cell(&self, index: usize) -> Cell {
+      Cell {
+         north_connected: (!self.on_north_edge(index) || self.wrap.north_south) && self.south.get(self.north(index)),
+         south_connected: (!self.on_south_edge(index) || self.wrap.north_south) && self.south.get(index),
+         east_connected: (index % self.width != (self.width - 1) || self.wrap.east_west) && self.east.get(index),
+         west_connected: (!index.is_multiple_of(self.width) || self.wrap.east_west) && self.east.get(self.west(index)),
+         up_connected: index + self.floor_size() < self.size() && self.up.get(index),
+         down_connected: index >= self.floor_size() && self.up.get(self.down(index)),
+      }
+   }
+
+   pub fn get(&self, index: usize) -> Option<Cell> {
+      (index < self.size()).then(|| self.cell(index))
+   }
+
+   /// Which side of the first cell the second cell lies on, ignoring masks
+   pub(crate) fn direction_to(&self, i1: usize, i2: usize) -> Option<Direction> {
+      let col = i1 % self.width;
+      if i2 == self.north(i1) && (!self.on_north_edge(i1) || self.wrap.north_south) {
+         Some(Direction::North)
+      } else if i2 == self.south(i1) && (!self.on_south_edge(i1) || self.wrap.north_south) {
+         Some(Direction::South)
+      } else if i2 == self.east(i1) && (col != (self.width - 1) || self.wrap.east_west) {
+         Some(Direction::East)
+      } else if i2 == self.west(i1) && (col != 0 || self.wrap.east_west) {
+         Some(Direction::West)
+      } else if i2 == i1 + self.floor_size() {
+         Some(Direction::Up)
+      } else if i2 + self.floor_size() == i1 {
+         Some(Direction::Down)
       } else {
-         false
+         None
+      }
+   }
+
+   pub fn check_if_neighbors_and_connected(&self, i1: usize, i2: usize) -> bool {
+      match self.direction_to(i1, i2) {
+         Some(Direction::North) => self.south.get(i2),
+         Some(Direction::South) => self.south.get(i1),
+         Some(Direction::East) => self.east.get(i1),
+         Some(Direction::West) => self.east.get(i2),
+         Some(Direction::Up) => self.up.get(i1),
+         Some(Direction::Down) => self.up.get(i2),
+         None => false,
       }
    }
 
    /// If the cells are not neighbors, an incorrect connection will be made
    pub fn connect_neighbors(&mut self, i1: usize, i2: usize) {
-      if i2 + self.width == i1 {
-         self.connect_cell_north(i1);
-      } else if i2 == i1 + self.width {
-         self.connect_cell_south(i1);
-      } else if i2 == i1 + 1 {
-         self.connect_cell_east(i1);
+      match self.direction_to(i1, i2) {
+         Some(Direction::North) => self.connect_cell_north(i1),
+         Some(Direction::South) => self.connect_cell_south(i1),
+         Some(Direction::East) => self.connect_cell_east(i1),
+         Some(Direction::Up) => self.connect_cell_up(i1),
+         Some(Direction::Down) => self.connect_cell_down(i1),
+         Some(Direction::West) | None => self.connect_cell_west(i1),
+      }
+   }
+
+   /// If the cells are not neighbors, an incorrect wall will be put up
+   pub fn disconnect_neighbors(&mut self, i1: usize, i2: usize) {
+      match self.direction_to(i1, i2) {
+         Some(Direction::North) => self.disconnect_cell_north(i1),
+         Some(Direction::South) => self.disconnect_cell_south(i1),
+         Some(Direction::East) => self.disconnect_cell_east(i1),
+         Some(Direction::Up) => self.disconnect_cell_up(i1),
+         Some(Direction::Down) => self.disconnect_cell_down(i1),
+         Some(Direction::West) | None => self.disconnect_cell_west(i1),
+      }
+   }
+
+   /// The cell to the north, wrapping around to the south edge
+   pub fn north(&self, index: usize) -> usize {
+      if !self.on_north_edge(index) {
+         index - self.width
+      } else {
+         index + self.floor_size() - self.width
+      }
+   }
+
+   /// The cell to the south, wrapping around to the north edge
+   pub fn south(&self, index: usize) -> usize {
+      if !self.on_south_edge(index) {
+         index + self.width
+      } else {
+         index + self.width - self.floor_size()
+      }
+   }
+
+   /// The cell to the east, wrapping around to the west edge
+   pub fn east(&self, index: usize) -> usize {
+      if index % self.width != (self.width - 1) {
+         index + 1
+      } else {
+         index + 1 - self.width
+      }
+   }
+
+   /// The cell to the west, wrapping around to the east edge
+   pub fn west(&self, index: usize) -> usize {
+      if !index.is_multiple_of(self.width) {
+         index - 1
       } else {
-         self.connect_cell_west(i1);
+         index + self.width - 1
       }
    }
 
+   /// The cell directly above, on the next floor up
+   pub fn up(&self, index: usize) -> usize {
+      index + self.floor_size()
+   }
+
+   /// The cell directly below, on the next floor down
+   pub fn down(&self, index: usize) -> usize {
+      index - self.floor_size()
+   }
+
    pub fn has_neighbor_north(&self, index: usize) -> bool {
-      index >= self.width
+      (!self.on_north_edge(index) || self.wrap.north_south) && self.both_enabled(index, self.north(index))
    }
 
    pub fn has_neighbor_south(&self, index: usize) -> bool {
-      index < (self.width * (self.height - 1))
+      (!self.on_south_edge(index) || self.wrap.north_south) && self.both_enabled(index, self.south(index))
    }
 
    pub fn has_neighbor_east(&self, index: usize) -> bool {
-      index % self.width != (self.width - 1)
+      (index % self.width != (self.width - 1) || self.wrap.east_west) && self.both_enabled(index, self.east(index))
    }
 
    pub fn has_neighbor_west(&self, index: usize) -> bool {
-      index % self.width != 0
+      (!index.is_multiple_of(self.width) || self.wrap.east_west) && self.both_enabled(index, self.west(index))
+   }
+
+   pub fn has_neighbor_up(&self, index: usize) -> bool {
+      index + self.floor_size() < self.size() && self.both_enabled(index, self.up(index))
+   }
+
+   pub fn has_neighbor_down(&self, index: usize) -> bool {
+      index >= self.floor_size() && self.both_enabled(index, self.down(index))
+   }
+
+   fn both_enabled(&self, i1: usize, i2: usize) -> bool {
+      self.is_enabled(i1) && self.is_enabled(i2)
+   }
+
+   /// Whether a wall should be drawn on the south side of the cell.
+   /// There is never a wall between two cells that are both masked off.
+   fn has_south_wall(&self, index: usize) -> bool {
+      !self.cell(index).south_connected
+         && (self.is_enabled(index)
+            || ((!self.on_south_edge(index) || self.wrap.north_south) && self.is_enabled(self.south(index))))
+   }
+
+   /// Whether a wall should be drawn on the east side of the cell.
+   /// There is never a wall between two cells that are both masked off.
+   fn has_east_wall(&self, index: usize) -> bool {
+      !self.cell(index).east_connected
+         && (self.is_enabled(index)
+            || ((index % self.width != (self.width - 1) || self.wrap.east_west) && self.is_enabled(self.east(index))))
+   }
+
+   /// Whether a wall should be drawn along the top of the cell at `row`, `col` of the floor starting at `floor_start`.
+   /// Row `height` is the south edge of the floor.
+   pub(crate) fn horizontal_wall(&self, floor_start: usize, row: usize, col: usize) -> bool {
+      if row == 0 {
+         self.is_enabled(floor_start + col) && !self.cell(floor_start + col).north_connected
+      } else {
+         self.has_south_wall(floor_start + (row - 1) * self.width + col)
+      }
+   }
+
+   /// Whether a wall should be drawn along the left of the cell at `row`, `col` of the floor starting at
+   /// `floor_start`. Column `width` is the east edge of the floor.
+   pub(crate) fn vertical_wall(&self, floor_start: usize, row: usize, col: usize) -> bool {
+      let row_start = floor_start + row * self.width;
+      if col == 0 {
+         self.is_enabled(row_start) && !self.cell(row_start).west_connected
+      } else {
+         self.has_east_wall(row_start + col - 1)
+      }
+   }
+
+   /// Which walls meet at the top left corner of the cell at `row`, `col` of the floor starting at `floor_start`,
+   /// as `(up, down, left, right)`
+   pub(crate) fn walls_at_corner(&self, floor_start: usize, row: usize, col: usize) -> (bool, bool, bool, bool) {
+      (
+         row > 0 && self.vertical_wall(floor_start, row - 1, col),
+         row < self.height && self.vertical_wall(floor_start, row, col),
+         col > 0 && self.horizontal_wall(floor_start, row, col - 1),
+         col < self.width && self.horizontal_wall(floor_start, row, col),
+      )
+   }
+
+   /// How to draw the inside of the cell, marking any stairs up or down
+   fn stairs(&self, index: usize) -> &'static str {
+      match (self.cell(index).up_connected, self.cell(index).down_connected) {
+         (false, false) => "   ",
+         (true, false) => " ^ ",
+         (false, true) => " v ",
+         (true, true) => "^ v",
+      }
+   }
+
+   /// How to draw the corner at the given grid point, which touches up to four cells.
+   /// Corners that only touch masked off cells are left blank.
+   fn corner(&self, point_row: usize, point_col: usize) -> &'static str {
+      if self.mask.is_none() {
+         return "+";
+      }
+      let rows = point_row.saturating_sub(1)..(point_row + 1).min(self.height);
+      let cols = point_col.saturating_sub(1)..(point_col + 1).min(self.width);
+      for row in rows {
+         for col in cols.clone() {
+            if self.is_enabled(row * self.width + col) {
+               return "+";
+            }
+         }
+      }
+      " "
    }
 
    pub fn neighbors(&self, index: usize, buf: &mut Vec<usize>) {
       if self.has_neighbor_north(index) {
-         buf.push(index - self.width);
+         buf.push(self.north(index));
       }
       if self.has_neighbor_south(index) {
-         buf.push(index + self.width);
+         buf.push(self.south(index));
       }
       if self.has_neighbor_east(index) {
-         buf.push(index + 1);
+         buf.push(self.east(index));
       }
       if self.has_neighbor_west(index) {
-         buf.push(index - 1);
+         buf.push(self.west(index));
+      }
+      if self.has_neighbor_up(index) {
+         buf.push(self.up(index));
+      }
+      if self.has_neighbor_down(index) {
+         buf.push(self.down(index));
       }
    }
 
    pub fn connect_cell_north(&mut self, index: usize) {
-      let width = self.width;
-      self[index].north_connected = true;
-      self[index - width].south_connected = true;
+      let north = self.north(index);
+      self.south.insert(north);
    }
 
    pub fn connect_cell_south(&mut self, index: usize) {
-      let width = self.width;
-      self[index].south_connected = true;
-      self[index + width].north_connected = true;
+      self.south.insert(index);
    }
 
    pub fn connect_cell_west(&mut self, index: usize) {
-      self[index].west_connected = true;
-      self[index - 1].east_connected = true;
+      let west = self.west(index);
+      self.east.insert(west);
    }
 
    pub fn connect_cell_east(&mut self, index: usize) {
-      self[index].east_connected = true;
-      self[index + 1].west_connected = true;
+      self.east.insert(index);
+   }
+
+   pub fn connect_cell_up(&mut self, index: usize) {
+      self.up.insert(index);
+   }
+
+   pub fn connect_cell_down(&mut self, index: usize) {
+      let down = self.down(index);
+      self.up.insert(down);
    }
 
    pub fn disconnect_cell_north(&mut self, index: usize) {
-      let width = self.width;
-      self[index].north_connected = false;
-      self[index - width].south_connected = false;
+      let north = self.north(index);
+      self.south.remove(north);
    }
 
    pub fn disconnect_cell_south(&mut self, index: usize) {
-      let width = self.width;
-      self[index].south_connected = false;
-      self[index + width].north_connected = false;
+      self.south.remove(index);
    }
 
    pub fn disconnect_cell_west(&mut self, index: usize) {
-      self[index].west_connected = false;
-      self[index - 1].east_connected = false;
+      let west = self.west(index);
+      self.east.remove(west);
    }
 
    pub fn disconnect_cell_east(&mut self, index: usize) {
-      self[index].east_connected = false;
-      self[index + 1].west_connected = false;
+      self.east.remove(index);
+   }
+
+   pub fn disconnect_cell_up(&mut self, index: usize) {
+      self.up.remove(index);
+   }
+
+   pub fn disconnect_cell_down(&mut self, index: usize) {
+      let down = self.down(index);
+      self.up.remove(down);
    }
 
    pub fn size(&self) -> usize {
-      self.inner.len()
+      self.floor_size() * self.depth
+   }
+
+   /// How far to the right each floor is drawn by `write_maze_as_svg`, leaving a cell's width between floors
+   pub(crate) fn floor_x_offset(&self, floor: usize) -> usize {
+      floor * (self.width + 1) * 3
+   }
+
+   /// The width and height of the maze as drawn by `write_maze_as_svg`
+   pub fn svg_size(&self) -> (usize, usize) {
+      (self.floor_x_offset(self.depth) - 3, self.height * 3)
    }
 
    pub fn write_skeleton_as_svg<W: Write>(&self, dest: &mut W) -> io::Result<()> {
       // first, we draw a simple grid
-      for i in 0..self.inner.len() {
-         let row = i / self.width;
+      for i in 0..self.size() {
+         let row = (i % self.floor_size()) / self.width;
          let col = i % self.width;
 
          let upper_left_y = row * 3;
-         let upper_left_x = col * 3;
+         let upper_left_x = col * 3 + self.floor_x_offset(self.floor(i));
          writeln!(
             dest,
             "<rect class=\"cell\" id=\"{}\" x=\"{}\" y=\"{}\" width=\"3\" height=\"3\"/>",
@@ -228,24 +570,102 @@ impl Grid {
       Ok(())
    }
 
+   /// Floors are drawn side by side, from the bottom one on the left, with a marker in every cell that has stairs
+   /// (`^` going up and `v` going down)
    pub fn write_maze_as_svg<W: Write>(&self, dest: &mut W) -> io::Result<()> {
-      // top wall
-      writeln!(dest, "<line x1=\"0\" y1=\"0\" x2=\"{}\" y2=\"0\"/>", self.width * 3)?;
-      // west wall
-      writeln!(dest, "<line x1=\"0\" y1=\"0\" x2=\"0\" y2=\"{}\"/>", self.height * 3)?;
+      for floor in 0..self.depth {
+         self.write_floor_as_svg(floor, dest)?;
+      }
+      for i in 0..self.size() {
+         let cell = self.cell(i);
+         let x = ((i % self.width) * 3 + self.floor_x_offset(self.floor(i))) as f64;
+         let y = (((i % self.floor_size()) / self.width) * 3) as f64;
+         if cell.up_connected {
+            writeln!(
+               dest,
+               "<polyline points=\"{},{} {},{} {},{}\" fill=\"none\"/>",
+               x + 1.0,
+               y + 1.25,
+               x + 1.5,
+               y + 0.75,
+               x + 2.0,
+               y + 1.25
+            )?;
+         }
+         if cell.down_connected {
+            writeln!(
+               dest,
+               "<polyline points=\"{},{} {},{} {},{}\" fill=\"none\"/>",
+               x + 1.0,
+               y + 1.75,
+               x + 1.5,
+               y + 2.25,
+               x + 2.0,
+               y + 1.75
+            )?;
+         }
+      }
+      Ok(())
+   }
+
+   fn write_floor_as_svg<W: Write>(&self, floor: usize, dest: &mut W) -> io::Result<()> {
+      let start = floor * self.floor_size();
+      let x_offset = self.floor_x_offset(floor);
+      if self.mask.is_none() && self.wrap == Wrap::NONE {
+         // top wall
+         writeln!(
+            dest,
+            "<line x1=\"{}\" y1=\"0\" x2=\"{}\" y2=\"0\"/>",
+            x_offset,
+            x_offset + self.width * 3
+         )?;
+         // west wall
+         writeln!(
+            dest,
+            "<line x1=\"{}\" y1=\"0\" x2=\"{}\" y2=\"{}\"/>",
+            x_offset,
+            x_offset,
+            self.height * 3
+         )?;
+      } else {
+         // only the enabled cells along the top and west edge get a wall, unless it wraps around to a passage
+         for col in
+            (0..self.width).filter(|col| self.is_enabled(start + *col) && !self.cell(start + *col).north_connected)
+         {
+            writeln!(
+               dest,
+               "<line x1=\"{}\" y1=\"0\" x2=\"{}\" y2=\"0\"/>",
+               x_offset + col * 3,
+               x_offset + col * 3 + 3
+            )?;
+         }
+         for row in (0..self.height).filter(|row| {
+            let i = start + row * self.width;
+            self.is_enabled(i) && !self.cell(i).west_connected
+         }) {
+            writeln!(
+               dest,
+               "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>",
+               x_offset,
+               row * 3,
+               x_offset,
+               row * 3 + 3
+            )?;
+         }
+      }
       let mut current_horizontal_line_segment: Option<HorizontalLineSegment> = None;
       let mut current_vertical_line_segments: Box<[Option<VerticalLineSegment>]> =
          vec![None; self.width].into_boxed_slice();
-      for (i, cell) in self.inner.iter().enumerate() {
-         let row = i / self.width;
+      for i in start..(start + self.floor_size()) {
+         let row = (i - start) / self.width;
          let col = i % self.width;
 
          let upper_left_y = row * 3;
-         let upper_left_x = col * 3;
+         let upper_left_x = x_offset + col * 3;
 
          let current_vertical_line_segment: &mut Option<VerticalLineSegment> = &mut current_vertical_line_segments[col];
 
-         if cell.south_connected {
+         if !self.has_south_wall(i) {
             if let Some(ref hls) = current_horizontal_line_segment {
                writeln!(
                   dest,
@@ -275,7 +695,7 @@ impl Grid {
             })
          }
 
-         if cell.east_connected {
+         if !self.has_east_wall(i) {
             if let Some(vls) = current_vertical_line_segment.as_ref() {
                writeln!(
                   dest,
@@ -338,7 +758,10 @@ struct VerticalLineSegment {
 
 #[cfg(test)]
 mod test {
-   use super::Grid;
+   use super::{Grid, Wrap};
+   use crate::mazegen;
+   use rand::SeedableRng;
+   use rand_xorshift::XorShiftRng;
 
    #[test]
    fn has_neighbor() {
@@ -364,4 +787,75 @@ mod test {
       assert!(g.has_neighbor_east(7));
       assert!(g.has_neighbor_west(7));
    }
+
+   #[test]
+   fn wrapping_neighbors() {
+      let mut g = Grid::new_wrapping(5, 4, Wrap::CYLINDER);
+      assert!(!g.has_neighbor_north(0));
+      assert!(g.has_neighbor_west(0));
+      assert_eq!(g.west(0), 4);
+      assert!(g.has_neighbor_east(9));
+      assert_eq!(g.east(9), 5);
+      g.connect_neighbors(9, 5);
+      assert!(g.cell(9).east_connected && g.cell(5).west_connected);
+      assert!(g.check_if_neighbors_and_connected(5, 9));
+      // the first cell of the next row is not a neighbor, even though it comes right after
+      assert!(!g.check_if_neighbors_and_connected(9, 10));
+
+      let g = Grid::new_wrapping(5, 4, Wrap::TORUS);
+      assert!(g.has_neighbor_north(2));
+      assert_eq!(g.north(2), 17);
+      assert_eq!(g.south(17), 2);
+      let mut neighbors = Vec::new();
+      g.neighbors(0, &mut neighbors);
+      assert_eq!(neighbors, [15, 5, 1, 4]);
+   }
+
+   #[test]
+   fn floors() {
+      let mut g = Grid::new_3d(4, 3, 2);
+      assert_eq!(g.size(), 24);
+      // the last row of the bottom floor is not next to the first row of the top floor
+      assert!(!g.has_neighbor_south(9));
+      assert!(!g.has_neighbor_north(13));
+      assert!(g.has_neighbor_up(9));
+      assert!(!g.has_neighbor_down(9));
+      assert!(g.has_neighbor_down(21));
+      assert!(!g.has_neighbor_up(21));
+      let mut neighbors = Vec::new();
+      g.neighbors(0, &mut neighbors);
+      assert_eq!(neighbors, [4, 1, 12]);
+      g.connect_neighbors(21, 9);
+      assert!(g.cell(21).down_connected && g.cell(9).up_connected);
+      assert!(g.check_if_neighbors_and_connected(9, 21));
+      assert_eq!(g.cell(9).num_connections(), 1);
+   }
+
+   #[test]
+   fn cell_view_matches_links() {
+      let mut rng = XorShiftRng::seed_from_u64(12);
+      for mut g in [Grid::new_wrapping(70, 5, Wrap::TORUS), Grid::new_3d(9, 8, 3)] {
+         mazegen::wilson(&mut g, &mut rng);
+         mazegen::braid(&mut g, &mut rng, 0.5);
+         for i in 0..g.size() {
+            let cell = g.cell(i);
+            let expected = [
+               (cell.north_connected, g.north(i)),
+               (cell.south_connected, g.south(i)),
+               (cell.east_connected, g.east(i)),
+               (cell.west_connected, g.west(i)),
+            ];
+            for (connected, neighbor) in expected {
+               assert_eq!(connected, g.check_if_neighbors_and_connected(i, neighbor));
+               assert_eq!(connected, g.check_if_neighbors_and_connected(neighbor, i));
+            }
+            assert_eq!(
+               cell.up_connected,
+               g.has_neighbor_up(i) && g.check_if_neighbors_and_connected(i, g.up(i))
+            );
+         }
+         g.reset();
+         assert!((0..g.size()).all(|i| g.cell(i).num_connections() == 0));
+      }
+   }
 }
@@ -0,0 +1,175 @@
+//! Coloring every cell by its distance from a starting cell, as found by `pathfinding::algos::djikstra`.
+//!
+//! The color of a cell comes from a `ColorRamp`, which is handed how far the cell is as a fraction of the farthest
+//! distance, so any function from `0.0..=1.0` to a color can be plugged in.
+
+use crate::grid::Grid;
+use crate::raster::Rgb;
+use std::io::{self, Write};
+
+/// Maps a distance from `0.0` (the start) to `1.0` (the farthest cell) to a color
+pub type ColorRamp = fn(f64) -> Rgb;
+
+/// Every ramp along with the name it goes by on the command line
+pub const RAMPS: [(&str, ColorRamp); 4] = [
+   ("green", green),
+   ("grayscale", grayscale),
+   ("fire", fire),
+   ("rainbow", rainbow),
+];
+
+pub fn ramp_from_name(name: &str) -> Option<ColorRamp> {
+   RAMPS.iter().find(|(x, _)| *x == name).map(|(_, ramp)| *ramp)
+}
+
+fn lerp(from: u8, to: u8, t: f64) -> u8 {
+   (f64::from(from) + (f64::from(to) - f64::from(from)) * t).round() as u8
+}
+
+/// White at the start, darkening to green at the farthest cell. This is the ramp of the web front end.
+pub fn green(t: f64) -> Rgb {
+   let intensity = 1.0 - t;
+   let dark = (255.0 * intensity).round() as u8;
+   let bright = 128 + (127.0 * intensity) as u8;
+   [dark, bright, dark]
+}
+
+/// White at the start, fading to black at the farthest cell
+pub fn grayscale(t: f64) -> Rgb {
+   let x = lerp(0xff, 0x00, t);
+   [x, x, x]
+}
+
+/// White hot at the start, through yellow and red, to black at the farthest cell
+pub fn fire(t: f64) -> Rgb {
+   const STOPS: [Rgb; 4] = [
+      [0xff, 0xff, 0xff],
+      [0xff, 0xd0, 0x00],
+      [0xc0, 0x10, 0x00],
+      [0x00, 0x00, 0x00],
+   ];
+   let scaled = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+   let i = (scaled as usize).min(STOPS.len() - 2);
+   let t = scaled - i as f64;
+   let (from, to) = (STOPS[i], STOPS[i + 1]);
+   [
+      lerp(from[0], to[0], t),
+      lerp(from[1], to[1], t),
+      lerp(from[2], to[2], t),
+   ]
+}
+
+/// Around the color wheel from red at the start to violet at the farthest cell
+pub fn rainbow(t: f64) -> Rgb {
+   // hue in sixths of the color wheel, stopping short of wrapping back around to red
+   let hue = t.clamp(0.0, 1.0) * 5.0;
+   let rising = lerp(0x00, 0xff, hue % 1.0);
+   let falling = 0xff - rising;
+   match hue as usize {
+      0 => [0xff, rising, 0x00],
+      1 => [falling, 0xff, 0x00],
+      2 => [0x00, 0xff, rising],
+      3 => [0x00, falling, 0xff],
+      4 => [rising, 0x00, 0xff],
+      _ => [0xff, 0x00, 0xff],
+   }
+}
+
+/// The distances from `pathfinding::algos::djikstra` along with the ramp to color them with
+pub struct Heatmap<'a> {
+   pub distances: &'a [usize],
+   pub ramp: ColorRamp,
+   /// The distance of the start, which is where the ramp begins
+   pub shortest: usize,
+   /// The farthest distance of any cell that can be reached, which is where the ramp ends
+   pub longest: usize,
+}
+
+impl<'a> Heatmap<'a> {
+   pub fn new(distances: &'a [usize], ramp: ColorRamp) -> Heatmap<'a> {
+      let reachable = || distances.iter().copied().filter(|x| *x != usize::MAX);
+      Heatmap {
+         distances,
+         ramp,
+         shortest: reachable().min().unwrap_or(0),
+         longest: reachable().max().unwrap_or(0),
+      }
+   }
+
+   /// The color of the cell at `index`, or `None` if it can't be reached from the start (i.e. it is masked off)
+   pub fn color(&self, index: usize) -> Option<Rgb> {
+      let distance = self.distances[index];
+      if distance == usize::MAX {
+         return None;
+      }
+      let t = if self.longest == self.shortest {
+         0.0
+      } else {
+         (distance - self.shortest) as f64 / (self.longest - self.shortest) as f64
+      };
+      Some((self.ramp)(t))
+   }
+
+   /// Writes a square for every cell that can be reached, laid out like `Grid::write_maze_as_svg`. Draw the maze on
+   /// top of it to get the walls.
+   pub fn write_to_svg<W: Write>(&self, grid: &Grid, dest: &mut W) -> io::Result<()> {
+      for i in 0..grid.size() {
+         let Some([r, g, b]) = self.color(i) else {
+            continue;
+         };
+         let upper_left_y = ((i % grid.floor_size()) / grid.width) * 3;
+         let upper_left_x = (i % grid.width) * 3 + grid.floor_x_offset(grid.floor(i));
+         writeln!(
+            dest,
+            "<rect x=\"{}\" y=\"{}\" width=\"3\" height=\"3\" style=\"stroke-width:0.1px;stroke:#{:02x}{:02x}{:02x};fill:#{:02x}{:02x}{:02x}\"/>",
+            upper_left_x, upper_left_y, r, g, b, r, g, b
+         )?;
+      }
+      Ok(())
+   }
+}
+
+#[cfg(test)]
+mod test {
+   use super::{Heatmap, RAMPS};
+   use crate::grid::Grid;
+   use crate::mask::Mask;
+   use crate::pathfinding::algos;
+
+   #[test]
+   fn ramps_run_from_start_to_farthest() {
+      assert_eq!(super::green(0.0), [0xff, 0xff, 0xff]);
+      assert_eq!(super::green(1.0), [0x00, 0x80, 0x00]);
+      assert_eq!(super::grayscale(0.5), [0x80, 0x80, 0x80]);
+      assert_eq!(super::fire(1.0), [0x00, 0x00, 0x00]);
+      assert_eq!(super::rainbow(0.0), [0xff, 0x00, 0x00]);
+      assert_eq!(super::rainbow(1.0), [0xff, 0x00, 0xff]);
+      for (name, ramp) in RAMPS {
+         assert_eq!(super::ramp_from_name(name).map(|x| x(0.25)), Some(ramp(0.25)));
+      }
+      assert!(super::ramp_from_name("plaid").is_none());
+   }
+
+   #[test]
+   fn colors_by_distance() {
+      let mut grid = Grid::new_masked(Mask::from_ascii("...\n..X\n").unwrap());
+      grid.connect_cell_east(0);
+      grid.connect_cell_east(1);
+      grid.connect_cell_south(0);
+      grid.connect_cell_east(3);
+      let distances = algos::djikstra(&grid, 0);
+      let heatmap = Heatmap::new(&distances, super::grayscale);
+      assert_eq!((heatmap.shortest, heatmap.longest), (1, 3));
+      assert_eq!(heatmap.color(0), Some([0xff, 0xff, 0xff]));
+      assert_eq!(heatmap.color(4), Some([0x00, 0x00, 0x00]));
+      assert_eq!(heatmap.color(5), None);
+
+      let mut svg = Vec::new();
+      heatmap.write_to_svg(&grid, &mut svg).unwrap();
+      let svg = String::from_utf8(svg).unwrap();
+      assert_eq!(svg.lines().count(), 5);
+      assert!(svg.contains(
+         "<rect x=\"3\" y=\"3\" width=\"3\" height=\"3\" style=\"stroke-width:0.1px;stroke:#000000;fill:#000000\"/>"
+      ));
+   }
+}
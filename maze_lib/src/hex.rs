@@ -0,0 +1,254 @@
+use crate::graph::MazeGraph;
+use std::io::{self, Write};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HexDirection {
+   North,
+   NorthEast,
+   SouthEast,
+   South,
+   SouthWest,
+   NorthWest,
+}
+
+impl HexDirection {
+   pub const ALL: [HexDirection; 6] = [
+      HexDirection::North,
+      HexDirection::NorthEast,
+      HexDirection::SouthEast,
+      HexDirection::South,
+      HexDirection::SouthWest,
+      HexDirection::NorthWest,
+   ];
+
+   pub fn opposite(self) -> HexDirection {
+      match self {
+         HexDirection::North => HexDirection::South,
+         HexDirection::NorthEast => HexDirection::SouthWest,
+         HexDirection::SouthEast => HexDirection::NorthWest,
+         HexDirection::South => HexDirection::North,
+         HexDirection::SouthWest => HexDirection::NorthEast,
+         HexDirection::NorthWest => HexDirection::SouthEast,
+      }
+   }
+
+   fn bit(self) -> u8 {
+      1 << self as u8
+   }
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct HexCell {
+   connections: u8,
+}
+
+impl HexCell {
+   pub fn is_connected(self, direction: HexDirection) -> bool {
+      self.connections & direction.bit() != 0
+   }
+
+   pub fn num_connections(self) -> u8 {
+      self.connections.count_ones() as u8
+   }
+}
+
+/// A grid of flat-topped hexagons, laid out in columns where every odd column sits half a cell lower than the even
+/// columns next to it. Cells are indexed row by row, like `Grid`.
+pub struct HexGrid {
+   pub inner: Box<[HexCell]>,
+   pub width: usize,
+   pub height: usize,
+}
+
+/// Distance from the center of a hexagon to any of its corners, in SVG units
+const HEX_SIZE: f64 = 2.0;
+
+impl HexGrid {
+   pub fn new(width: usize, height: usize) -> HexGrid {
+      HexGrid {
+         inner: vec![HexCell::default(); width * height].into_boxed_slice(),
+         width,
+         height,
+      }
+   }
+
+   pub fn reset(&mut self) {
+      for x in self.inner.iter_mut() {
+         *x = HexCell::default();
+      }
+   }
+
+   pub fn size(&self) -> usize {
+      self.inner.len()
+   }
+
+   pub fn neighbor(&self, index: usize, direction: HexDirection) -> Option<usize> {
+      let row = index / self.width;
+      let col = index % self.width;
+      // odd columns are shifted down, so their diagonal neighbors are one row lower than for even columns
+      let (north_diagonal, south_diagonal) = if col.is_multiple_of(2) {
+         (row.checked_sub(1), Some(row))
+      } else {
+         (Some(row), Some(row + 1))
+      };
+      let (row, col) = match direction {
+         HexDirection::North => (row.checked_sub(1), Some(col)),
+         HexDirection::South => (Some(row + 1), Some(col)),
+         HexDirection::NorthEast => (north_diagonal, Some(col + 1)),
+         HexDirection::SouthEast => (south_diagonal, Some(col + 1)),
+         HexDirection::NorthWest => (north_diagonal, col.checked_sub(1)),
+         HexDirection::SouthWest => (south_diagonal, col.checked_sub(1)),
+      };
+      match (row, col) {
+         (Some(row), Some(col)) if row < self.height && col < self.width => Some(row * self.width + col),
+         _ => None,
+      }
+   }
+
+   pub fn neighbors(&self, index: usize, buf: &mut Vec<usize>) {
+      buf.extend(HexDirection::ALL.iter().filter_map(|d| self.neighbor(index, *d)));
+   }
+
+   fn direction_to(&self, i1: usize, i2: usize) -> Option<HexDirection> {
+      HexDirection::ALL
+         .iter()
+         .copied()
+         .find(|d| self.neighbor(i1, *d) == Some(i2))
+   }
+
+   pub fn dead_ends(&self) -> impl Iterator<Item = &HexCell> {
+      self.inner.iter().filter(|x| x.num_connections() == 1)
+   }
+
+   pub fn check_if_neighbors_and_connected(&self, i1: usize, i2: usize) -> bool {
+      self
+         .direction_to(i1, i2)
+         .is_some_and(|d| self.inner[i1].is_connected(d))
+   }
+
+   /// Does nothing if the cells are not neighbors
+   pub fn connect_neighbors(&mut self, i1: usize, i2: usize) {
+      if let Some(d) = self.direction_to(i1, i2) {
+         self.inner[i1].connections |= d.bit();
+         self.inner[i2].connections |= d.opposite().bit();
+      }
+   }
+
+   /// Does nothing if the cells are not neighbors
+   pub fn disconnect_neighbors(&mut self, i1: usize, i2: usize) {
+      if let Some(d) = self.direction_to(i1, i2) {
+         self.inner[i1].connections &= !d.bit();
+         self.inner[i2].connections &= !d.opposite().bit();
+      }
+   }
+
+   /// The width and height of the maze as drawn by `write_maze_as_svg`
+   pub fn svg_size(&self) -> (f64, f64) {
+      let b_size = HEX_SIZE * 3f64.sqrt() / 2.0;
+      let width = 3.0 * self.width as f64 * HEX_SIZE / 2.0 + HEX_SIZE / 2.0;
+      let height = self.height as f64 * b_size * 2.0 + b_size;
+      (width, height)
+   }
+
+   pub fn write_maze_as_svg<W: Write>(&self, dest: &mut W) -> io::Result<()> {
+      let a_size = HEX_SIZE / 2.0;
+      let b_size = HEX_SIZE * 3f64.sqrt() / 2.0;
+      for (i, cell) in self.inner.iter().enumerate() {
+         let row = i / self.width;
+         let col = i % self.width;
+
+         let cx = HEX_SIZE + 3.0 * col as f64 * a_size;
+         let mut cy = b_size + row as f64 * b_size * 2.0;
+         if col % 2 == 1 {
+            cy += b_size;
+         }
+
+         // f/n = far/near, n/s/e/w = north/south/east/west, m = middle
+         let x_fw = cx - HEX_SIZE;
+         let x_nw = cx - a_size;
+         let x_ne = cx + a_size;
+         let x_fe = cx + HEX_SIZE;
+         let y_n = cy - b_size;
+         let y_m = cy;
+         let y_s = cy + b_size;
+
+         let mut line = |x1: f64, y1: f64, x2: f64, y2: f64| {
+            writeln!(
+               dest,
+               "<line x1=\"{:.3}\" y1=\"{:.3}\" x2=\"{:.3}\" y2=\"{:.3}\"/>",
+               x1, y1, x2, y2
+            )
+         };
+
+         // walls shared with another cell are drawn by only one of the two
+         if self.neighbor(i, HexDirection::SouthWest).is_none() {
+            line(x_fw, y_m, x_nw, y_s)?;
+         }
+         if self.neighbor(i, HexDirection::NorthWest).is_none() {
+            line(x_fw, y_m, x_nw, y_n)?;
+         }
+         if self.neighbor(i, HexDirection::North).is_none() {
+            line(x_nw, y_n, x_ne, y_n)?;
+         }
+         if !cell.is_connected(HexDirection::NorthEast) {
+            line(x_ne, y_n, x_fe, y_m)?;
+         }
+         if !cell.is_connected(HexDirection::SouthEast) {
+            line(x_fe, y_m, x_ne, y_s)?;
+         }
+         if !cell.is_connected(HexDirection::South) {
+            line(x_ne, y_s, x_nw, y_s)?;
+         }
+      }
+      Ok(())
+   }
+}
+
+impl MazeGraph for HexGrid {
+   fn size(&self) -> usize {
+      HexGrid::size(self)
+   }
+
+   fn neighbors(&self, index: usize, buf: &mut Vec<usize>) {
+      HexGrid::neighbors(self, index, buf)
+   }
+
+   fn link(&mut self, i1: usize, i2: usize) {
+      self.connect_neighbors(i1, i2)
+   }
+
+   fn unlink(&mut self, i1: usize, i2: usize) {
+      self.disconnect_neighbors(i1, i2)
+   }
+
+   fn is_linked(&self, i1: usize, i2: usize) -> bool {
+      self.check_if_neighbors_and_connected(i1, i2)
+   }
+}
+
+#[cfg(test)]
+mod test {
+   use super::HexGrid;
+   use crate::{graph, mazegen};
+   use rand::SeedableRng;
+   use rand_xorshift::XorShiftRng;
+
+   #[test]
+   fn generators_carve_perfect_mazes() {
+      let mut rng = XorShiftRng::seed_from_u64(0);
+      let generators: [fn(&mut HexGrid, &mut XorShiftRng); 7] = [
+         mazegen::aldous_broder,
+         mazegen::wilson,
+         mazegen::hunt_and_kill,
+         mazegen::recursive_backtracker,
+         mazegen::kruskal,
+         mazegen::prim_simplified,
+         mazegen::prim_true,
+      ];
+      for generator in generators {
+         let mut g = HexGrid::new(9, 7);
+         generator(&mut g, &mut rng);
+         graph::assert_perfect(&g);
+      }
+   }
+}
@@ -1,6 +1,20 @@
 #![allow(clippy::uninlined_format_args)] // I'm an old man and I like the way it was before
 
+pub mod ascii;
+mod bit_set;
+pub mod code;
 mod disjoint_set;
+pub mod graph;
 pub mod grid;
+pub mod heatmap;
+pub mod hex;
+pub mod mask;
 pub mod mazegen;
 pub mod pathfinding;
+mod png;
+pub mod polar;
+pub mod raster;
+pub mod save;
+pub mod triangle;
+pub mod unicode;
+pub mod weave;
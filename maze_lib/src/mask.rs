@@ -0,0 +1,355 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// Switches individual cells of a grid on or off, so that mazes can take arbitrary shapes.
+///
+/// Cells that are off are never carved into, and pathfinding never passes through them. The cells that are left on
+/// must form a single region, connected north, south, east and west, since every generator carves a single maze that
+/// reaches every enabled cell. `from_ascii` and `from_pbm` refuse masks that don't, but `set_enabled` doesn't check.
+#[derive(Clone)]
+pub struct Mask {
+   pub width: usize,
+   pub height: usize,
+   enabled: Box<[bool]>,
+   num_enabled: usize,
+}
+
+#[derive(Debug)]
+pub enum MaskError {
+   /// No cells, or none of them switched on
+   Empty,
+   /// The cells switched on form more than one region, with no way to get from one to another
+   Disconnected {
+      regions: usize,
+   },
+   /// A row of the template is not as wide as the first row
+   RaggedRow {
+      line: usize,
+      expected: usize,
+      found: usize,
+   },
+   UnexpectedChar {
+      line: usize,
+      column: usize,
+      found: char,
+   },
+   /// The image is not a well formed PBM (P1 or P4) image
+   BadImage(&'static str),
+}
+
+impl Display for MaskError {
+   fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+      match self {
+         MaskError::Empty => write!(f, "mask has no cells"),
+         MaskError::Disconnected { regions } => write!(
+            f,
+            "mask's cells form {} separate regions, but a maze must be a single region",
+            regions
+         ),
+         MaskError::RaggedRow { line, expected, found } => write!(
+            f,
+            "line {}: expected a row of {} cells, found {}",
+            line, expected, found
+         ),
+         MaskError::UnexpectedChar { line, column, found } => write!(
+            f,
+            "line {}, column {}: unexpected character {:?} (expected 'X' or '.')",
+            line, column, found
+         ),
+         MaskError::BadImage(reason) => write!(f, "bad PBM image: {}", reason),
+      }
+   }
+}
+
+impl Error for MaskError {}
+
+impl Mask {
+   /// A mask with every cell switched on
+   pub fn new(width: usize, height: usize) -> Mask {
+      Mask {
+         width,
+         height,
+         enabled: vec![true; width * height].into_boxed_slice(),
+         num_enabled: width * height,
+      }
+   }
+
+   /// Reads a mask from a text template with one line per row, where `X` switches a cell off and `.` leaves it on.
+   ///
+   /// ```text
+   /// X.....X
+   /// .......
+   /// X.....X
+   /// ```
+   pub fn from_ascii(template: &str) -> Result<Mask, MaskError> {
+      let mut width = 0;
+      let mut enabled = Vec::new();
+      for (line_i, line) in template.lines().enumerate() {
+         let line = line.trim_end_matches('\r');
+         let mut row_width = 0;
+         for (col_i, c) in line.chars().enumerate() {
+            match c {
+               '.' => enabled.push(true),
+               'X' | 'x' => enabled.push(false),
+               found => {
+                  return Err(MaskError::UnexpectedChar {
+                     line: line_i + 1,
+                     column: col_i + 1,
+                     found,
+                  });
+               }
+            }
+            row_width += 1;
+         }
+         if line_i == 0 {
+            width = row_width;
+         } else if row_width != width {
+            return Err(MaskError::RaggedRow {
+               line: line_i + 1,
+               expected: width,
+               found: row_width,
+            });
+         }
+      }
+      if enabled.is_empty() {
+         return Err(MaskError::Empty);
+      }
+      let height = enabled.len() / width;
+      Mask::from_cells(width, height, enabled)
+   }
+
+   /// Reads a mask from a black and white PBM image (either the plain `P1` or the raw `P4` flavor),
+   /// with one pixel per cell. Black pixels switch a cell off, white pixels leave it on.
+   pub fn from_pbm(image: &[u8]) -> Result<Mask, MaskError> {
+      let mut pos = 0;
+      let magic = pbm_token(image, &mut pos).ok_or(MaskError::BadImage("missing magic number"))?;
+      let raw = match magic {
+         b"P1" => false,
+         b"P4" => true,
+         _ => return Err(MaskError::BadImage("not a P1 or P4 image")),
+      };
+      let width = pbm_number(image, &mut pos)?;
+      let height = pbm_number(image, &mut pos)?;
+      if width == 0 || height == 0 {
+         return Err(MaskError::Empty);
+      }
+      // the header is untrusted, so check that the pixel data is really there before reserving space for it
+      let size = width
+         .checked_mul(height)
+         .ok_or(MaskError::BadImage("image is too large"))?;
+      let mut enabled;
+      if raw {
+         // exactly one whitespace byte separates the header from the pixel data
+         pos += 1;
+         let row_bytes = width.div_ceil(8);
+         let data = row_bytes
+            .checked_mul(height)
+            .and_then(|len| pos.checked_add(len))
+            .and_then(|end| image.get(pos..end))
+            .ok_or(MaskError::BadImage("pixel data is truncated"))?;
+         enabled = Vec::with_capacity(size);
+         for row in data.chunks_exact(row_bytes) {
+            for col in 0..width {
+               let black = row[col / 8] & (0x80 >> (col % 8)) != 0;
+               enabled.push(!black);
+            }
+         }
+      } else {
+         // every pixel takes at least one byte
+         if size > image.len().saturating_sub(pos) {
+            return Err(MaskError::BadImage("pixel data is truncated"));
+         }
+         enabled = Vec::with_capacity(size);
+         while enabled.len() < size {
+            skip_pbm_whitespace(image, &mut pos);
+            match image.get(pos) {
+               Some(b'0') => enabled.push(true),
+               Some(b'1') => enabled.push(false),
+               Some(_) => return Err(MaskError::BadImage("pixels must be 0 or 1")),
+               None => return Err(MaskError::BadImage("pixel data is truncated")),
+            }
+            pos += 1;
+         }
+      }
+      Mask::from_cells(width, height, enabled)
+   }
+
+   fn from_cells(width: usize, height: usize, enabled: Vec<bool>) -> Result<Mask, MaskError> {
+      let num_enabled = enabled.iter().filter(|x| **x).count();
+      if num_enabled == 0 {
+         return Err(MaskError::Empty);
+      }
+      let mask = Mask {
+         width,
+         height,
+         enabled: enabled.into_boxed_slice(),
+         num_enabled,
+      };
+      let regions = mask.num_regions();
+      if regions > 1 {
+         return Err(MaskError::Disconnected { regions });
+      }
+      Ok(mask)
+   }
+
+   /// How many separate regions the enabled cells form, counting cells as connected to the enabled cells directly
+   /// north, south, east and west of them
+   fn num_regions(&self) -> usize {
+      let mut seen = vec![false; self.enabled.len()].into_boxed_slice();
+      let mut stack = Vec::new();
+      let mut regions = 0;
+      for first in 0..self.enabled.len() {
+         if !self.enabled[first] || seen[first] {
+            continue;
+         }
+         regions += 1;
+         seen[first] = true;
+         stack.push(first);
+         while let Some(i) = stack.pop() {
+            let (row, col) = (i / self.width, i % self.width);
+            let neighbors = [
+               (row > 0).then(|| i - self.width),
+               (row + 1 < self.height).then(|| i + self.width),
+               (col > 0).then(|| i - 1),
+               (col + 1 < self.width).then(|| i + 1),
+            ];
+            for neighbor in neighbors.into_iter().flatten() {
+               if self.enabled[neighbor] && !seen[neighbor] {
+                  seen[neighbor] = true;
+                  stack.push(neighbor);
+               }
+            }
+         }
+      }
+      regions
+   }
+
+   pub fn is_enabled(&self, index: usize) -> bool {
+      self.enabled[index]
+   }
+
+   pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+      if self.enabled[index] != enabled {
+         self.enabled[index] = enabled;
+         if enabled {
+            self.num_enabled += 1;
+         } else {
+            self.num_enabled -= 1;
+         }
+      }
+   }
+
+   pub fn num_enabled(&self) -> usize {
+      self.num_enabled
+   }
+}
+
+fn skip_pbm_whitespace(image: &[u8], pos: &mut usize) {
+   while let Some(b) = image.get(*pos) {
+      if *b == b'#' {
+         // comments run to the end of the line
+         while image.get(*pos).is_some_and(|b| *b != b'\n') {
+            *pos += 1;
+         }
+      } else if b.is_ascii_whitespace() {
+         *pos += 1;
+      } else {
+         break;
+      }
+   }
+}
+
+fn pbm_token<'a>(image: &'a [u8], pos: &mut usize) -> Option<&'a [u8]> {
+   skip_pbm_whitespace(image, pos);
+   let start = *pos;
+   while image.get(*pos).is_some_and(|b| !b.is_ascii_whitespace() && *b != b'#') {
+      *pos += 1;
+   }
+   if start == *pos { None } else { Some(&image[start..*pos]) }
+}
+
+fn pbm_number(image: &[u8], pos: &mut usize) -> Result<usize, MaskError> {
+   pbm_token(image, pos)
+      .and_then(|x| std::str::from_utf8(x).ok())
+      .and_then(|x| x.parse().ok())
+      .ok_or(MaskError::BadImage("bad width or height"))
+}
+
+#[cfg(test)]
+mod test {
+   use super::{Mask, MaskError};
+
+   #[test]
+   fn ascii_template() {
+      let mask = Mask::from_ascii("X..\n...\n..X\n").unwrap();
+      assert_eq!(mask.width, 3);
+      assert_eq!(mask.height, 3);
+      assert_eq!(mask.num_enabled(), 7);
+      assert!(!mask.is_enabled(0));
+      assert!(mask.is_enabled(1));
+      assert!(!mask.is_enabled(8));
+
+      assert!(matches!(
+         Mask::from_ascii("...\n..\n"),
+         Err(MaskError::RaggedRow {
+            line: 2,
+            expected: 3,
+            found: 2
+         })
+      ));
+      assert!(matches!(
+         Mask::from_ascii("...\n.?.\n"),
+         Err(MaskError::UnexpectedChar {
+            line: 2,
+            column: 2,
+            found: '?'
+         })
+      ));
+   }
+
+   #[test]
+   fn pbm_image() {
+      let plain = Mask::from_pbm(b"P1\n# a comment\n3 2\n1 0 0\n0 0 1\n").unwrap();
+      let raw = Mask::from_pbm(b"P4\n3 2\n\x80\x20").unwrap();
+      for mask in [plain, raw] {
+         assert_eq!(mask.width, 3);
+         assert_eq!(mask.height, 2);
+         let cells: Vec<bool> = (0..6).map(|i| mask.is_enabled(i)).collect();
+         assert_eq!(cells, [false, true, true, true, true, false]);
+      }
+      assert!(Mask::from_pbm(b"P4\n3 2\n\x80").is_err());
+   }
+
+   #[test]
+   fn pbm_header_is_not_trusted() {
+      // sizes that would overflow, or need gigabytes, are refused before anything is allocated
+      assert!(matches!(
+         Mask::from_pbm(b"P4 4000000000 4000000000 "),
+         Err(MaskError::BadImage(_))
+      ));
+      assert!(matches!(
+         Mask::from_pbm(b"P4 18446744073709551615 2 "),
+         Err(MaskError::BadImage(_))
+      ));
+      assert!(matches!(
+         Mask::from_pbm(b"P1 100000 100000 0"),
+         Err(MaskError::BadImage(_))
+      ));
+   }
+
+   #[test]
+   fn masks_must_be_one_region() {
+      assert!(matches!(Mask::from_ascii("XX\nXX\n"), Err(MaskError::Empty)));
+      assert!(matches!(Mask::from_pbm(b"P1\n2 1\n1 1\n"), Err(MaskError::Empty)));
+      assert!(matches!(
+         Mask::from_ascii("..X..\n..X..\n"),
+         Err(MaskError::Disconnected { regions: 2 })
+      ));
+      // cells that only touch at a corner aren't connected
+      assert!(matches!(
+         Mask::from_pbm(b"P1\n2 2\n0 1\n1 0\n"),
+         Err(MaskError::Disconnected { regions: 2 })
+      ));
+      assert!(Mask::from_ascii("..X..\n.....\n").is_ok());
+   }
+}
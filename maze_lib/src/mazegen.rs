@@ -1,13 +1,122 @@
+use crate::bit_set::BitSet;
 use crate::disjoint_set::DisjointSet;
+use crate::graph::MazeGraph;
 use crate::grid::Grid;
+use crate::weave::WeaveGrid;
 use rand::distr::{Distribution, Uniform};
 use rand::seq::{IteratorRandom, SliceRandom};
 use rand::Rng;
 use std::cmp::Ordering;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, VecDeque};
+use std::error::Error;
 use std::fmt;
+use std::str::FromStr;
 
-#[derive(Copy, Clone)]
+/// Relative weights used by the Growing Tree algorithm to choose which active cell to grow from next.
+///
+/// A selection of only `newest` behaves like the Recursive Backtracker, only `random` behaves like Prim's (Simplified).
+/// Mixing weights, e.g. 3 parts newest and 1 part random, blends the long corridors of the former with the short
+/// branches of the latter.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CellSelection {
+   pub newest: u8,
+   pub oldest: u8,
+   pub random: u8,
+   pub middle: u8,
+}
+
+impl CellSelection {
+   pub const NEWEST: CellSelection = CellSelection {
+      newest: 1,
+      oldest: 0,
+      random: 0,
+      middle: 0,
+   };
+   pub const OLDEST: CellSelection = CellSelection {
+      newest: 0,
+      oldest: 1,
+      random: 0,
+      middle: 0,
+   };
+   pub const RANDOM: CellSelection = CellSelection {
+      newest: 0,
+      oldest: 0,
+      random: 1,
+      middle: 0,
+   };
+   pub const MIDDLE: CellSelection = CellSelection {
+      newest: 0,
+      oldest: 0,
+      random: 0,
+      middle: 1,
+   };
+
+   fn total(self) -> u32 {
+      u32::from(self.newest) + u32::from(self.oldest) + u32::from(self.random) + u32::from(self.middle)
+   }
+
+   /// Picks an index into a list of `len` active cells, ordered from oldest to newest.
+   /// A selection with all weights at zero is treated as `NEWEST`.
+   fn choose<R: Rng>(self, len: usize, rng: &mut R) -> usize {
+      let weights = [self.newest, self.oldest, self.random, self.middle];
+      // only spend a random number on the strategy if there is more than one to pick from
+      let strategy = if weights.iter().filter(|x| **x > 0).count() > 1 {
+         let mut roll = rng.random_range(0..self.total());
+         weights
+            .iter()
+            .position(|w| {
+               if roll < u32::from(*w) {
+                  true
+               } else {
+                  roll -= u32::from(*w);
+                  false
+               }
+            })
+            .unwrap()
+      } else {
+         weights.iter().position(|x| *x > 0).unwrap_or(0)
+      };
+      match strategy {
+         0 => len - 1,
+         1 => 0,
+         2 => rng.random_range(0..len),
+         _ => len / 2,
+      }
+   }
+}
+
+impl fmt::Display for CellSelection {
+   fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+      let total = self.total();
+      if total == 0 {
+         return write!(fmt, "Newest");
+      }
+      let mut first = true;
+      for (name, weight) in [
+         ("Newest", self.newest),
+         ("Oldest", self.oldest),
+         ("Random", self.random),
+         ("Middle", self.middle),
+      ] {
+         if weight == 0 {
+            continue;
+         }
+         if !first {
+            write!(fmt, " / ")?;
+         }
+         first = false;
+         if u32::from(weight) == total {
+            write!(fmt, "{}", name)?;
+         } else {
+            let pct = (f64::from(weight) * 100.0 / f64::from(total)).round();
+            write!(fmt, "{}% {}", pct, name)?;
+         }
+      }
+      Ok(())
+   }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Algo {
    BinaryTree,
    Sidewinder,
@@ -20,6 +129,7 @@ pub enum Algo {
    RecursiveDivision,
    PrimSimplified,
    PrimTrue,
+   GrowingTree(CellSelection),
    Empty,
 }
 
@@ -32,7 +142,7 @@ impl fmt::Display for Algo {
             Algo::BinaryTree => "Binary Tree",
             Algo::Sidewinder => "Sidewinder",
             Algo::AldousBroder => "Aldous-Broder",
-            Algo::Wilson => "Wilsons's",
+            Algo::Wilson => "Wilson's",
             Algo::HuntAndKill => "Hunt and Kill",
             Algo::RecursiveBacktracker => "Recursive Backtracker",
             Algo::Kruskal => "Kruskal's",
@@ -40,12 +150,112 @@ impl fmt::Display for Algo {
             Algo::RecursiveDivision => "Recursive Division",
             Algo::PrimSimplified => "Prim's (Simplified)",
             Algo::PrimTrue => "Prim's (True)",
+            Algo::GrowingTree(selection) => return write!(fmt, "Growing Tree ({})", selection),
             Algo::Empty => "Empty",
          }
       )
    }
 }
 
+/// Every algorithm with a name of its own, along with that name. These are the names `Algo::from_str` takes, which the
+/// algorithm picker of the web front end and `maze_bin --algo` both go by.
+pub const ALGO_NAMES: [(&str, Algo); 17] = [
+   ("BinaryTree", Algo::BinaryTree),
+   ("Sidewinder", Algo::Sidewinder),
+   ("AldousBroder", Algo::AldousBroder),
+   ("Wilson", Algo::Wilson),
+   ("HuntAndKill", Algo::HuntAndKill),
+   ("RecursiveBacktracker", Algo::RecursiveBacktracker),
+   ("Kruskal", Algo::Kruskal),
+   ("Eller", Algo::Eller),
+   ("RecursiveDivision", Algo::RecursiveDivision),
+   ("PrimSimplified", Algo::PrimSimplified),
+   ("PrimTrue", Algo::PrimTrue),
+   ("GrowingTreeNewest", Algo::GrowingTree(CellSelection::NEWEST)),
+   ("GrowingTreeOldest", Algo::GrowingTree(CellSelection::OLDEST)),
+   ("GrowingTreeRandom", Algo::GrowingTree(CellSelection::RANDOM)),
+   ("GrowingTreeMiddle", Algo::GrowingTree(CellSelection::MIDDLE)),
+   (
+      "GrowingTreeNewestRandom",
+      Algo::GrowingTree(CellSelection {
+         newest: 3,
+         oldest: 0,
+         random: 1,
+         middle: 0,
+      }),
+   ),
+   ("Empty", Algo::Empty),
+];
+
+/// The prefix of a Growing Tree whose weights have no name of their own, e.g. `GrowingTree:3,0,1,0` for 3 parts
+/// newest and 1 part random
+const GROWING_TREE_PREFIX: &str = "GrowingTree:";
+
+impl Algo {
+   /// The name `Algo::from_str` turns back into this algorithm. Unlike `Display`, it never changes.
+   pub fn id(&self) -> String {
+      if let Some((name, _)) = ALGO_NAMES.iter().find(|(_, x)| x == self) {
+         return (*name).to_string();
+      }
+      match self {
+         Algo::GrowingTree(s) => format!(
+            "{}{},{},{},{}",
+            GROWING_TREE_PREFIX, s.newest, s.oldest, s.random, s.middle
+         ),
+         _ => unreachable!("every other algorithm is named in ALGO_NAMES"),
+      }
+   }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseAlgoError {
+   /// The name isn't in `ALGO_NAMES`
+   Unknown(String),
+   /// The weights after `GrowingTree:` aren't four numbers from 0 to 255
+   BadWeights(String),
+}
+
+impl fmt::Display for ParseAlgoError {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      match self {
+         ParseAlgoError::Unknown(name) => write!(f, "unknown algorithm {:?}", name),
+         ParseAlgoError::BadWeights(weights) => write!(
+            f,
+            "growing tree weights {:?} are not four numbers for newest, oldest, random and middle",
+            weights
+         ),
+      }
+   }
+}
+
+impl Error for ParseAlgoError {}
+
+impl FromStr for Algo {
+   type Err = ParseAlgoError;
+
+   fn from_str(s: &str) -> Result<Algo, ParseAlgoError> {
+      if let Some((_, algo)) = ALGO_NAMES.iter().find(|(name, _)| *name == s) {
+         return Ok(*algo);
+      }
+      let Some(weights) = s.strip_prefix(GROWING_TREE_PREFIX) else {
+         return Err(ParseAlgoError::Unknown(s.to_string()));
+      };
+      let parsed = weights
+         .split(',')
+         .map(|x| x.trim().parse::<u8>())
+         .collect::<Result<Vec<_>, _>>();
+      match parsed.as_deref() {
+         Ok(&[newest, oldest, random, middle]) => Ok(Algo::GrowingTree(CellSelection {
+            newest,
+            oldest,
+            random,
+            middle,
+         })),
+         _ => Err(ParseAlgoError::BadWeights(weights.to_string())),
+      }
+   }
+}
+
 pub const ALGOS: [Algo; 11] = [
    Algo::BinaryTree,
    Algo::Sidewinder,
@@ -60,134 +270,379 @@ pub const ALGOS: [Algo; 11] = [
    Algo::PrimTrue,
 ];
 
+/// A single step taken by a maze generator, in the order it happened.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CarveEvent {
+   /// A passage was carved between two neighboring cells
+   Connect(usize, usize),
+   /// A wall was put up between two neighboring cells
+   Disconnect(usize, usize),
+   /// The cell became part of the maze
+   Visit(usize),
+   /// The cell became a candidate for the generator to work from
+   FrontierAdd(usize),
+   /// The cell stopped being a candidate for the generator to work from
+   FrontierRemove(usize),
+}
+
+pub trait CarveObserver {
+   fn observe(&mut self, event: CarveEvent);
+}
+
+impl CarveObserver for () {
+   #[inline(always)]
+   fn observe(&mut self, _: CarveEvent) {}
+}
+
+impl CarveObserver for Vec<CarveEvent> {
+   fn observe(&mut self, event: CarveEvent) {
+      self.push(event);
+   }
+}
+
 pub fn carve_maze<R: Rng>(grid: &mut Grid, rng: &mut R, algo: Algo) {
+   carve_maze_observed(grid, rng, algo, &mut ());
+}
+
+/// Carves a maze exactly like `carve_maze` would, additionally returning every step the generator took.
+///
+/// The grid is fully carved by the time this returns; the steps are meant to be replayed on top of an empty grid
+/// (e.g. to animate generation), and applying every `Connect` and `Disconnect` in order yields the same maze.
+pub fn carve_maze_steps<R: Rng>(grid: &mut Grid, rng: &mut R, algo: Algo) -> std::vec::IntoIter<CarveEvent> {
+   let mut events = Vec::new();
+   carve_maze_observed(grid, rng, algo, &mut events);
+   events.into_iter()
+}
+
+pub fn carve_maze_observed<R: Rng, O: CarveObserver>(grid: &mut Grid, rng: &mut R, algo: Algo, obs: &mut O) {
    match algo {
-      Algo::BinaryTree => binary_tree(grid, rng),
-      Algo::Sidewinder => sidewinder(grid, rng),
-      Algo::AldousBroder => aldous_broder(grid, rng),
-      Algo::Wilson => wilson(grid, rng),
-      Algo::HuntAndKill => hunt_and_kill(grid, rng),
-      Algo::RecursiveBacktracker => recursive_backtracker(grid, rng),
-      Algo::Kruskal => kruskal(grid, rng),
-      Algo::Eller => eller(grid, rng),
-      Algo::RecursiveDivision => recursive_division(grid, rng),
-      Algo::PrimSimplified => prim_simplified(grid, rng),
-      Algo::PrimTrue => prim_true(grid, rng),
-      Algo::Empty => empty(grid),
+      Algo::BinaryTree => binary_tree_observed(grid, rng, obs),
+      Algo::Sidewinder => sidewinder_observed(grid, rng, obs),
+      Algo::AldousBroder => aldous_broder_observed(grid, rng, obs),
+      Algo::Wilson => wilson_observed(grid, rng, obs),
+      Algo::HuntAndKill => hunt_and_kill_observed(grid, rng, obs),
+      Algo::RecursiveBacktracker => recursive_backtracker_observed(grid, rng, obs),
+      Algo::Kruskal => kruskal_observed(grid, rng, obs),
+      Algo::Eller => eller_observed(grid, rng, obs),
+      Algo::RecursiveDivision => recursive_division_observed(grid, rng, obs),
+      Algo::PrimSimplified => prim_simplified_observed(grid, rng, obs),
+      Algo::PrimTrue => prim_true_observed(grid, rng, obs),
+      Algo::GrowingTree(selection) => growing_tree_observed(grid, rng, selection, obs),
+      Algo::Empty => empty_observed(grid, obs),
    }
 }
 
-pub fn binary_tree<R: Rng>(grid: &mut Grid, rng: &mut R) {
+/// Picks a random cell that is part of the maze
+fn random_cell<G: MazeGraph, R: Rng>(grid: &G, rng: &mut R) -> usize {
+   if grid.num_enabled() == grid.size() {
+      rng.random_range(0..grid.size())
+   } else {
+      let nth = rng.random_range(0..grid.num_enabled());
+      (0..grid.size()).filter(|i| grid.is_enabled(*i)).nth(nth).unwrap()
+   }
+}
+
+/// The first cell (by index) that is part of the maze
+fn first_cell<G: MazeGraph>(grid: &G) -> usize {
+   (0..grid.size()).find(|i| grid.is_enabled(*i)).unwrap()
+}
+
+/// Cells that are not part of the maze start out visited, so that they are never carved into
+fn initial_visited<G: MazeGraph>(grid: &G) -> BitSet {
+   let mut visited = BitSet::new(grid.size());
+   for i in (0..grid.size()).filter(|i| !grid.is_enabled(*i)) {
+      visited.insert(i);
+   }
+   visited
+}
+
+/// The row by row generators can leave a masked grid split into several regions, since a cell may have no neighbor
+/// in the direction the generator would carve. This joins those regions back up with random passages, leaving a
+/// perfect maze.
+fn join_masked_regions<R: Rng, O: CarveObserver>(grid: &mut Grid, rng: &mut R, obs: &mut O) {
+   if grid.mask.is_none() {
+      return;
+   }
+   let mut disjoint_set = DisjointSet::new(grid.size());
+   let mut edges = Vec::new();
    for i in 0..grid.size() {
-      if grid.has_neighbor_north(i) && grid.has_neighbor_east(i) {
-         if rng.random_bool(0.5) {
-            grid.connect_cell_north(i);
+      if grid.has_neighbor_south(i) {
+         if grid.cell(i).south_connected {
+            disjoint_set.union(i, grid.south(i));
          } else {
-            grid.connect_cell_east(i);
+            edges.push((i, grid.south(i)));
          }
-      } else if grid.has_neighbor_north(i) {
-         grid.connect_cell_north(i);
-      } else if grid.has_neighbor_east(i) {
-         grid.connect_cell_east(i);
+      }
+      if grid.has_neighbor_east(i) {
+         if grid.cell(i).east_connected {
+            disjoint_set.union(i, grid.east(i));
+         } else {
+            edges.push((i, grid.east(i)));
+         }
+      }
+   }
+   edges.shuffle(rng);
+   for edge in edges {
+      if disjoint_set.find(edge.0) == disjoint_set.find(edge.1) {
+         continue;
+      }
+      disjoint_set.union(edge.0, edge.1);
+      grid.connect_neighbors(edge.0, edge.1);
+      obs.observe(CarveEvent::Connect(edge.0, edge.1));
+   }
+}
+
+/// Carving all the way around an edge that wraps would leave a loop, so the row by row generators cut each wrapped
+/// edge open somewhere and carve no passages across the cut. The cut is put at a random column, so that passages
+/// cross the wrapped edge itself like any other. This is the first column after the cut, which for a grid that
+/// doesn't wrap east to west is always the west edge.
+fn first_col<R: Rng>(grid: &Grid, rng: &mut R) -> usize {
+   if grid.wrap.east_west {
+      rng.random_range(0..grid.width)
+   } else {
+      0
+   }
+}
+
+/// Every row or column number below `len`, starting from `first` and wrapping around to end just before it
+fn from_cut(first: usize, len: usize) -> impl Iterator<Item = usize> {
+   (0..len).map(move |x| (first + x) % len)
+}
+
+/// Like `first_col`, the first row after a random cut across a grid that wraps north to south, or else the north edge
+fn first_row<R: Rng>(grid: &Grid, rng: &mut R) -> usize {
+   if grid.wrap.north_south {
+      rng.random_range(0..grid.height)
+   } else {
+      0
+   }
+}
+
+/// The row by row generators carve each floor of a 3D grid as a maze of its own. This joins every floor to the one
+/// above it with a single staircase, leaving a perfect maze.
+fn join_floors<R: Rng, O: CarveObserver>(grid: &mut Grid, rng: &mut R, obs: &mut O) {
+   for floor_start in (0..grid.size() - grid.floor_size()).step_by(grid.floor_size()) {
+      let floor = floor_start..(floor_start + grid.floor_size());
+      if let Some(i) = floor.filter(|i| grid.has_neighbor_up(*i)).choose(rng) {
+         grid.connect_cell_up(i);
+         obs.observe(CarveEvent::Connect(i, grid.up(i)));
       }
    }
 }
 
+pub fn binary_tree<R: Rng>(grid: &mut Grid, rng: &mut R) {
+   binary_tree_observed(grid, rng, &mut ());
+}
+
+fn binary_tree_observed<R: Rng, O: CarveObserver>(grid: &mut Grid, rng: &mut R, obs: &mut O) {
+   for floor_start in (0..grid.size()).step_by(grid.floor_size()) {
+      let top = first_row(grid, rng);
+      for r in from_cut(top, grid.height) {
+         // every row is cut in a different place, so that they don't all line up
+         let first = first_col(grid, rng);
+         for c in from_cut(first, grid.width) {
+            let i = floor_start + r * grid.width + c;
+            if !grid.is_enabled(i) {
+               continue;
+            }
+            obs.observe(CarveEvent::Visit(i));
+            let north = grid.has_neighbor_north(i) && r != top;
+            let east = grid.has_neighbor_east(i) && grid.east(i) != floor_start + r * grid.width + first;
+            if north && east {
+               if rng.random_bool(0.5) {
+                  grid.connect_cell_north(i);
+                  obs.observe(CarveEvent::Connect(i, grid.north(i)));
+               } else {
+                  grid.connect_cell_east(i);
+                  obs.observe(CarveEvent::Connect(i, grid.east(i)));
+               }
+            } else if north {
+               grid.connect_cell_north(i);
+               obs.observe(CarveEvent::Connect(i, grid.north(i)));
+            } else if east {
+               grid.connect_cell_east(i);
+               obs.observe(CarveEvent::Connect(i, grid.east(i)));
+            }
+         }
+      }
+   }
+   join_masked_regions(grid, rng, obs);
+   join_floors(grid, rng, obs);
+}
+
 pub fn sidewinder<R: Rng>(grid: &mut Grid, rng: &mut R) {
+   sidewinder_observed(grid, rng, &mut ());
+}
+
+fn sidewinder_observed<R: Rng, O: CarveObserver>(grid: &mut Grid, rng: &mut R, obs: &mut O) {
    let mut cur_run = vec![];
-   for i in 0..grid.size() {
-      if grid.has_neighbor_north(i) && grid.has_neighbor_east(i) {
-         cur_run.push(i);
-         if rng.random_bool(0.5) {
-            grid.connect_cell_north(cur_run.iter().choose(rng).copied().unwrap());
-            cur_run.clear();
-         } else {
-            grid.connect_cell_east(i);
+   for floor_start in (0..grid.size()).step_by(grid.floor_size()) {
+      let top = first_row(grid, rng);
+      for r in from_cut(top, grid.height) {
+         // every row is cut in a different place, so that a run can carry on across the wrapped edge
+         let first = first_col(grid, rng);
+         for c in from_cut(first, grid.width) {
+            let i = floor_start + r * grid.width + c;
+            if !grid.is_enabled(i) {
+               continue;
+            }
+            obs.observe(CarveEvent::Visit(i));
+            let north = grid.has_neighbor_north(i) && r != top;
+            let east = grid.has_neighbor_east(i) && grid.east(i) != floor_start + r * grid.width + first;
+            if north && east {
+               cur_run.push(i);
+               obs.observe(CarveEvent::FrontierAdd(i));
+               if rng.random_bool(0.5) {
+                  let chosen = cur_run.iter().choose(rng).copied().unwrap();
+                  grid.connect_cell_north(chosen);
+                  obs.observe(CarveEvent::Connect(chosen, grid.north(chosen)));
+                  for x in cur_run.drain(..) {
+                     obs.observe(CarveEvent::FrontierRemove(x));
+                  }
+               } else {
+                  grid.connect_cell_east(i);
+                  obs.observe(CarveEvent::Connect(i, grid.east(i)));
+               }
+            } else if north {
+               cur_run.push(i);
+               obs.observe(CarveEvent::FrontierAdd(i));
+               let chosen = cur_run.iter().choose(rng).copied().unwrap();
+               grid.connect_cell_north(chosen);
+               obs.observe(CarveEvent::Connect(chosen, grid.north(chosen)));
+               for x in cur_run.drain(..) {
+                  obs.observe(CarveEvent::FrontierRemove(x));
+               }
+            } else if east {
+               grid.connect_cell_east(i);
+               obs.observe(CarveEvent::Connect(i, grid.east(i)));
+            } else if !cur_run.is_empty() {
+               // only reachable on masked grids: the run was cut short by a masked off cell
+               let chosen = cur_run.iter().choose(rng).copied().unwrap();
+               grid.connect_cell_north(chosen);
+               obs.observe(CarveEvent::Connect(chosen, grid.north(chosen)));
+               for x in cur_run.drain(..) {
+                  obs.observe(CarveEvent::FrontierRemove(x));
+               }
+            }
          }
-      } else if grid.has_neighbor_north(i) {
-         cur_run.push(i);
-         grid.connect_cell_north(cur_run.iter().choose(rng).copied().unwrap());
-         cur_run.clear();
-      } else if grid.has_neighbor_east(i) {
-         grid.connect_cell_east(i);
       }
    }
+   join_masked_regions(grid, rng, obs);
+   join_floors(grid, rng, obs);
+}
+
+pub fn aldous_broder<G: MazeGraph, R: Rng>(grid: &mut G, rng: &mut R) {
+   aldous_broder_observed(grid, rng, &mut ());
 }
 
-pub fn aldous_broder<R: Rng>(grid: &mut Grid, rng: &mut R) {
+fn aldous_broder_observed<G: MazeGraph, R: Rng, O: CarveObserver>(grid: &mut G, rng: &mut R, obs: &mut O) {
    let mut neighbors = Vec::with_capacity(4);
-   let mut visited = vec![false; grid.size()];
-   let mut cur_index = (0..grid.size()).choose(rng).unwrap();
-   visited[cur_index] = true;
-   while visited.iter().any(|x| !x) {
+   let mut visited = initial_visited(grid);
+   let mut cur_index = random_cell(grid, rng);
+   visited.insert(cur_index);
+   obs.observe(CarveEvent::Visit(cur_index));
+   let mut unvisited = grid.num_enabled() - 1;
+   while unvisited > 0 {
       neighbors.clear();
       grid.neighbors(cur_index, &mut neighbors);
       let target = neighbors.iter().choose(rng).copied().unwrap();
-      if !visited[target] {
-         grid.connect_neighbors(cur_index, target);
+      if !visited.get(target) {
+         unvisited -= 1;
+         grid.link(cur_index, target);
+         obs.observe(CarveEvent::Connect(cur_index, target));
+         obs.observe(CarveEvent::Visit(target));
       }
       cur_index = target;
-      visited[cur_index] = true;
+      visited.insert(cur_index);
    }
 }
 
-pub fn wilson<R: Rng>(grid: &mut Grid, rng: &mut R) {
+pub fn wilson<G: MazeGraph, R: Rng>(grid: &mut G, rng: &mut R) {
+   wilson_observed(grid, rng, &mut ());
+}
+
+fn wilson_observed<G: MazeGraph, R: Rng, O: CarveObserver>(grid: &mut G, rng: &mut R, obs: &mut O) {
    let mut neighbors = Vec::with_capacity(4);
-   let mut visited = vec![false; grid.size()];
-   let mut walker_path: Vec<usize> = vec![(0..grid.size()).choose(rng).unwrap()];
-   visited[0] = true;
-   while visited.iter().any(|x| !x) {
-      if visited[*walker_path.last().unwrap()] {
+   let mut visited = initial_visited(grid);
+   let mut walker_path: Vec<usize> = vec![random_cell(grid, rng)];
+   let first = first_cell(grid);
+   visited.insert(first);
+   obs.observe(CarveEvent::Visit(first));
+   obs.observe(CarveEvent::FrontierAdd(walker_path[0]));
+   let mut unvisited = grid.num_enabled() - 1;
+   while unvisited > 0 {
+      if visited.get(*walker_path.last().unwrap()) {
          for window in walker_path.windows(2) {
-            visited[window[0]] = true;
-            visited[window[1]] = true;
-            grid.connect_neighbors(window[0], window[1]);
+            obs.observe(CarveEvent::FrontierRemove(window[0]));
+            if !visited.get(window[0]) {
+               unvisited -= 1;
+               obs.observe(CarveEvent::Visit(window[0]));
+            }
+            visited.insert(window[0]);
+            grid.link(window[0], window[1]);
+            obs.observe(CarveEvent::Connect(window[0], window[1]));
          }
+         obs.observe(CarveEvent::FrontierRemove(*walker_path.last().unwrap()));
          walker_path.clear();
-         walker_path.push((0..grid.size()).choose(rng).unwrap());
+         walker_path.push(random_cell(grid, rng));
+         obs.observe(CarveEvent::FrontierAdd(walker_path[0]));
       } else {
          neighbors.clear();
          grid.neighbors(*walker_path.last().unwrap(), &mut neighbors);
          let target = neighbors.iter().choose(rng).copied().unwrap();
          if let Some(i) = walker_path.iter().rposition(|i| *i == target) {
-            walker_path.truncate(i + 1);
+            // erase the loop
+            for x in walker_path.drain(i + 1..) {
+               obs.observe(CarveEvent::FrontierRemove(x));
+            }
          } else {
             walker_path.push(target);
+            obs.observe(CarveEvent::FrontierAdd(target));
          }
       }
    }
+   for x in walker_path {
+      obs.observe(CarveEvent::FrontierRemove(x));
+   }
+}
+
+pub fn hunt_and_kill<G: MazeGraph, R: Rng>(grid: &mut G, rng: &mut R) {
+   hunt_and_kill_observed(grid, rng, &mut ());
 }
 
-pub fn hunt_and_kill<R: Rng>(grid: &mut Grid, rng: &mut R) {
+fn hunt_and_kill_observed<G: MazeGraph, R: Rng, O: CarveObserver>(grid: &mut G, rng: &mut R, obs: &mut O) {
    let mut neighbors = Vec::with_capacity(4);
-   let mut visited = vec![false; grid.size()];
-   visited[0] = true;
-   let mut cur_index = 0;
+   let mut visited = initial_visited(grid);
+   let mut cur_index = first_cell(grid);
+   visited.insert(cur_index);
+   obs.observe(CarveEvent::Visit(cur_index));
    'outer: loop {
       neighbors.clear();
       grid.neighbors(cur_index, &mut neighbors);
-      neighbors.retain(|i| !visited[*i]);
+      neighbors.retain(|i| !visited.get(*i));
       if neighbors.is_empty() {
          // HUNT
          for i in 0..grid.size() {
             // unvisited...
-            if visited[i] {
+            if visited.get(i) {
                continue;
             }
 
             // ...with at least one visited neighbor
             neighbors.clear();
             grid.neighbors(i, &mut neighbors);
-            neighbors.retain(|j| visited[*j]);
+            neighbors.retain(|j| visited.get(*j));
             if neighbors.is_empty() {
                continue;
             }
 
             // choose a visited neighbor, connect
             let target = neighbors.iter().choose(rng).copied().unwrap();
-            grid.connect_neighbors(i, target);
+            grid.link(i, target);
+            obs.observe(CarveEvent::Connect(i, target));
             cur_index = i;
-            visited[i] = true;
+            visited.insert(i);
+            obs.observe(CarveEvent::Visit(i));
             continue 'outer;
          }
          // didn't find any cells in hunt
@@ -195,42 +650,115 @@ pub fn hunt_and_kill<R: Rng>(grid: &mut Grid, rng: &mut R) {
          break;
       }
       let target = neighbors.iter().choose(rng).copied().unwrap();
-      grid.connect_neighbors(cur_index, target);
+      grid.link(cur_index, target);
+      obs.observe(CarveEvent::Connect(cur_index, target));
       cur_index = target;
-      visited[cur_index] = true;
+      visited.insert(cur_index);
+      obs.observe(CarveEvent::Visit(cur_index));
    }
 }
 
-pub fn recursive_backtracker<R: Rng>(grid: &mut Grid, rng: &mut R) {
+pub fn recursive_backtracker<G: MazeGraph, R: Rng>(grid: &mut G, rng: &mut R) {
+   recursive_backtracker_observed(grid, rng, &mut ());
+}
+
+fn recursive_backtracker_observed<G: MazeGraph, R: Rng, O: CarveObserver>(grid: &mut G, rng: &mut R, obs: &mut O) {
    let mut neighbors = Vec::with_capacity(4);
-   let mut stack = vec![0];
-   let mut visited = vec![false; grid.size()];
-   visited[0] = true;
+   let start = first_cell(grid);
+   let mut stack = vec![start];
+   let mut visited = initial_visited(grid);
+   visited.insert(start);
+   obs.observe(CarveEvent::Visit(start));
+   obs.observe(CarveEvent::FrontierAdd(start));
    while !stack.is_empty() {
       neighbors.clear();
       grid.neighbors(*stack.last().unwrap(), &mut neighbors);
-      neighbors.retain(|i| !visited[*i]);
+      neighbors.retain(|i| !visited.get(*i));
       if neighbors.is_empty() {
-         stack.pop();
+         obs.observe(CarveEvent::FrontierRemove(stack.pop().unwrap()));
       } else {
          let target = neighbors.iter().choose(rng).copied().unwrap();
-         grid.connect_neighbors(*stack.last().unwrap(), target);
+         grid.link(*stack.last().unwrap(), target);
+         obs.observe(CarveEvent::Connect(*stack.last().unwrap(), target));
          stack.push(target);
-         visited[target] = true;
+         visited.insert(target);
+         obs.observe(CarveEvent::Visit(target));
+         obs.observe(CarveEvent::FrontierAdd(target));
       }
    }
 }
 
-pub fn kruskal<R: Rng>(grid: &mut Grid, rng: &mut R) {
+pub fn kruskal<G: MazeGraph, R: Rng>(grid: &mut G, rng: &mut R) {
+   kruskal_observed(grid, rng, &mut ());
+}
+
+fn kruskal_observed<G: MazeGraph, R: Rng, O: CarveObserver>(grid: &mut G, rng: &mut R, obs: &mut O) {
    let mut disjoint_set = DisjointSet::new(grid.size());
    let mut edges = Vec::with_capacity(grid.size() * 2);
+   let mut neighbors = Vec::with_capacity(4);
    for i in 0..grid.size() {
-      if grid.has_neighbor_south(i) {
-         edges.push((i, i + grid.width))
+      neighbors.clear();
+      grid.neighbors(i, &mut neighbors);
+      // every wall is shared by two cells, only take it from the lower one
+      edges.extend(neighbors.iter().filter(|j| **j > i).map(|j| (i, *j)));
+   }
+   edges.shuffle(rng);
+   for edge in edges {
+      if disjoint_set.find(edge.0) == disjoint_set.find(edge.1) {
+         continue;
       }
-      if grid.has_neighbor_east(i) {
-         edges.push((i, i + 1))
+      disjoint_set.union(edge.0, edge.1);
+      grid.link(edge.0, edge.1);
+      obs.observe(CarveEvent::Connect(edge.0, edge.1));
+   }
+}
+
+/// Kruskal's algorithm for weave mazes. Before any passages are carved, crossings (a corridor passing over a tunnel)
+/// are scattered across the grid, which would rarely come about by chance.
+///
+/// `crossing_density` is the number of attempts at placing a crossing, as a fraction of the number of cells.
+pub fn kruskal_weave<R: Rng>(grid: &mut WeaveGrid, rng: &mut R, crossing_density: f64) {
+   let size = grid.grid().size();
+   let width = grid.grid().width;
+   let mut disjoint_set = DisjointSet::new(size);
+   let mut crossings = vec![false; size];
+   let attempts = (size as f64 * crossing_density) as usize;
+   for _ in 0..attempts {
+      let i = random_cell(grid.grid(), rng);
+      let has_all_neighbors = grid.grid().has_neighbor_north(i)
+         && grid.grid().has_neighbor_south(i)
+         && grid.grid().has_neighbor_east(i)
+         && grid.grid().has_neighbor_west(i);
+      if !has_all_neighbors || grid.grid().cell(i).num_connections() != 0 {
+         continue;
+      }
+      let (north, south, east, west) = (i - width, i + width, i + 1, i - 1);
+      // a crossing must not touch another one, and all four neighbors must still be apart, or a loop would be made
+      let mut sets = [north, south, east, west].map(|x| disjoint_set.find(x));
+      sets.sort_unstable();
+      if [north, south, east, west].iter().any(|x| crossings[*x]) || sets.windows(2).any(|x| x[0] == x[1]) {
+         continue;
       }
+      let ((over_1, over_2), (under_1, under_2)) = if rng.random_bool(0.5) {
+         ((west, east), (north, south))
+      } else {
+         ((north, south), (west, east))
+      };
+      grid.link(over_1, i);
+      grid.link(i, over_2);
+      grid.link(under_1, under_2);
+      disjoint_set.union(over_1, i);
+      disjoint_set.union(i, over_2);
+      disjoint_set.union(under_1, under_2);
+      crossings[i] = true;
+   }
+   let mut neighbors = Vec::with_capacity(4);
+   let mut edges = Vec::new();
+   for i in (0..size).filter(|i| !crossings[*i]) {
+      neighbors.clear();
+      grid.grid().neighbors(i, &mut neighbors);
+      // every wall is shared by two cells, only take it from the lower one
+      edges.extend(neighbors.iter().filter(|j| **j > i && !crossings[**j]).map(|j| (i, *j)));
    }
    edges.shuffle(rng);
    for edge in edges {
@@ -238,85 +766,170 @@ pub fn kruskal<R: Rng>(grid: &mut Grid, rng: &mut R) {
          continue;
       }
       disjoint_set.union(edge.0, edge.1);
-      grid.connect_neighbors(edge.0, edge.1);
+      grid.link(edge.0, edge.1);
    }
 }
 
 pub fn eller<R: Rng>(grid: &mut Grid, rng: &mut R) {
+   eller_observed(grid, rng, &mut ());
+}
+
+fn eller_observed<R: Rng, O: CarveObserver>(grid: &mut Grid, rng: &mut R, obs: &mut O) {
    let mut disjoint_set = DisjointSet::new(grid.size());
    let mut sets_to_elems_in_set: Vec<Vec<usize>> = vec![vec![]; grid.size()];
    let mut sets_in_row: Vec<usize> = Vec::with_capacity(grid.width);
-   for r in 0..grid.height {
-      let start_of_row = r * grid.width;
-      let end_of_row = start_of_row + (grid.width - 1);
-      // connect within row
-      for i in start_of_row..end_of_row {
-         if disjoint_set.find(i) == disjoint_set.find(i + 1) {
-            continue;
+   // each floor is carved on its own, and joined up afterwards
+   for floor_start in (0..grid.size()).step_by(grid.floor_size()) {
+      // the sets keep a row from joining up with itself, even across an edge that wraps, but there are no sets to
+      // keep the last row from joining up with the first, so a grid that wraps north to south is cut open
+      let top = first_row(grid, rng);
+      for (row_num, r) in from_cut(top, grid.height).enumerate() {
+         let last_row = row_num == grid.height - 1;
+         let start_of_row = floor_start + r * grid.width;
+         let end_of_row = start_of_row + (grid.width - 1);
+         // connect within row
+         for i in start_of_row..=end_of_row {
+            if !grid.has_neighbor_east(i) || disjoint_set.find(i) == disjoint_set.find(grid.east(i)) {
+               continue;
+            }
+            if last_row || rng.random_bool(0.5) {
+               disjoint_set.union(i, grid.east(i));
+               grid.connect_cell_east(i);
+               obs.observe(CarveEvent::Connect(i, grid.east(i)));
+            }
          }
-         if r == (grid.height - 1) || rng.random_bool(0.5) {
-            disjoint_set.union(i, i + 1);
-            grid.connect_cell_east(i);
+         if last_row {
+            break;
          }
-      }
-      if r == (grid.height - 1) {
-         break;
-      }
-      // connect one representative of each set in row south
-      for list in sets_to_elems_in_set.iter_mut() {
-         list.clear();
-      }
-      sets_in_row.clear();
-      for i in start_of_row..=end_of_row {
-         sets_to_elems_in_set[disjoint_set.find(i)].push(i);
-         sets_in_row.push(disjoint_set.find(i));
-      }
-      sets_in_row.sort_unstable();
-      sets_in_row.dedup();
-      for set_in_row in sets_in_row.iter() {
-         sets_to_elems_in_set[*set_in_row].shuffle(rng);
-         let chosen_rep = sets_to_elems_in_set[*set_in_row][0];
-         disjoint_set.union(chosen_rep, chosen_rep + grid.width);
-         grid.connect_cell_south(chosen_rep);
-         for elem in sets_to_elems_in_set[*set_in_row].iter().skip(1) {
-            if rng.random_bool(0.333) {
-               disjoint_set.union(*elem, *elem + grid.width);
-               grid.connect_cell_south(*elem);
+         // connect one representative of each set in row south
+         for list in sets_to_elems_in_set.iter_mut() {
+            list.clear();
+         }
+         sets_in_row.clear();
+         for i in start_of_row..=end_of_row {
+            if !grid.has_neighbor_south(i) {
+               continue;
+            }
+            sets_to_elems_in_set[disjoint_set.find(i)].push(i);
+            sets_in_row.push(disjoint_set.find(i));
+         }
+         sets_in_row.sort_unstable();
+         sets_in_row.dedup();
+         for set_in_row in sets_in_row.iter() {
+            sets_to_elems_in_set[*set_in_row].shuffle(rng);
+            let chosen_rep = sets_to_elems_in_set[*set_in_row][0];
+            disjoint_set.union(chosen_rep, grid.south(chosen_rep));
+            grid.connect_cell_south(chosen_rep);
+            obs.observe(CarveEvent::Connect(chosen_rep, grid.south(chosen_rep)));
+            for elem in sets_to_elems_in_set[*set_in_row].iter().skip(1) {
+               if rng.random_bool(0.333) {
+                  disjoint_set.union(*elem, grid.south(*elem));
+                  grid.connect_cell_south(*elem);
+                  obs.observe(CarveEvent::Connect(*elem, grid.south(*elem)));
+               }
             }
          }
       }
    }
+   join_masked_regions(grid, rng, obs);
+   join_floors(grid, rng, obs);
 }
 
 // not really a maze at all
 pub fn empty(grid: &mut Grid) {
-   // make the grid fully connected
+   empty_observed(grid, &mut ());
+}
+
+fn empty_observed<O: CarveObserver>(grid: &mut Grid, obs: &mut O) {
+   // make the grid fully connected, across wrapped edges too
    for i in 0..grid.size() {
       if grid.has_neighbor_south(i) {
-         grid.connect_cell_south(i)
+         grid.connect_cell_south(i);
+         obs.observe(CarveEvent::Connect(i, grid.south(i)));
       }
       if grid.has_neighbor_east(i) {
-         grid.connect_cell_east(i)
+         grid.connect_cell_east(i);
+         obs.observe(CarveEvent::Connect(i, grid.east(i)));
+      }
+      if grid.has_neighbor_up(i) {
+         grid.connect_cell_up(i);
+         obs.observe(CarveEvent::Connect(i, grid.up(i)));
       }
    }
 }
 
 pub fn recursive_division<R: Rng>(grid: &mut Grid, rng: &mut R) {
+   recursive_division_observed(grid, rng, &mut ());
+}
+
+fn recursive_division_observed<R: Rng, O: CarveObserver>(grid: &mut Grid, rng: &mut R, obs: &mut O) {
+   /// `x` and `y` count from where the floor was cut open (see `first_col` and `first_row`), not from its edges
    struct Rectangle {
+      /// The index of the first cell of the floor the rectangle is on
+      floor_start: usize,
       x: usize,
       y: usize,
       width: usize,
       height: usize,
    }
 
-   empty(grid);
+   empty_observed(grid, obs);
+
+   // where a passage can be left in the wall being put up
+   let mut doors = Vec::new();
+
+   // floors are split apart first, leaving one staircase between each floor and the next
+   for floor_start in (0..grid.size() - grid.floor_size()).step_by(grid.floor_size()) {
+      doors.clear();
+      for cell in floor_start..(floor_start + grid.floor_size()) {
+         if grid.has_neighbor_up(cell) {
+            grid.disconnect_cell_up(cell);
+            obs.observe(CarveEvent::Disconnect(cell, grid.up(cell)));
+            doors.push(cell);
+         }
+      }
+      if let Some(cell) = doors.iter().copied().choose(rng) {
+         grid.connect_cell_up(cell);
+         obs.observe(CarveEvent::Connect(cell, grid.up(cell)));
+      }
+   }
+
+   // a wall all the way along each cut, with no door in it
+   let mut cuts = Vec::with_capacity(grid.depth);
+   for floor_start in (0..grid.size()).step_by(grid.floor_size()) {
+      let (top, first) = (first_row(grid, rng), first_col(grid, rng));
+      cuts.push((top, first));
+      for r in 0..grid.height {
+         let cell = floor_start + r * grid.width + first;
+         if grid.wrap.east_west && grid.has_neighbor_west(cell) {
+            grid.disconnect_cell_west(cell);
+            obs.observe(CarveEvent::Disconnect(cell, grid.west(cell)));
+         }
+      }
+      for c in 0..grid.width {
+         let cell = floor_start + top * grid.width + c;
+         if grid.wrap.north_south && grid.has_neighbor_north(cell) {
+            grid.disconnect_cell_north(cell);
+            obs.observe(CarveEvent::Disconnect(cell, grid.north(cell)));
+         }
+      }
+   }
+   let (width, height, floor_size) = (grid.width, grid.height, grid.floor_size());
+   let cell_at = |floor_start: usize, x: usize, y: usize| {
+      let (top, first) = cuts[floor_start / floor_size];
+      floor_start + ((y + top) % height) * width + (x + first) % width
+   };
 
-   let mut rects = vec![Rectangle {
-      x: 0,
-      y: 0,
-      width: grid.width,
-      height: grid.height,
-   }];
+   let mut rects: Vec<Rectangle> = (0..grid.size())
+      .step_by(grid.floor_size())
+      .map(|floor_start| Rectangle {
+         floor_start,
+         x: 0,
+         y: 0,
+         width: grid.width,
+         height: grid.height,
+      })
+      .collect();
    while let Some(rect) = rects.pop() {
       if rect.width <= 1 || rect.height <= 1 {
          continue;
@@ -325,19 +938,29 @@ pub fn recursive_division<R: Rng>(grid: &mut Grid, rng: &mut R) {
       // divide vertically
       if rect.height <= rect.width {
          let mid_x = rect.x + rect.width / 2;
+         doors.clear();
          for i in rect.y..(rect.y + rect.height) {
-            grid.disconnect_cell_west(i * grid.width + mid_x);
+            let cell = cell_at(rect.floor_start, mid_x, i);
+            if grid.has_neighbor_west(cell) {
+               grid.disconnect_cell_west(cell);
+               obs.observe(CarveEvent::Disconnect(cell, grid.west(cell)));
+               doors.push(cell);
+            }
+         }
+         if let Some(cell) = doors.iter().copied().choose(rng) {
+            grid.connect_cell_west(cell);
+            obs.observe(CarveEvent::Connect(cell, grid.west(cell)));
          }
-         let random_i = (rect.y..(rect.y + rect.height)).choose(rng).unwrap();
-         grid.connect_cell_west(random_i * grid.width + mid_x);
          // divide
          rects.push(Rectangle {
+            floor_start: rect.floor_start,
             x: rect.x,
             y: rect.y,
             width: rect.width / 2,
             height: rect.height,
          });
          rects.push(Rectangle {
+            floor_start: rect.floor_start,
             x: rect.x + rect.width / 2,
             y: rect.y,
             width: (rect.width / 2) + (rect.width % 2),
@@ -345,19 +968,29 @@ pub fn recursive_division<R: Rng>(grid: &mut Grid, rng: &mut R) {
          });
       } else {
          let mid_y = rect.y + rect.height / 2;
+         doors.clear();
          for i in rect.x..(rect.x + rect.width) {
-            grid.disconnect_cell_north(mid_y * grid.width + i);
+            let cell = cell_at(rect.floor_start, i, mid_y);
+            if grid.has_neighbor_north(cell) {
+               grid.disconnect_cell_north(cell);
+               obs.observe(CarveEvent::Disconnect(cell, grid.north(cell)));
+               doors.push(cell);
+            }
+         }
+         if let Some(cell) = doors.iter().copied().choose(rng) {
+            grid.connect_cell_north(cell);
+            obs.observe(CarveEvent::Connect(cell, grid.north(cell)));
          }
-         let random_i = (rect.x..(rect.x + rect.width)).choose(rng).unwrap();
-         grid.connect_cell_north(mid_y * grid.width + random_i);
          // divide
          rects.push(Rectangle {
+            floor_start: rect.floor_start,
             x: rect.x,
             y: rect.y,
             width: rect.width,
             height: rect.height / 2,
          });
          rects.push(Rectangle {
+            floor_start: rect.floor_start,
             x: rect.x,
             y: rect.y + rect.height / 2,
             width: rect.width,
@@ -365,11 +998,20 @@ pub fn recursive_division<R: Rng>(grid: &mut Grid, rng: &mut R) {
          });
       }
    }
+   join_masked_regions(grid, rng, obs);
+}
+
+pub fn prim_simplified<G: MazeGraph, R: Rng>(grid: &mut G, rng: &mut R) {
+   prim_simplified_observed(grid, rng, &mut ());
 }
 
-pub fn prim_simplified<R: Rng>(grid: &mut Grid, rng: &mut R) {
+fn prim_simplified_observed<G: MazeGraph, R: Rng, O: CarveObserver>(grid: &mut G, rng: &mut R, obs: &mut O) {
+   let mut visited = initial_visited(grid);
    let mut frontier = Vec::new();
-   frontier.push(rng.random_range(0..grid.size()));
+   frontier.push(random_cell(grid, rng));
+   visited.insert(frontier[0]);
+   obs.observe(CarveEvent::Visit(frontier[0]));
+   obs.observe(CarveEvent::FrontierAdd(frontier[0]));
 
    let mut neighbors: Vec<usize> = Vec::with_capacity(4);
    let mut available_neighbors: Vec<usize> = Vec::with_capacity(4);
@@ -380,19 +1022,28 @@ pub fn prim_simplified<R: Rng>(grid: &mut Grid, rng: &mut R) {
       neighbors.clear();
       grid.neighbors(i, &mut neighbors);
       available_neighbors.clear();
-      available_neighbors.extend(neighbors.iter().filter(|x| grid[**x].num_connections() == 0));
+      available_neighbors.extend(neighbors.iter().filter(|x| !visited.get(**x)));
 
       if available_neighbors.is_empty() {
          frontier.swap_remove(frontier_index);
+         obs.observe(CarveEvent::FrontierRemove(i));
       } else {
          let chosen_neighbor = available_neighbors.iter().choose(rng).copied().unwrap();
-         grid.connect_neighbors(i, chosen_neighbor);
+         grid.link(i, chosen_neighbor);
+         visited.insert(chosen_neighbor);
+         obs.observe(CarveEvent::Connect(i, chosen_neighbor));
+         obs.observe(CarveEvent::Visit(chosen_neighbor));
          frontier.push(chosen_neighbor);
+         obs.observe(CarveEvent::FrontierAdd(chosen_neighbor));
       }
    }
 }
 
-pub fn prim_true<R: Rng>(grid: &mut Grid, rng: &mut R) {
+pub fn prim_true<G: MazeGraph, R: Rng>(grid: &mut G, rng: &mut R) {
+   prim_true_observed(grid, rng, &mut ());
+}
+
+fn prim_true_observed<G: MazeGraph, R: Rng, O: CarveObserver>(grid: &mut G, rng: &mut R, obs: &mut O) {
    #[derive(PartialEq, Eq)]
    struct FrontierNode {
       grid_index: usize,
@@ -420,12 +1071,16 @@ pub fn prim_true<R: Rng>(grid: &mut Grid, rng: &mut R) {
       costs
    };
 
+   let mut visited = initial_visited(grid);
    let mut frontier = BinaryHeap::new();
-   let start = rng.random_range(0..grid.size());
+   let start = random_cell(grid, rng);
+   visited.insert(start);
    frontier.push(FrontierNode {
       grid_index: start,
       cost: costs[start],
    });
+   obs.observe(CarveEvent::Visit(start));
+   obs.observe(CarveEvent::FrontierAdd(start));
 
    let mut neighbors: Vec<usize> = Vec::with_capacity(4);
    let mut available_neighbors: Vec<usize> = Vec::with_capacity(4);
@@ -434,17 +1089,298 @@ pub fn prim_true<R: Rng>(grid: &mut Grid, rng: &mut R) {
       neighbors.clear();
       grid.neighbors(frn.grid_index, &mut neighbors);
       available_neighbors.clear();
-      available_neighbors.extend(neighbors.iter().filter(|x| grid[**x].num_connections() == 0));
+      available_neighbors.extend(neighbors.iter().filter(|x| !visited.get(**x)));
 
       if available_neighbors.is_empty() {
+         obs.observe(CarveEvent::FrontierRemove(frn.grid_index));
          frontier.pop();
       } else {
          let chosen_neighbor = *available_neighbors.iter().min_by_key(|x| costs[**x]).unwrap();
-         grid.connect_neighbors(frn.grid_index, chosen_neighbor);
+         grid.link(frn.grid_index, chosen_neighbor);
+         visited.insert(chosen_neighbor);
+         obs.observe(CarveEvent::Connect(frn.grid_index, chosen_neighbor));
+         obs.observe(CarveEvent::Visit(chosen_neighbor));
          frontier.push(FrontierNode {
             grid_index: chosen_neighbor,
             cost: costs[chosen_neighbor],
          });
+         obs.observe(CarveEvent::FrontierAdd(chosen_neighbor));
+      }
+   }
+}
+
+pub fn growing_tree<G: MazeGraph, R: Rng>(grid: &mut G, rng: &mut R, selection: CellSelection) {
+   growing_tree_observed(grid, rng, selection, &mut ());
+}
+
+fn growing_tree_observed<G: MazeGraph, R: Rng, O: CarveObserver>(
+   grid: &mut G,
+   rng: &mut R,
+   selection: CellSelection,
+   obs: &mut O,
+) {
+   let mut neighbors = Vec::with_capacity(4);
+   let mut visited = initial_visited(grid);
+   let start = random_cell(grid, rng);
+   visited.insert(start);
+   obs.observe(CarveEvent::Visit(start));
+   // ordered from oldest to newest
+   let mut active = VecDeque::new();
+   active.push_back(start);
+   obs.observe(CarveEvent::FrontierAdd(start));
+   while !active.is_empty() {
+      let active_index = selection.choose(active.len(), rng);
+      let i = active[active_index];
+
+      neighbors.clear();
+      grid.neighbors(i, &mut neighbors);
+      neighbors.retain(|x| !visited.get(*x));
+
+      if neighbors.is_empty() {
+         active.remove(active_index);
+         obs.observe(CarveEvent::FrontierRemove(i));
+      } else {
+         let target = neighbors.iter().choose(rng).copied().unwrap();
+         grid.link(i, target);
+         obs.observe(CarveEvent::Connect(i, target));
+         visited.insert(target);
+         obs.observe(CarveEvent::Visit(target));
+         active.push_back(target);
+         obs.observe(CarveEvent::FrontierAdd(target));
+      }
+   }
+}
+
+/// Removes dead ends from a carved maze by knocking down one of their walls, adding loops so that there is more than
+/// one route between cells.
+///
+/// Each dead end is braided with probability `p`, so `0.0` leaves the maze untouched and `1.0` removes every dead end.
+/// Where possible the wall to a neighbor that is also a dead end is removed, which gets rid of two at once.
+///
+/// # Panics
+///
+/// If `p` is not between `0.0` and `1.0`.
+pub fn braid<G: MazeGraph, R: Rng>(grid: &mut G, rng: &mut R, p: f64) {
+   debug_assert!(
+      (0.0..=1.0).contains(&p),
+      "braid probability {} is not between 0 and 1",
+      p
+   );
+   let mut links = Vec::with_capacity(4);
+   let mut num_links = |grid: &G, i: usize| {
+      links.clear();
+      grid.links(i, &mut links);
+      links.len()
+   };
+   let mut dead_ends: Vec<usize> = (0..grid.size()).filter(|i| num_links(grid, *i) == 1).collect();
+   dead_ends.shuffle(rng);
+   let mut neighbors = Vec::with_capacity(4);
+   for i in dead_ends {
+      // an earlier dead end may have already been joined up to this one
+      if num_links(grid, i) != 1 || !rng.random_bool(p) {
+         continue;
+      }
+      neighbors.clear();
+      grid.neighbors(i, &mut neighbors);
+      neighbors.retain(|x| !grid.is_linked(i, *x));
+      let best = neighbors
+         .iter()
+         .copied()
+         .filter(|x| num_links(grid, *x) == 1)
+         .choose(rng)
+         .or_else(|| neighbors.iter().copied().choose(rng));
+      if let Some(target) = best {
+         grid.link(i, target);
+      }
+   }
+}
+
+#[cfg(test)]
+mod test {
+   use super::{ALGO_NAMES, ALGOS, Algo, CarveEvent, CellSelection, ParseAlgoError};
+   use crate::graph;
+   use crate::grid::{Grid, Wrap};
+   use crate::mask::Mask;
+   use rand::SeedableRng;
+   use rand_xorshift::XorShiftRng;
+
+   #[test]
+   fn algos_carve_perfect_mazes() {
+      let mut rng = XorShiftRng::seed_from_u64(0);
+      let mut grid = Grid::new(13, 9);
+      let growing_trees = [
+         CellSelection::NEWEST,
+         CellSelection::OLDEST,
+         CellSelection::RANDOM,
+         CellSelection::MIDDLE,
+         CellSelection {
+            newest: 3,
+            oldest: 0,
+            random: 1,
+            middle: 0,
+         },
+      ];
+      for algo in ALGOS
+         .iter()
+         .copied()
+         .chain(growing_trees.iter().copied().map(Algo::GrowingTree))
+      {
+         grid.reset();
+         super::carve_maze(&mut grid, &mut rng, algo);
+         graph::assert_perfect(&grid);
+      }
+   }
+
+   #[test]
+   fn replaying_steps_matches_one_shot() {
+      for algo in ALGOS
+         .iter()
+         .copied()
+         .chain([Algo::GrowingTree(CellSelection::MIDDLE), Algo::Empty])
+      {
+         let mut one_shot = Grid::new(11, 7);
+         super::carve_maze(&mut one_shot, &mut XorShiftRng::seed_from_u64(7), algo);
+
+         let mut stepped = Grid::new(11, 7);
+         let steps = super::carve_maze_steps(&mut stepped, &mut XorShiftRng::seed_from_u64(7), algo);
+
+         let mut replayed = Grid::new(11, 7);
+         for step in steps {
+            match step {
+               CarveEvent::Connect(a, b) => replayed.connect_neighbors(a, b),
+               CarveEvent::Disconnect(a, b) => replayed.disconnect_neighbors(a, b),
+               _ => (),
+            }
+         }
+
+         for i in 0..one_shot.size() {
+            for j in 0..one_shot.size() {
+               let expected = one_shot.check_if_neighbors_and_connected(i, j);
+               assert_eq!(expected, stepped.check_if_neighbors_and_connected(i, j));
+               assert_eq!(expected, replayed.check_if_neighbors_and_connected(i, j));
+            }
+         }
+      }
+   }
+
+   #[test]
+   fn algos_respect_masks() {
+      let mask = Mask::from_ascii(
+         "XX.....XX\n\
+          X.......X\n\
+          ...XXX...\n\
+          ...XXX...\n\
+          X.......X\n\
+          XX.....XX\n",
+      )
+      .unwrap();
+      let mut rng = XorShiftRng::seed_from_u64(3);
+      let mut grid = Grid::new_masked(mask);
+      for algo in ALGOS.iter().copied().chain([Algo::GrowingTree(CellSelection::OLDEST)]) {
+         for _ in 0..20 {
+            grid.reset();
+            super::carve_maze(&mut grid, &mut rng, algo);
+            for i in (0..grid.size()).filter(|i| !grid.is_enabled(*i)) {
+               assert_eq!(grid.cell(i).num_connections(), 0);
+            }
+            graph::assert_perfect(&grid);
+         }
+      }
+   }
+
+   #[test]
+   fn braiding_removes_dead_ends() {
+      let mut rng = XorShiftRng::seed_from_u64(4);
+      let mut grid = Grid::new(20, 20);
+      super::recursive_backtracker(&mut grid, &mut rng);
+      let before = grid.dead_ends().count();
+      super::braid(&mut grid, &mut rng, 0.0);
+      assert_eq!(grid.dead_ends().count(), before);
+      super::braid(&mut grid, &mut rng, 0.5);
+      let after = grid.dead_ends().count();
+      assert!(after < before && after > 0);
+      super::braid(&mut grid, &mut rng, 1.0);
+      assert_eq!(grid.dead_ends().count(), 0);
+   }
+
+   #[test]
+   fn algos_carve_wrapping_grids() {
+      let mut rng = XorShiftRng::seed_from_u64(5);
+      for wrap in [Wrap::CYLINDER, Wrap::TORUS] {
+         let mut grid = Grid::new_wrapping(13, 9, wrap);
+         for algo in ALGOS {
+            grid.reset();
+            super::carve_maze(&mut grid, &mut rng, algo);
+            graph::assert_perfect(&grid);
+         }
+         // passages cross the wrapped edges like any others, even for the generators that work row by row
+         for algo in ALGOS.iter().copied().chain([Algo::Empty]) {
+            let (mut west_crossings, mut north_crossings) = (0, 0);
+            for _ in 0..5 {
+               grid.reset();
+               super::carve_maze(&mut grid, &mut rng, algo);
+               if algo != Algo::Empty {
+                  graph::assert_perfect(&grid);
+               }
+               west_crossings += (0..grid.height)
+                  .filter(|r| grid.cell(r * grid.width).west_connected)
+                  .count();
+               north_crossings += (0..grid.width).filter(|c| grid.cell(*c).north_connected).count();
+            }
+            assert!(west_crossings > 0, "{}", algo);
+            assert_eq!(north_crossings > 0, wrap.north_south, "{}", algo);
+         }
+         grid.reset();
+         super::empty(&mut grid);
+         let mut neighbors = Vec::new();
+         for i in 0..grid.size() {
+            neighbors.clear();
+            grid.neighbors(i, &mut neighbors);
+            assert_eq!(usize::from(grid.cell(i).num_connections()), neighbors.len());
+         }
+      }
+   }
+
+   #[test]
+   fn algos_carve_between_floors() {
+      let mut rng = XorShiftRng::seed_from_u64(6);
+      let mut grid = Grid::new_3d(7, 5, 3);
+      for algo in ALGOS {
+         grid.reset();
+         super::carve_maze(&mut grid, &mut rng, algo);
+         graph::assert_perfect(&grid);
+         assert!((0..grid.size()).any(|i| grid.cell(i).up_connected));
+      }
+   }
+
+   #[test]
+   fn algo_names_round_trip() {
+      for (name, algo) in ALGO_NAMES {
+         assert_eq!(algo.id(), name);
+         assert_eq!(name.parse::<Algo>(), Ok(algo));
+      }
+      for algo in ALGOS {
+         assert_eq!(algo.id().parse::<Algo>(), Ok(algo));
+      }
+      let blend = Algo::GrowingTree(CellSelection {
+         newest: 1,
+         oldest: 2,
+         random: 0,
+         middle: 5,
+      });
+      assert_eq!(blend.id(), "GrowingTree:1,2,0,5");
+      assert_eq!("GrowingTree:1,2,0,5".parse::<Algo>(), Ok(blend));
+      assert_eq!(Algo::Wilson.to_string(), "Wilson's");
+
+      assert_eq!(
+         "Wilsons".parse::<Algo>(),
+         Err(ParseAlgoError::Unknown("Wilsons".to_string()))
+      );
+      for weights in ["1,2,3", "1,2,3,256", "a,b,c,d", ""] {
+         assert_eq!(
+            format!("GrowingTree:{}", weights).parse::<Algo>(),
+            Err(ParseAlgoError::BadWeights(weights.to_string()))
+         );
       }
    }
 }
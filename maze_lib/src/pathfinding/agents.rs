@@ -0,0 +1,500 @@
+//! Solvers that walk the maze like a person inside it would, only ever seeing the cell they are standing in, instead
+//! of searching it from above like the solvers in `algos`. Each one records what it did along the way as a list of
+//! `AgentStep`s, so its walk can be played back.
+//!
+//! The wall followers and the Pledge algorithm need to know left from right, so they only walk a `Grid`, and only
+//! north, south, east and west on it.
+
+use crate::graph::MazeGraph;
+use crate::grid::Grid;
+use fxhash::{FxHashMap, FxHashSet};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AgentStep {
+   /// Walked into the cell
+   Move(usize),
+   /// Scratched a mark into the passage between the two cells, on the way from the first to the second
+   Mark(usize, usize),
+   /// Filled in the cell, as it only leads to a dead end
+   Fill(usize),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AgentOutcome {
+   /// Reached the goal. The path leaves out any detours that came back to where they started.
+   Solved(Box<[usize]>),
+   /// Came back to where it had already been, in the same state, so it would only ever go around the same way again
+   Looped,
+   /// Ran out of ways to go without reaching the goal
+   Unreachable,
+}
+
+pub struct AgentRun {
+   pub outcome: AgentOutcome,
+   pub steps: Box<[AgentStep]>,
+}
+
+impl AgentRun {
+   fn new(outcome: AgentOutcome, steps: Vec<AgentStep>) -> AgentRun {
+      AgentRun {
+         outcome,
+         steps: steps.into_boxed_slice(),
+      }
+   }
+
+   /// Every cell walked into, in order, without the start
+   pub fn moves(&self) -> impl Iterator<Item = usize> + '_ {
+      self.steps.iter().filter_map(|x| match x {
+         AgentStep::Move(i) => Some(*i),
+         _ => None,
+      })
+   }
+}
+
+/// The walk with every detour that came back to where it started cut out
+fn loop_erased(walk: &[usize]) -> Box<[usize]> {
+   let mut path: Vec<usize> = Vec::new();
+   let mut position_on_path = FxHashMap::default();
+   for i in walk.iter().copied() {
+      if let Some(position) = position_on_path.get(&i).copied() {
+         for erased in path.drain(position + 1..) {
+            position_on_path.remove(&erased);
+         }
+      } else {
+         position_on_path.insert(i, path.len());
+         path.push(i);
+      }
+   }
+   path.into_boxed_slice()
+}
+
+/// Which way an agent on a `Grid` is facing
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum Heading {
+   North,
+   East,
+   South,
+   West,
+}
+
+impl Heading {
+   /// Turns right a quarter turn `quarters` times, or left for a negative number
+   fn turn(self, quarters: i64) -> Heading {
+      const CLOCKWISE: [Heading; 4] = [Heading::North, Heading::East, Heading::South, Heading::West];
+      CLOCKWISE[(self as i64 + quarters).rem_euclid(4) as usize]
+   }
+
+   /// The cell one step from `i` in this direction, if there is a passage to it
+   fn step(self, grid: &Grid, i: usize) -> Option<usize> {
+      let cell = grid.cell(i);
+      match self {
+         Heading::North => cell.north_connected.then(|| grid.north(i)),
+         Heading::East => cell.east_connected.then(|| grid.east(i)),
+         Heading::South => cell.south_connected.then(|| grid.south(i)),
+         Heading::West => cell.west_connected.then(|| grid.west(i)),
+      }
+   }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Hand {
+   Left,
+   Right,
+}
+
+/// Keeps one hand on the wall: turns toward that hand wherever it can, and otherwise goes straight, turns away or turns
+/// back, in that order. On a perfect maze that always reaches the goal. On a maze with loops, the wall the hand is on
+/// may never lead to the goal, which it notices as soon as it walks into a cell facing the same way as before.
+pub fn wall_follower(grid: &Grid, start: usize, goal: usize, hand: Hand) -> AgentRun {
+   let mut steps = Vec::new();
+   if !grid.is_enabled(start) || !grid.is_enabled(goal) {
+      return AgentRun::new(AgentOutcome::Unreachable, steps);
+   }
+   let toward_hand = match hand {
+      Hand::Left => -1,
+      Hand::Right => 1,
+   };
+   let mut walk = vec![start];
+   let mut seen = FxHashSet::default();
+   let mut cur = start;
+   let mut heading = Heading::North;
+   while cur != goal {
+      let next = [toward_hand, 0, -toward_hand, 2]
+         .into_iter()
+         .map(|x| heading.turn(x))
+         .find_map(|x| x.step(grid, cur).map(|i| (x, i)));
+      let Some((next_heading, next)) = next else {
+         return AgentRun::new(AgentOutcome::Unreachable, steps);
+      };
+      heading = next_heading;
+      cur = next;
+      steps.push(AgentStep::Move(cur));
+      walk.push(cur);
+      if !seen.insert((cur, heading)) {
+         return AgentRun::new(AgentOutcome::Looped, steps);
+      }
+   }
+   AgentRun::new(AgentOutcome::Solved(loop_erased(&walk)), steps)
+}
+
+/// Walks in a chosen direction, and when a wall is in the way, follows it with the right hand while counting how far
+/// it has turned, a quarter turn right at a time. Once the count is back to zero, it is facing the chosen direction
+/// again and lets go of the wall. Unlike `wall_follower`, that gets it away from walls that go around in a loop.
+///
+/// The chosen direction is toward the goal, along whichever axis it is farther along. The Pledge algorithm is for
+/// finding a way out of a maze, so it is only sure to reach a goal on the edge of the maze. It gives up as `Looped`
+/// when it is back where it was in the same state, or after walking into every cell more than `PLEDGE_MOVES_PER_CELL`
+/// times, since it can go around in circles while turning further each time.
+pub fn pledge(grid: &Grid, start: usize, goal: usize) -> AgentRun {
+   let mut steps = Vec::new();
+   if !grid.is_enabled(start) || !grid.is_enabled(goal) {
+      return AgentRun::new(AgentOutcome::Unreachable, steps);
+   }
+   let (start_row, start_col) = ((start % grid.floor_size()) / grid.width, start % grid.width);
+   let (goal_row, goal_col) = ((goal % grid.floor_size()) / grid.width, goal % grid.width);
+   let chosen = if goal_col.abs_diff(start_col) >= goal_row.abs_diff(start_row) {
+      if goal_col >= start_col {
+         Heading::East
+      } else {
+         Heading::West
+      }
+   } else if goal_row >= start_row {
+      Heading::South
+   } else {
+      Heading::North
+   };
+   let mut walk = vec![start];
+   let mut seen = FxHashSet::default();
+   let mut cur = start;
+   // quarter turns right from the chosen direction, only ever zero while not following a wall
+   let mut turned: i64 = 0;
+   let mut following_wall = false;
+   while cur != goal {
+      let options: &[i64] = if following_wall {
+         &[1, 0, -1, -2]
+      } else {
+         // turn left until the wall is on the right
+         &[0, -1, -2, -3]
+      };
+      let heading = chosen.turn(turned);
+      let Some((turn, next)) = options
+         .iter()
+         .find_map(|x| heading.turn(*x).step(grid, cur).map(|i| (*x, i)))
+      else {
+         return AgentRun::new(AgentOutcome::Unreachable, steps);
+      };
+      turned += turn;
+      following_wall = turned != 0;
+      cur = next;
+      steps.push(AgentStep::Move(cur));
+      walk.push(cur);
+      if !seen.insert((cur, turned)) || walk.len() > grid.size() * PLEDGE_MOVES_PER_CELL {
+         return AgentRun::new(AgentOutcome::Looped, steps);
+      }
+   }
+   AgentRun::new(AgentOutcome::Solved(loop_erased(&walk)), steps)
+}
+
+/// How many times `pledge` can walk into every cell before giving up
+pub const PLEDGE_MOVES_PER_CELL: usize = 16;
+
+/// Trémaux's algorithm: marks every passage it walks through, and never walks through a passage marked twice. At a
+/// junction it hasn't been to, it takes any passage it hasn't marked. Coming to a junction it has been to along a new
+/// passage, it turns back. Otherwise it takes the passage with the fewest marks. That always reaches the goal if it
+/// can be reached, or comes back to the start with every passage marked twice if it can't.
+pub fn tremaux<G: MazeGraph>(grid: &G, start: usize, goal: usize) -> AgentRun {
+   let mut steps = Vec::new();
+   if !grid.is_enabled(start) || !grid.is_enabled(goal) {
+      return AgentRun::new(AgentOutcome::Unreachable, steps);
+   }
+   let passage = |a: usize, b: usize| (a.min(b), a.max(b));
+   let mut marks: FxHashMap<(usize, usize), u8> = FxHashMap::default();
+   let mut walk = vec![start];
+   let mut cur = start;
+   let mut entrance: Option<usize> = None;
+   let mut links = Vec::with_capacity(4);
+   while cur != goal {
+      links.clear();
+      grid.links(cur, &mut links);
+      let marks_on = |i: usize| marks.get(&passage(cur, i)).copied().unwrap_or(0);
+      let others = links.iter().copied().filter(|x| Some(*x) != entrance);
+      let new_junction = others.clone().all(|x| marks_on(x) == 0);
+      let next = match entrance {
+         // a dead end, or a junction that has been visited before reached along a new passage
+         Some(back) if (!new_junction || others.clone().next().is_none()) && marks_on(back) < 2 => Some(back),
+         _ => others
+            .filter(|x| marks_on(*x) < 2)
+            .min_by_key(|x| marks_on(*x))
+            .or(entrance.filter(|x| marks_on(*x) < 2)),
+      };
+      let Some(next) = next else {
+         return AgentRun::new(AgentOutcome::Unreachable, steps);
+      };
+      *marks.entry(passage(cur, next)).or_insert(0) += 1;
+      steps.push(AgentStep::Mark(cur, next));
+      steps.push(AgentStep::Move(next));
+      walk.push(next);
+      entrance = Some(cur);
+      cur = next;
+   }
+   AgentRun::new(AgentOutcome::Solved(loop_erased(&walk)), steps)
+}
+
+/// Fills in every dead end other than the start and the goal, and then every cell that became a dead end, until there
+/// are none left. On a perfect maze, the cells left are the path. On a maze with loops, every path is left, and the
+/// shortest one through the cells left is taken.
+pub fn dead_end_filling<G: MazeGraph>(grid: &G, start: usize, goal: usize) -> AgentRun {
+   let mut steps = Vec::new();
+   if !grid.is_enabled(start) || !grid.is_enabled(goal) {
+      return AgentRun::new(AgentOutcome::Unreachable, steps);
+   }
+   let mut links = Vec::with_capacity(4);
+   let mut open_links: Box<[usize]> = (0..grid.size())
+      .map(|i| {
+         links.clear();
+         grid.links(i, &mut links);
+         links.len()
+      })
+      .collect();
+   let mut filled = vec![false; grid.size()].into_boxed_slice();
+   let mut dead_ends: Vec<usize> = (0..grid.size())
+      .filter(|i| open_links[*i] == 1 && *i != start && *i != goal)
+      .collect();
+   while let Some(i) = dead_ends.pop() {
+      filled[i] = true;
+      steps.push(AgentStep::Fill(i));
+      links.clear();
+      grid.links(i, &mut links);
+      for neighbor in links.iter().copied().filter(|x| !filled[*x]) {
+         open_links[neighbor] -= 1;
+         if open_links[neighbor] == 1 && neighbor != start && neighbor != goal {
+            dead_ends.push(neighbor);
+         }
+      }
+   }
+
+   // a breadth-first search through the cells left
+   let mut parents = vec![usize::MAX; grid.size()].into_boxed_slice();
+   parents[start] = start;
+   let mut open = VecDeque::from([start]);
+   while let Some(cur) = open.pop_front() {
+      if cur == goal {
+         let mut path = vec![goal];
+         while *path.last().unwrap() != start {
+            path.push(parents[*path.last().unwrap()]);
+         }
+         path.reverse();
+         return AgentRun::new(AgentOutcome::Solved(path.into_boxed_slice()), steps);
+      }
+      links.clear();
+      grid.links(cur, &mut links);
+      for i in links.iter().copied() {
+         if !filled[i] && parents[i] == usize::MAX {
+            parents[i] = cur;
+            open.push_back(i);
+         }
+      }
+   }
+   AgentRun::new(AgentOutcome::Unreachable, steps)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Agent {
+   WallFollowerLeft,
+   WallFollowerRight,
+   Pledge,
+   Tremaux,
+   DeadEndFilling,
+}
+
+pub const AGENTS: [Agent; 5] = [
+   Agent::WallFollowerLeft,
+   Agent::WallFollowerRight,
+   Agent::Pledge,
+   Agent::Tremaux,
+   Agent::DeadEndFilling,
+];
+
+impl Agent {
+   /// The name `Agent::from_str` turns back into this agent. Unlike `Display`, it never changes.
+   pub fn id(self) -> &'static str {
+      match self {
+         Agent::WallFollowerLeft => "WallFollowerLeft",
+         Agent::WallFollowerRight => "WallFollowerRight",
+         Agent::Pledge => "Pledge",
+         Agent::Tremaux => "Tremaux",
+         Agent::DeadEndFilling => "DeadEndFilling",
+      }
+   }
+
+   pub fn run(self, grid: &Grid, start: usize, goal: usize) -> AgentRun {
+      match self {
+         Agent::WallFollowerLeft => wall_follower(grid, start, goal, Hand::Left),
+         Agent::WallFollowerRight => wall_follower(grid, start, goal, Hand::Right),
+         Agent::Pledge => pledge(grid, start, goal),
+         Agent::Tremaux => tremaux(grid, start, goal),
+         Agent::DeadEndFilling => dead_end_filling(grid, start, goal),
+      }
+   }
+}
+
+impl Display for Agent {
+   fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+      f.write_str(match self {
+         Agent::WallFollowerLeft => "Wall Follower (Left Hand)",
+         Agent::WallFollowerRight => "Wall Follower (Right Hand)",
+         Agent::Pledge => "Pledge",
+         Agent::Tremaux => "Trémaux's",
+         Agent::DeadEndFilling => "Dead-End Filling",
+      })
+   }
+}
+
+/// The name given isn't the `id` of any `Agent`
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownAgent(pub String);
+
+impl Display for UnknownAgent {
+   fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+      write!(f, "unknown agent {:?}", self.0)
+   }
+}
+
+impl Error for UnknownAgent {}
+
+impl FromStr for Agent {
+   type Err = UnknownAgent;
+
+   fn from_str(s: &str) -> Result<Agent, UnknownAgent> {
+      AGENTS
+         .iter()
+         .copied()
+         .find(|x| x.id() == s)
+         .ok_or_else(|| UnknownAgent(s.to_string()))
+   }
+}
+
+#[cfg(test)]
+mod test {
+   use super::{AGENTS, Agent, AgentOutcome, AgentStep, Hand};
+   use crate::graph::MazeGraph;
+   use crate::grid::Grid;
+   use crate::mazegen;
+   use crate::pathfinding::algos;
+   use crate::pathfinding::heuristics::null_h;
+   use rand::SeedableRng;
+   use rand_xorshift::XorShiftRng;
+
+   fn solved_path(outcome: AgentOutcome) -> Box<[usize]> {
+      match outcome {
+         AgentOutcome::Solved(path) => path,
+         x => panic!("expected a path, got {:?}", x),
+      }
+   }
+
+   #[test]
+   fn agents_solve_perfect_mazes() {
+      let mut rng = XorShiftRng::seed_from_u64(0);
+      let mut grid = Grid::new(15, 10);
+      mazegen::wilson(&mut grid, &mut rng);
+      for goal in [grid.size() - 1, 14, 77] {
+         // there is only one path through a perfect maze
+         let only_path = algos::a_star(&grid, null_h, 0, goal, false).unwrap().path;
+         for agent in [
+            Agent::WallFollowerLeft,
+            Agent::WallFollowerRight,
+            Agent::Tremaux,
+            Agent::DeadEndFilling,
+         ] {
+            let run = agent.run(&grid, 0, goal);
+            for i in run.moves() {
+               assert!(grid.is_enabled(i));
+            }
+            assert_eq!(solved_path(run.outcome), only_path, "{}", agent);
+         }
+      }
+      // dead-end filling fills in everything but the path
+      let run = Agent::DeadEndFilling.run(&grid, 0, grid.size() - 1);
+      let fills = run.steps.iter().filter(|x| matches!(x, AgentStep::Fill(_))).count();
+      assert_eq!(fills + solved_path(run.outcome).len(), grid.size());
+   }
+
+   #[test]
+   fn wall_followers_notice_loops() {
+      // the goal is in the middle of an open room, where no wall leads to it
+      let mut grid = Grid::new(5, 5);
+      mazegen::empty(&mut grid);
+      for hand in [Hand::Left, Hand::Right] {
+         let run = super::wall_follower(&grid, 0, 12, hand);
+         assert_eq!(run.outcome, AgentOutcome::Looped);
+         assert!(run.moves().all(|x| x != 12));
+      }
+      // but marking passages finds it anyway
+      let path = solved_path(super::tremaux(&grid, 0, 12).outcome);
+      assert!(path.windows(2).all(|x| grid.is_linked(x[0], x[1])));
+      assert_eq!(solved_path(super::dead_end_filling(&grid, 0, 12).outcome).len(), 5);
+   }
+
+   #[test]
+   fn tremaux_marks_passages_at_most_twice() {
+      let mut rng = XorShiftRng::seed_from_u64(1);
+      let mut grid = Grid::new(12, 12);
+      mazegen::recursive_backtracker(&mut grid, &mut rng);
+      mazegen::braid(&mut grid, &mut rng, 0.5);
+      // wall off the goal
+      let goal = 5 * grid.width + 5;
+      let mut links = Vec::new();
+      grid.links(goal, &mut links);
+      for i in links {
+         grid.unlink(goal, i);
+      }
+      let run = super::tremaux(&grid, 0, goal);
+      assert_eq!(run.outcome, AgentOutcome::Unreachable);
+      let mut marks = fxhash::FxHashMap::default();
+      for step in run.steps.iter() {
+         if let AgentStep::Mark(a, b) = *step {
+            *marks.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+         }
+      }
+      // every passage that can be reached was walked through both ways
+      assert!(marks.values().all(|x| *x == 2));
+      assert_eq!(run.moves().last(), Some(0));
+      assert_eq!(
+         super::dead_end_filling(&grid, 0, goal).outcome,
+         AgentOutcome::Unreachable
+      );
+   }
+
+   #[test]
+   fn pledge_lets_go_of_walls() {
+      // a room with a wall standing on its own in the middle, right in front of the start
+      let mut grid = Grid::new(7, 3);
+      mazegen::empty(&mut grid);
+      grid.disconnect_cell_east(9);
+      // with a hand on that wall, a wall follower only ever goes around it
+      for hand in [Hand::Left, Hand::Right] {
+         assert_eq!(super::wall_follower(&grid, 9, 13, hand).outcome, AgentOutcome::Looped);
+      }
+      let path = solved_path(super::pledge(&grid, 9, 13).outcome);
+      assert_eq!(path.first(), Some(&9));
+      assert_eq!(path.last(), Some(&13));
+      assert!(path.windows(2).all(|x| grid.is_linked(x[0], x[1])));
+
+      grid.disconnect_cell_north(13);
+      grid.disconnect_cell_south(13);
+      grid.disconnect_cell_west(13);
+      assert!(!matches!(super::pledge(&grid, 9, 13).outcome, AgentOutcome::Solved(_)));
+   }
+
+   #[test]
+   fn names_round_trip() {
+      for agent in AGENTS {
+         assert_eq!(agent.id().parse::<Agent>(), Ok(agent));
+      }
+      assert!("Tremaux's".parse::<Agent>().is_err());
+   }
+}
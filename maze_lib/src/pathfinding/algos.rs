@@ -1,15 +1,16 @@
 use super::diagnostic_map::{DiagMap, FinalizedDiagMap, DIAG_EXPANDED, DIAG_GENERATED, DIAG_UNEXPLORED};
-use super::heuristics::manhattan_h;
-use crate::grid::Grid;
+use crate::graph::MazeGraph;
+use fxhash::FxHashSet;
 use std::cmp::{Ord, Ordering, PartialOrd, Reverse};
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, VecDeque};
 use std::io::{self, Write};
 
-#[derive(Clone, PartialEq, Eq)]
+/// A cell on the open list of `a_star`. `path_len` counts the cells of the path that reached it, start and all.
+#[derive(Copy, Clone, PartialEq, Eq)]
 struct PriorityNode {
    priority: usize,
    i: usize,
-   path: Vec<usize>,
+   path_len: usize,
 }
 
 impl PartialOrd for PriorityNode {
@@ -23,14 +24,15 @@ impl Ord for PriorityNode {
       self
          .priority
          .cmp(&other.priority)
-         .then(self.path.len().cmp(&other.path.len()))
+         .then(self.path_len.cmp(&other.path_len))
    }
 }
 
-#[derive(Clone, PartialEq, Eq)]
+/// A cell on the open list of `djikstra`
+#[derive(Copy, Clone, PartialEq, Eq)]
 struct Node {
    i: usize,
-   path: Vec<usize>,
+   path_len: usize,
 }
 
 impl PartialOrd for Node {
@@ -41,10 +43,25 @@ impl PartialOrd for Node {
 
 impl Ord for Node {
    fn cmp(&self, other: &Node) -> Ordering {
-      self.path.len().cmp(&other.path.len())
+      self.path_len.cmp(&other.path_len)
    }
 }
 
+/// The parent of a cell that was never generated, or of the start
+const NO_PARENT: usize = usize::MAX;
+
+/// Follows `parents` back from `goal` to the start, which is the cell without a parent
+fn reconstruct_path(parents: &[usize], goal: usize) -> Box<[usize]> {
+   let mut path = vec![goal];
+   let mut i = goal;
+   while parents[i] != NO_PARENT {
+      i = parents[i];
+      path.push(i);
+   }
+   path.reverse();
+   path.into_boxed_slice()
+}
+
 pub struct PathData {
    pub path: Box<[usize]>,
    pub diag: FinalizedDiagMap,
@@ -52,6 +69,28 @@ pub struct PathData {
    pub nodes_expanded: usize,
 }
 
+/// What `ida_star` and `beam_search` find. Unlike `PathData`, there is no `FinalizedDiagMap`, which would take memory
+/// for every cell.
+pub struct BoundedPathData {
+   pub path: Box<[usize]>,
+   pub nodes_generated: usize,
+   pub nodes_expanded: usize,
+   /// The most cells the search had to hold on to at once
+   pub peak_nodes_kept: usize,
+}
+
+impl BoundedPathData {
+   /// The same path and counts, with every one of `size` cells marked unexplored, for code that draws `PathData`
+   pub fn into_path_data(self, size: usize) -> PathData {
+      PathData {
+         path: self.path,
+         diag: FinalizedDiagMap::unexplored(size),
+         nodes_generated: self.nodes_generated,
+         nodes_expanded: self.nodes_expanded,
+      }
+   }
+}
+
 pub fn write_diag_to_svg<W: Write>(diag: &DiagMap, width: usize, dest: &mut W) -> io::Result<()> {
    for (i, x) in diag.inner.iter().enumerate() {
       let row = i / width;
@@ -98,98 +137,63 @@ pub fn write_path_to_svg<W: Write>(path: &[usize], width: usize, dest: &mut W) -
    Ok(())
 }
 
-pub fn a_star<F>(grid: &Grid, h: F, start: usize, goal: usize, greedy: bool) -> Option<PathData>
+/// `h` estimates the distance from a cell (first argument) to the goal (second argument)
+pub fn a_star<G, F>(grid: &G, h: F, start: usize, goal: usize, greedy: bool) -> Option<PathData>
 where
-   F: Fn(usize, usize, usize) -> usize,
+   G: MazeGraph,
+   F: Fn(usize, usize) -> usize,
 {
+   if !grid.is_enabled(start) || !grid.is_enabled(goal) {
+      return None;
+   }
    let mut nodes_generated = 0;
    let mut nodes_expanded = 0;
    let mut diag_map = DiagMap::new(grid.size());
    let mut open: BinaryHeap<Reverse<PriorityNode>> = BinaryHeap::new();
+   // the length of the shortest path to each cell generated so far, and the cell it was generated from
+   let mut best_paths: Box<[usize]> = vec![usize::MAX; grid.size()].into_boxed_slice();
+   let mut parents: Box<[usize]> = vec![NO_PARENT; grid.size()].into_boxed_slice();
    open.push(Reverse(PriorityNode {
-      priority: h(start, goal, grid.width),
+      priority: h(start, goal),
       i: start,
-      path: vec![],
+      path_len: 1,
    }));
-   let mut closed: Box<[usize]> = vec![usize::MAX; grid.size()].into_boxed_slice();
+   // otherwise the start is generated again from its own neighbors
+   best_paths[start] = 1;
    let mut neighbors_to_generate = Vec::with_capacity(4);
-   while let Some(Reverse(mut cur_node)) = open.pop() {
+   while let Some(Reverse(cur_node)) = open.pop() {
       if cur_node.i == goal {
-         let mut final_path = cur_node.path.to_vec();
-         final_path.push(goal);
          return Some(PathData {
-            path: final_path.into_boxed_slice(),
+            path: reconstruct_path(&parents, goal),
             diag: diag_map.into(),
             nodes_generated,
             nodes_expanded,
          });
       }
 
-      let cur_path_len = cur_node.path.len() + 1;
-
-      if closed[cur_node.i] <= cur_node.path.len() {
+      // a shorter path to this cell was found after it was put on the open list
+      if best_paths[cur_node.i] < cur_node.path_len {
          continue;
       }
 
       // Expand
-      {
-         let new_path_len = cur_path_len + 1;
-         cur_node.path.reserve_exact(1);
-         cur_node.path.push(cur_node.i);
-         // wrapping sub is ok because we only use
-         // i.e. i_north when we know it is north connected
-         let i_north = cur_node.i.wrapping_sub(grid.width);
-         let i_south = cur_node.i + grid.width;
-         let i_east = cur_node.i + 1;
-         let i_west = cur_node.i.wrapping_sub(1);
-         neighbors_to_generate.clear();
-         // fill neighbors_to_generate with our neighbors
-         {
-            // N
-            if grid[cur_node.i].north_connected && closed[i_north] > new_path_len {
-               neighbors_to_generate.push(i_north);
-            }
-            // S
-            if grid[cur_node.i].south_connected && closed[i_south] > new_path_len {
-               neighbors_to_generate.push(i_south);
-            }
-            // E
-            if grid[cur_node.i].east_connected && closed[i_east] > new_path_len {
-               neighbors_to_generate.push(i_east);
-            }
-            // W
-            if grid[cur_node.i].west_connected && closed[i_west] > new_path_len {
-               neighbors_to_generate.push(i_west);
-            }
-         }
-         // actually do expansion
-         {
-            let g = new_path_len * !greedy as usize;
-            // first, we generate every node other than the first neighbor
-            neighbors_to_generate.iter().skip(1).for_each(|i| {
-               open.push(Reverse(PriorityNode {
-                  priority: g + h(*i, goal, grid.width),
-                  i: *i,
-                  path: cur_node.path.clone(),
-               }));
-               nodes_generated += 1;
-               diag_map.mark_generated(*i);
-               closed[*i] = new_path_len;
-            });
-            // now, we generate the first neighbor
-            // the vast vast majority of cells have only one neighbor
-            // in mazes, so avoiding a clone is a huge optimization
-            if let Some(i) = neighbors_to_generate.first() {
-               open.push(Reverse(PriorityNode {
-                  priority: g + h(*i, goal, grid.width),
-                  i: *i,
-                  path: cur_node.path,
-               }));
-               nodes_generated += 1;
-               diag_map.mark_generated(*i);
-               closed[*i] = new_path_len;
-            }
+      let new_path_len = cur_node.path_len + 1;
+      let g = new_path_len * !greedy as usize;
+      neighbors_to_generate.clear();
+      grid.links(cur_node.i, &mut neighbors_to_generate);
+      for i in neighbors_to_generate.iter().copied() {
+         if best_paths[i] <= new_path_len {
+            continue;
          }
+         open.push(Reverse(PriorityNode {
+            priority: g + h(i, goal),
+            i,
+            path_len: new_path_len,
+         }));
+         nodes_generated += 1;
+         diag_map.mark_generated(i);
+         best_paths[i] = new_path_len;
+         parents[i] = cur_node.i;
       }
       nodes_expanded += 1;
       diag_map.mark_expanded(cur_node.i);
@@ -197,17 +201,27 @@ where
    None
 }
 
-pub fn dfs(grid: &Grid, start: usize, goal: usize) -> Option<PathData> {
+/// Neighbors are searched in order of `h`, which estimates the distance from a cell (first argument) to the goal
+/// (second argument)
+pub fn dfs<G, F>(grid: &G, h: F, start: usize, goal: usize) -> Option<PathData>
+where
+   G: MazeGraph,
+   F: Fn(usize, usize) -> usize,
+{
    struct DfsNode {
       i: usize,
       path_len: usize,
    }
 
+   if !grid.is_enabled(start) || !grid.is_enabled(goal) {
+      return None;
+   }
    let mut nodes_generated = 0;
    let mut nodes_expanded = 0;
    let mut diag_map = DiagMap::new(grid.size());
    let mut path = vec![];
    let mut stack: Vec<DfsNode> = vec![DfsNode { i: start, path_len: 0 }];
+   let mut neighbors_to_generate = Vec::with_capacity(4);
    while let Some(cur_node) = stack.pop() {
       path.truncate(cur_node.path_len);
       path.push(cur_node.i);
@@ -224,37 +238,13 @@ pub fn dfs(grid: &Grid, start: usize, goal: usize) -> Option<PathData> {
       let stack_size_before_expansion = stack.len();
       let path_len = path.len();
       {
-         // wrapping sub is ok because we only use
-         // i.e. i_north when we know it is north connected
-         let i_north = cur_node.i.wrapping_sub(grid.width);
-         let i_south = cur_node.i + grid.width;
-         let i_east = cur_node.i + 1;
-         let i_west = cur_node.i.wrapping_sub(1);
-         // fill neighbors_to_generate with our neighbors
-         {
-            // N
-            if grid[cur_node.i].north_connected && diag_map[i_north] == DIAG_UNEXPLORED {
-               stack.push(DfsNode { i: i_north, path_len });
-               nodes_generated += 1;
-               diag_map.mark_generated(i_north);
-            }
-            // S
-            if grid[cur_node.i].south_connected && diag_map[i_south] == DIAG_UNEXPLORED {
-               stack.push(DfsNode { i: i_south, path_len });
-               nodes_generated += 1;
-               diag_map.mark_generated(i_south);
-            }
-            // E
-            if grid[cur_node.i].east_connected && diag_map[i_east] == DIAG_UNEXPLORED {
-               stack.push(DfsNode { i: i_east, path_len });
-               nodes_generated += 1;
-               diag_map.mark_generated(i_east);
-            }
-            // W
-            if grid[cur_node.i].west_connected && diag_map[i_west] == DIAG_UNEXPLORED {
-               stack.push(DfsNode { i: i_west, path_len });
+         neighbors_to_generate.clear();
+         grid.links(cur_node.i, &mut neighbors_to_generate);
+         for i in neighbors_to_generate.iter().copied() {
+            if diag_map[i] == DIAG_UNEXPLORED {
+               stack.push(DfsNode { i, path_len });
                nodes_generated += 1;
-               diag_map.mark_generated(i_west);
+               diag_map.mark_generated(i);
             }
          }
       }
@@ -265,75 +255,556 @@ pub fn dfs(grid: &Grid, start: usize, goal: usize) -> Option<PathData> {
       newly_added_elems.sort_unstable_by(|a, b| {
          // note we compare b to a (not a to b) in order to obtain a reverse sort,
          // such that the elements with the smallest h are at the top of the slice
-         manhattan_h(b.i, goal, grid.width).cmp(&manhattan_h(a.i, goal, grid.width))
+         h(b.i, goal).cmp(&h(a.i, goal))
       });
    }
    None
 }
 
-pub fn djikstra(grid: &Grid, start: usize) -> Box<[usize]> {
-   let mut best_paths = vec![usize::MAX; grid.size()].into_boxed_slice();
-   let mut open: BinaryHeap<Reverse<Node>> = BinaryHeap::new();
+/// Every step costs the same in a maze, so searching outward one layer at a time finds a shortest path just like
+/// `a_star` with `null_h`, without needing a heap
+pub fn bfs<G: MazeGraph>(grid: &G, start: usize, goal: usize) -> Option<PathData> {
+   if !grid.is_enabled(start) || !grid.is_enabled(goal) {
+      return None;
+   }
+   let mut nodes_generated = 0;
+   let mut nodes_expanded = 0;
+   let mut diag_map = DiagMap::new(grid.size());
+   let mut parents: Box<[usize]> = vec![NO_PARENT; grid.size()].into_boxed_slice();
+   let mut open = VecDeque::from([start]);
    let mut neighbors_to_generate = Vec::with_capacity(4);
-   open.push(Reverse(Node { i: start, path: vec![] }));
-   while let Some(Reverse(mut cur_node)) = open.pop() {
-      let cur_path_len = cur_node.path.len() + 1;
-      if best_paths[cur_node.i] <= cur_node.path.len() {
-         continue;
+   while let Some(cur) = open.pop_front() {
+      if cur == goal {
+         return Some(PathData {
+            path: reconstruct_path(&parents, goal),
+            diag: diag_map.into(),
+            nodes_generated,
+            nodes_expanded,
+         });
       }
-      // expand
-      {
-         let new_path_len = cur_path_len + 1;
-         cur_node.path.reserve_exact(1);
-         cur_node.path.push(cur_node.i);
-         // wrapping sub is ok because we only use
-         // i.e. i_north when we know it is north connected
-         let i_north = cur_node.i.wrapping_sub(grid.width);
-         let i_south = cur_node.i + grid.width;
-         let i_east = cur_node.i + 1;
-         let i_west = cur_node.i.wrapping_sub(1);
+
+      // Expand
+      neighbors_to_generate.clear();
+      grid.links(cur, &mut neighbors_to_generate);
+      for i in neighbors_to_generate.iter().copied() {
+         // the start is expanded before any other cell, so it is never generated again
+         if diag_map[i] == DIAG_UNEXPLORED {
+            open.push_back(i);
+            nodes_generated += 1;
+            diag_map.mark_generated(i);
+            parents[i] = cur;
+         }
+      }
+      nodes_expanded += 1;
+      diag_map.mark_expanded(cur);
+   }
+   None
+}
+
+/// Searches outward from both `start` and `goal` at once, a layer at a time, until the two searches meet. Each only
+/// has to get about halfway, which between two cells in the middle of an open grid is about half the cells `bfs`
+/// explores. Both searches share one `DiagMap`, so the path data shows the two frontiers growing toward each other.
+pub fn bidirectional_bfs<G: MazeGraph>(grid: &G, start: usize, goal: usize) -> Option<PathData> {
+   // which search reached a cell first
+   const NEITHER: u8 = 0;
+   const FROM_START: u8 = 1;
+   const FROM_GOAL: u8 = 2;
+
+   if !grid.is_enabled(start) || !grid.is_enabled(goal) {
+      return None;
+   }
+   let mut nodes_generated = 0;
+   let mut nodes_expanded = 0;
+   let mut diag_map = DiagMap::new(grid.size());
+   if start == goal {
+      return Some(PathData {
+         path: Box::new([start]),
+         diag: diag_map.into(),
+         nodes_generated,
+         nodes_expanded,
+      });
+   }
+   let mut reached_by = vec![NEITHER; grid.size()].into_boxed_slice();
+   reached_by[start] = FROM_START;
+   reached_by[goal] = FROM_GOAL;
+   // the parent of a cell points back toward whichever end it was reached from
+   let mut parents: Box<[usize]> = vec![NO_PARENT; grid.size()].into_boxed_slice();
+   let mut frontiers = [vec![start], vec![goal]];
+   let mut next_frontier = Vec::new();
+   let mut neighbors_to_generate = Vec::with_capacity(4);
+   while !frontiers[0].is_empty() && !frontiers[1].is_empty() {
+      // grow the smaller frontier by a whole layer. every cell where the searches can first meet is then the same
+      // distance from both ends, so the first meeting is on a shortest path.
+      let (side, searching_from, other) = if frontiers[0].len() <= frontiers[1].len() {
+         (0, FROM_START, FROM_GOAL)
+      } else {
+         (1, FROM_GOAL, FROM_START)
+      };
+      next_frontier.clear();
+      for cur in frontiers[side].iter().copied() {
          neighbors_to_generate.clear();
-         // fill neighbors_to_generate with our neighbors
-         {
-            // N
-            if grid[cur_node.i].north_connected && best_paths[i_north] > new_path_len {
-               neighbors_to_generate.push(i_north);
+         grid.links(cur, &mut neighbors_to_generate);
+         for i in neighbors_to_generate.iter().copied() {
+            if reached_by[i] == other {
+               let (from_start, from_goal) = if searching_from == FROM_START {
+                  (cur, i)
+               } else {
+                  (i, cur)
+               };
+               let mut path = reconstruct_path(&parents, from_start).into_vec();
+               let mut i = from_goal;
+               path.push(i);
+               while parents[i] != NO_PARENT {
+                  i = parents[i];
+                  path.push(i);
+               }
+               return Some(PathData {
+                  path: path.into_boxed_slice(),
+                  diag: diag_map.into(),
+                  nodes_generated,
+                  nodes_expanded,
+               });
             }
-            // S
-            if grid[cur_node.i].south_connected && best_paths[i_south] > new_path_len {
-               neighbors_to_generate.push(i_south);
+            if reached_by[i] == NEITHER {
+               reached_by[i] = searching_from;
+               parents[i] = cur;
+               next_frontier.push(i);
+               nodes_generated += 1;
+               diag_map.mark_generated(i);
             }
-            // E
-            if grid[cur_node.i].east_connected && best_paths[i_east] > new_path_len {
-               neighbors_to_generate.push(i_east);
+         }
+         nodes_expanded += 1;
+         diag_map.mark_expanded(cur);
+      }
+      std::mem::swap(&mut frontiers[side], &mut next_frontier);
+   }
+   None
+}
+
+/// Iterative Deepening A*: depth-first searches that give up on a path once its length plus `h` is over a bound, with
+/// the bound raised to the smallest length that went over it after every search that doesn't reach the goal. It only
+/// needs memory for the path being searched and the neighbors along it that haven't been tried yet, in exchange for
+/// searching the cells near the start again on every pass. Nothing stops it from searching the same cell again by
+/// another path either, so on mazes with many loops there can be far too many paths under the bound to search.
+///
+/// Finds a shortest path as long as `h` never overestimates. `h` estimates the distance from a cell (first argument)
+/// to the goal (second argument).
+pub fn ida_star<G, F>(grid: &G, h: F, start: usize, goal: usize) -> Option<BoundedPathData>
+where
+   G: MazeGraph,
+   F: Fn(usize, usize) -> usize,
+{
+   if !grid.is_enabled(start) || !grid.is_enabled(goal) {
+      return None;
+   }
+   let mut nodes_generated = 0;
+   let mut nodes_expanded = 0;
+   let mut peak_nodes_kept = 0;
+   let mut bound = h(start, goal);
+   let mut path = Vec::new();
+   let mut on_path = FxHashSet::default();
+   // the neighbors of every cell on the path that are left to try, with where the neighbors of each cell begin
+   let mut untried = Vec::new();
+   let mut untried_starts = Vec::new();
+   loop {
+      // the smallest length plus `h` of any path given up on
+      let mut next_bound = usize::MAX;
+      path.push(start);
+      on_path.insert(start);
+      let mut next = Some(start);
+      loop {
+         // Expand
+         if let Some(cur) = next.take() {
+            if cur == goal {
+               return Some(BoundedPathData {
+                  path: path.into_boxed_slice(),
+                  nodes_generated,
+                  nodes_expanded,
+                  peak_nodes_kept,
+               });
             }
-            // W
-            if grid[cur_node.i].west_connected && best_paths[i_west] > new_path_len {
-               neighbors_to_generate.push(i_west);
+            untried_starts.push(untried.len());
+            grid.links(cur, &mut untried);
+            peak_nodes_kept = peak_nodes_kept.max(path.len() + untried.len());
+            let neighbors = &mut untried[*untried_starts.last().unwrap()..];
+            // tried from the end, so sorted to try the neighbors closest to the goal first
+            neighbors.sort_unstable_by_key(|x| Reverse(h(*x, goal)));
+            nodes_expanded += 1;
+         }
+
+         // every neighbor of the last cell on the path has been tried, so back up
+         if untried_starts.last() == Some(&untried.len()) {
+            untried_starts.pop();
+            on_path.remove(&path.pop().unwrap());
+            if path.is_empty() {
+               break;
             }
+            continue;
+         }
+         let i = untried.pop().unwrap();
+         if on_path.contains(&i) {
+            continue;
          }
-         // actually do expansion
-         {
-            // first, we generate every node other than the first neighbor
-            neighbors_to_generate.iter().skip(1).for_each(|i| {
-               open.push(Reverse(Node {
-                  i: *i,
-                  path: cur_node.path.clone(),
-               }));
-               best_paths[*i] = new_path_len;
-            });
-            // now, we generate the first neighbor
-            // the vast vast majority of cells have only one neighbor
-            // in mazes, so avoiding a clone is a huge optimization
-            if let Some(i) = neighbors_to_generate.first() {
-               open.push(Reverse(Node {
-                  i: *i,
-                  path: cur_node.path,
-               }));
-               best_paths[*i] = new_path_len;
+         nodes_generated += 1;
+         // the steps taken to get to `i`, which is one for every cell on the path before it
+         let f = path.len() + h(i, goal);
+         if f > bound {
+            next_bound = next_bound.min(f);
+            continue;
+         }
+         path.push(i);
+         on_path.insert(i);
+         next = Some(i);
+      }
+      if next_bound == usize::MAX {
+         return None;
+      }
+      bound = next_bound;
+   }
+}
+
+/// Searches outward a layer at a time like `bfs`, but only keeps the `width` cells of every layer that `h` puts closest
+/// to the goal. Instead of memory for every cell, it only needs memory for the cells of the layer and the paths back
+/// to the start from them, which soon run together. The price is that the path it finds may not be the shortest one,
+/// and that it gives up if every path to the goal gets crowded out of the layers. `h` estimates the distance from a
+/// cell (first argument) to the goal (second argument).
+pub fn beam_search<G, F>(grid: &G, h: F, start: usize, goal: usize, width: usize) -> Option<BoundedPathData>
+where
+   G: MazeGraph,
+   F: Fn(usize, usize) -> usize,
+{
+   assert!(width > 0, "a beam must keep at least one cell");
+   if !grid.is_enabled(start) || !grid.is_enabled(goal) {
+      return None;
+   }
+   let mut nodes_generated = 0;
+   let mut nodes_expanded = 0;
+   // the cells of the layer and every cell on the way back to the start from them, with the index of the one before
+   // it. each cell is only compared against the one it was reached from, so the beam can come back around a loop,
+   // but never turns straight back.
+   let mut kept = vec![(start, NO_PARENT)];
+   let mut peak_nodes_kept = 1;
+   let mut moved_to = Vec::new();
+   let mut layer = vec![0];
+   let mut candidates = Vec::new();
+   let mut neighbors_to_generate = Vec::with_capacity(4);
+   let path_to = |kept: &[(usize, usize)], mut node: usize| {
+      let mut path = Vec::new();
+      while node != NO_PARENT {
+         path.push(kept[node].0);
+         node = kept[node].1;
+      }
+      path.reverse();
+      path.into_boxed_slice()
+   };
+   if start == goal {
+      return Some(BoundedPathData {
+         path: path_to(&kept, 0),
+         nodes_generated,
+         nodes_expanded,
+         peak_nodes_kept,
+      });
+   }
+   // a path never needs more steps than there are cells
+   for _ in 0..grid.size() {
+      candidates.clear();
+      for node in layer.iter().copied() {
+         let (cur, parent) = kept[node];
+         neighbors_to_generate.clear();
+         grid.links(cur, &mut neighbors_to_generate);
+         for i in neighbors_to_generate.iter().copied() {
+            if parent != NO_PARENT && i == kept[parent].0 {
+               continue;
+            }
+            nodes_generated += 1;
+            if i == goal {
+               kept.push((i, node));
+               return Some(BoundedPathData {
+                  path: path_to(&kept, kept.len() - 1),
+                  nodes_generated,
+                  nodes_expanded,
+                  peak_nodes_kept: peak_nodes_kept.max(kept.len()),
+               });
             }
+            candidates.push((h(i, goal), i, node));
+         }
+         nodes_expanded += 1;
+      }
+      // keep each cell once, coming from wherever it was first reached
+      candidates.sort_unstable();
+      candidates.dedup_by_key(|(_, i, _)| *i);
+      candidates.truncate(width);
+      if candidates.is_empty() {
+         return None;
+      }
+      layer.clear();
+      for (_, i, node) in candidates.iter().copied() {
+         layer.push(kept.len());
+         kept.push((i, node));
+      }
+      peak_nodes_kept = peak_nodes_kept.max(kept.len());
+      prune_kept(&mut kept, &mut layer, &mut moved_to);
+   }
+   None
+}
+
+/// Drops every cell of `kept` that isn't on the way back to the start from a cell of `layer`, and points `layer` at
+/// where its cells were moved to
+fn prune_kept(kept: &mut Vec<(usize, usize)>, layer: &mut [usize], moved_to: &mut Vec<usize>) {
+   moved_to.clear();
+   moved_to.resize(kept.len(), NO_PARENT);
+   // mark the cells that are still needed
+   for node in layer.iter().copied() {
+      let mut node = node;
+      while node != NO_PARENT && moved_to[node] == NO_PARENT {
+         moved_to[node] = 0;
+         node = kept[node].1;
+      }
+   }
+   // every cell comes after the one before it, so they can be moved down in order
+   let mut len = 0;
+   for node in 0..kept.len() {
+      if moved_to[node] == NO_PARENT {
+         continue;
+      }
+      let (i, parent) = kept[node];
+      kept[len] = (
+         i,
+         if parent == NO_PARENT {
+            NO_PARENT
+         } else {
+            moved_to[parent]
+         },
+      );
+      moved_to[node] = len;
+      len += 1;
+   }
+   kept.truncate(len);
+   for node in layer.iter_mut() {
+      *node = moved_to[*node];
+   }
+}
+
+/// The length of the shortest path from `start` to every cell, counting both ends, or `usize::MAX` for cells that
+/// can't be reached
+pub fn djikstra<G: MazeGraph>(grid: &G, start: usize) -> Box<[usize]> {
+   let mut best_paths = vec![usize::MAX; grid.size()].into_boxed_slice();
+   let mut open: BinaryHeap<Reverse<Node>> = BinaryHeap::new();
+   let mut neighbors_to_generate = Vec::with_capacity(4);
+   open.push(Reverse(Node { i: start, path_len: 1 }));
+   // otherwise the start is generated again from its own neighbors
+   best_paths[start] = 1;
+   while let Some(Reverse(cur_node)) = open.pop() {
+      if best_paths[cur_node.i] < cur_node.path_len {
+         continue;
+      }
+      // expand
+      let new_path_len = cur_node.path_len + 1;
+      neighbors_to_generate.clear();
+      grid.links(cur_node.i, &mut neighbors_to_generate);
+      for i in neighbors_to_generate.iter().copied() {
+         if best_paths[i] > new_path_len {
+            open.push(Reverse(Node {
+               i,
+               path_len: new_path_len,
+            }));
+            best_paths[i] = new_path_len;
          }
       }
    }
    best_paths
 }
+
+#[cfg(test)]
+mod test {
+   use super::{a_star, beam_search, bfs, bidirectional_bfs, dfs, djikstra, ida_star};
+   use crate::graph::MazeGraph;
+   use crate::grid::{Grid, Wrap};
+   use crate::hex::HexGrid;
+   use crate::mazegen;
+   use crate::pathfinding::heuristics::{manhattan_3d_h, manhattan_h, null_h, wrapping_manhattan_h};
+   use rand::SeedableRng;
+   use rand_xorshift::XorShiftRng;
+
+   fn assert_valid_path<G: MazeGraph>(graph: &G, path: &[usize], start: usize, goal: usize) {
+      assert_eq!(path.first(), Some(&start));
+      assert_eq!(path.last(), Some(&goal));
+      assert!(path.windows(2).all(|x| graph.is_linked(x[0], x[1])));
+   }
+
+   #[test]
+   fn solvers_work_on_any_graph() {
+      let mut rng = XorShiftRng::seed_from_u64(0);
+
+      let mut grid = Grid::new(20, 15);
+      mazegen::recursive_backtracker(&mut grid, &mut rng);
+      let goal = grid.size() - 1;
+      let h = |i, goal| manhattan_h(i, goal, grid.width);
+      let shortest = a_star(&grid, h, 0, goal, false).unwrap();
+      assert_valid_path(&grid, &shortest.path, 0, goal);
+      assert_valid_path(&grid, &dfs(&grid, h, 0, goal).unwrap().path, 0, goal);
+      assert_eq!(djikstra(&grid, 0)[goal], shortest.path.len());
+
+      let mut hex = HexGrid::new(20, 15);
+      mazegen::kruskal(&mut hex, &mut rng);
+      let goal = hex.size() - 1;
+      let shortest = a_star(&hex, null_h, 0, goal, false).unwrap();
+      assert_valid_path(&hex, &shortest.path, 0, goal);
+      assert_valid_path(&hex, &dfs(&hex, null_h, 0, goal).unwrap().path, 0, goal);
+      assert_eq!(djikstra(&hex, 0)[goal], shortest.path.len());
+   }
+
+   #[test]
+   fn solvers_cross_wrapped_edges() {
+      let mut rng = XorShiftRng::seed_from_u64(1);
+      let mut grid = Grid::new_wrapping(20, 15, Wrap::TORUS);
+      mazegen::kruskal(&mut grid, &mut rng);
+      mazegen::braid(&mut grid, &mut rng, 1.0);
+      let h = |i, goal| wrapping_manhattan_h(i, goal, grid.width, grid.height, grid.wrap);
+      let best_paths = djikstra(&grid, 0);
+      for goal in [grid.size() - 1, 19, 280, 150] {
+         let shortest = a_star(&grid, h, 0, goal, false).unwrap();
+         assert_valid_path(&grid, &shortest.path, 0, goal);
+         // the heuristic never overestimates, so A* still finds a shortest path
+         assert_eq!(best_paths[goal], shortest.path.len());
+         assert_valid_path(&grid, &dfs(&grid, h, 0, goal).unwrap().path, 0, goal);
+      }
+   }
+
+   #[test]
+   fn solvers_climb_between_floors() {
+      let mut rng = XorShiftRng::seed_from_u64(2);
+      let mut grid = Grid::new_3d(10, 8, 3);
+      mazegen::wilson(&mut grid, &mut rng);
+      mazegen::braid(&mut grid, &mut rng, 0.5);
+      let goal = grid.size() - 1;
+      let h = |i, goal| manhattan_3d_h(i, goal, grid.width, grid.height);
+      let shortest = a_star(&grid, h, 0, goal, false).unwrap();
+      assert_valid_path(&grid, &shortest.path, 0, goal);
+      assert_eq!(djikstra(&grid, 0)[goal], shortest.path.len());
+      assert_valid_path(&grid, &dfs(&grid, h, 0, goal).unwrap().path, 0, goal);
+   }
+
+   #[test]
+   fn solvers_cross_open_grids() {
+      let mut grid = Grid::new(30, 20);
+      mazegen::empty(&mut grid);
+      let h = |i, goal| manhattan_h(i, goal, grid.width);
+      let best_paths = djikstra(&grid, 0);
+      for goal in [grid.size() - 1, 29, 315] {
+         let shortest = a_star(&grid, h, 0, goal, false).unwrap();
+         assert_valid_path(&grid, &shortest.path, 0, goal);
+         assert_eq!(shortest.path.len(), h(0, goal) + 1);
+         assert_eq!(best_paths[goal], shortest.path.len());
+         assert!(!shortest.diag.generated_history.contains(&0));
+         let uniform = a_star(&grid, null_h, 0, goal, false).unwrap();
+         assert_eq!(uniform.path.len(), shortest.path.len());
+         assert!(uniform.nodes_expanded >= shortest.nodes_expanded);
+         assert_valid_path(&grid, &a_star(&grid, h, 0, goal, true).unwrap().path, 0, goal);
+      }
+      assert_eq!(best_paths.iter().max(), Some(&(h(0, grid.size() - 1) + 1)));
+   }
+
+   #[test]
+   fn breadth_first_searches_find_shortest_paths() {
+      let mut rng = XorShiftRng::seed_from_u64(3);
+      let mut grid = Grid::new_wrapping(20, 15, Wrap::CYLINDER);
+      mazegen::kruskal(&mut grid, &mut rng);
+      mazegen::braid(&mut grid, &mut rng, 0.5);
+      for start in [0, 37, 150] {
+         let best_paths = djikstra(&grid, start);
+         for goal in [start, grid.size() - 1, 19, 280, 151] {
+            let forward = bfs(&grid, start, goal).unwrap();
+            assert_valid_path(&grid, &forward.path, start, goal);
+            assert_eq!(best_paths[goal], forward.path.len());
+            let both_ways = bidirectional_bfs(&grid, start, goal).unwrap();
+            assert_valid_path(&grid, &both_ways.path, start, goal);
+            assert_eq!(best_paths[goal], both_ways.path.len());
+         }
+      }
+
+      let mut open = Grid::new(30, 30);
+      mazegen::empty(&mut open);
+      let (start, goal) = (15 * 30 + 5, 15 * 30 + 25);
+      let forward = bfs(&open, start, goal).unwrap();
+      let both_ways = bidirectional_bfs(&open, start, goal).unwrap();
+      assert_eq!(forward.path.len(), both_ways.path.len());
+      assert!(both_ways.nodes_expanded * 3 / 2 < forward.nodes_expanded);
+      // both frontiers show up in the diagnostics
+      let generated = &both_ways.diag.generated_history;
+      assert!(generated.contains(&(start + 1)) && generated.contains(&(goal - 1)));
+
+      // cut the open grid in two
+      for i in 0..open.height {
+         open.disconnect_cell_east(i * open.width + 14);
+      }
+      assert!(bfs(&open, start, goal).is_none());
+      assert!(bidirectional_bfs(&open, start, goal).is_none());
+   }
+
+   #[test]
+   fn bounded_searches_report_counts() {
+      let mut rng = XorShiftRng::seed_from_u64(4);
+      let mut grid = Grid::new(12, 10);
+      mazegen::recursive_backtracker(&mut grid, &mut rng);
+      mazegen::braid(&mut grid, &mut rng, 0.5);
+      let h = |i, goal| manhattan_h(i, goal, grid.width);
+      for goal in [0, grid.size() - 1, 11, 65] {
+         let shortest = a_star(&grid, h, 0, goal, false).unwrap();
+         let deepened = ida_star(&grid, h, 0, goal).unwrap();
+         assert_valid_path(&grid, &deepened.path, 0, goal);
+         assert_eq!(deepened.path.len(), shortest.path.len());
+         // every pass searches the cells near the start again
+         assert!(deepened.nodes_expanded >= shortest.nodes_expanded);
+
+         // wide enough to never crowd anything out, so it is a breadth-first search
+         let wide = beam_search(&grid, h, 0, goal, grid.size()).unwrap();
+         assert_valid_path(&grid, &wide.path, 0, goal);
+         assert_eq!(wide.path.len(), shortest.path.len());
+         if let Some(narrow) = beam_search(&grid, h, 0, goal, 2) {
+            assert_valid_path(&grid, &narrow.path, 0, goal);
+            assert!(narrow.path.len() >= shortest.path.len());
+         }
+      }
+
+      let mut open = Grid::new(20, 20);
+      mazegen::empty(&mut open);
+      let h = |i, goal| manhattan_h(i, goal, open.width);
+      let goal = open.size() - 1;
+      let deepened = ida_star(&open, h, 0, goal).unwrap();
+      assert_eq!(deepened.path.len(), h(0, goal) + 1);
+      // the heuristic is exact on an open grid, so nothing is ever searched again
+      assert_eq!(deepened.nodes_expanded, deepened.path.len() - 1);
+      let narrow = beam_search(&open, h, 0, goal, 1).unwrap();
+      assert_eq!(narrow.path.len(), h(0, goal) + 1);
+      assert_eq!(narrow.into_path_data(open.size()).diag.inner.len(), open.size());
+
+      // without loops to go around, a search that can't reach the goal runs out of paths to try quickly
+      let mut perfect = Grid::new(8, 8);
+      mazegen::kruskal(&mut perfect, &mut rng);
+      assert!(ida_star(&perfect, null_h, 0, 63).is_some());
+      perfect.disconnect_cell_east(27);
+      perfect.disconnect_cell_south(27);
+      perfect.disconnect_cell_north(27);
+      perfect.disconnect_cell_west(27);
+      assert!(ida_star(&perfect, null_h, 0, 27).is_none());
+      assert!(beam_search(&perfect, null_h, 0, 27, 4).is_none());
+   }
+
+   #[test]
+   fn beam_search_forgets_paths_it_dropped() {
+      let mut rng = XorShiftRng::seed_from_u64(5);
+      let mut grid = Grid::new(40, 40);
+      mazegen::recursive_backtracker(&mut grid, &mut rng);
+      mazegen::braid(&mut grid, &mut rng, 1.0);
+      let h = |i, goal| manhattan_h(i, goal, grid.width);
+      for goal in [grid.size() - 1, 39, 820] {
+         let found = beam_search(&grid, h, 0, goal, 16).unwrap();
+         assert_valid_path(&grid, &found.path, 0, goal);
+         // the beam goes around the loops many times over, and would still be holding on to every cell it expanded if
+         // it kept every path it had dropped
+         assert!(found.nodes_expanded > 10 * found.path.len());
+         assert!(found.peak_nodes_kept * 2 < found.nodes_expanded);
+      }
+   }
+}
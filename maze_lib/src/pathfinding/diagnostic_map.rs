@@ -8,6 +8,13 @@ pub const DIAG_EXPANDED: DiagStatus = DiagStatus(0x03);
 #[repr(transparent)]
 pub struct DiagStatus(u8);
 
+impl DiagStatus {
+   /// The status as a plain byte, for handing to code that can't see this type (e.g. across the wasm boundary)
+   pub const fn to_u8(self) -> u8 {
+      self.0
+   }
+}
+
 impl BitOrAssign for DiagStatus {
    fn bitor_assign(&mut self, rhs: Self) {
       self.0 |= rhs.0
@@ -75,3 +82,20 @@ impl From<DiagMap> for FinalizedDiagMap {
       }
    }
 }
+
+impl FinalizedDiagMap {
+   /// A map of `size` cells that were never explored, for searches that don't keep track of what they explore
+   pub fn unexplored(size: usize) -> FinalizedDiagMap {
+      FinalizedDiagMap {
+         inner: vec![DIAG_UNEXPLORED; size].into_boxed_slice(),
+         generated_history: Box::new([]),
+         expanded_history: Box::new([]),
+         num_generated_history: Box::new([]),
+      }
+   }
+
+   /// The status of every cell as a plain byte, see `DiagStatus::to_u8`
+   pub fn status_bytes(&self) -> Box<[u8]> {
+      self.inner.iter().map(|x| x.to_u8()).collect()
+   }
+}
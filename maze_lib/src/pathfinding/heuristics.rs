@@ -1,4 +1,6 @@
-pub fn null_h(_: usize, _: usize, _: usize) -> usize {
+use crate::grid::Wrap;
+
+pub fn null_h(_: usize, _: usize) -> usize {
    0
 }
 
@@ -11,3 +13,29 @@ pub fn manhattan_h(i: usize, goal: usize, width: usize) -> usize {
 
    i_col.abs_diff(goal_col) + i_row.abs_diff(goal_row)
 }
+
+/// Like `manhattan_h`, but taking the shorter way around across any edges that wrap
+pub fn wrapping_manhattan_h(i: usize, goal: usize, width: usize, height: usize, wrap: Wrap) -> usize {
+   let i_row = i / width;
+   let i_col = i % width;
+
+   let goal_row = goal / width;
+   let goal_col = goal % width;
+
+   let mut col_diff = i_col.abs_diff(goal_col);
+   if wrap.east_west {
+      col_diff = col_diff.min(width - col_diff);
+   }
+   let mut row_diff = i_row.abs_diff(goal_row);
+   if wrap.north_south {
+      row_diff = row_diff.min(height - row_diff);
+   }
+   col_diff + row_diff
+}
+
+/// Like `manhattan_h`, for grids of several floors, counting one step per floor
+pub fn manhattan_3d_h(i: usize, goal: usize, width: usize, height: usize) -> usize {
+   let floor_size = width * height;
+   let floor_diff = (i / floor_size).abs_diff(goal / floor_size);
+   floor_diff + manhattan_h(i % floor_size, goal % floor_size, width)
+}
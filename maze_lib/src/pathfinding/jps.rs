@@ -0,0 +1,333 @@
+//! Jump Point Search on a `Grid`, for open rooms and braided mazes where many paths of the same length lead to the
+//! same place and `algos::a_star` expands all of them.
+//!
+//! Of all the shortest paths between two cells, only the canonical one is searched for: the one that makes its moves
+//! along earlier axes (east and west, then north and south, then up and down) as early as it can. A path that moves
+//! north and then east can instead move east and then north, unless a wall is in the way of that, so the search only
+//! turns from a later axis onto an earlier one when it is forced to. Everywhere else it jumps straight ahead, only
+//! stopping at cells where the canonical path might turn.
+
+use super::algos::PathData;
+use super::diagnostic_map::DiagMap;
+use crate::grid::Grid;
+use fxhash::FxHashMap;
+use std::cmp::{Ord, Ordering, PartialOrd, Reverse};
+use std::collections::BinaryHeap;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Dir {
+   East,
+   West,
+   North,
+   South,
+   Up,
+   Down,
+}
+
+const DIRS: [Dir; 6] = [Dir::East, Dir::West, Dir::North, Dir::South, Dir::Up, Dir::Down];
+
+impl Dir {
+   /// The order in which the canonical path prefers to make its moves
+   fn axis(self) -> u8 {
+      match self {
+         Dir::East | Dir::West => 0,
+         Dir::North | Dir::South => 1,
+         Dir::Up | Dir::Down => 2,
+      }
+   }
+
+   fn opposite(self) -> Dir {
+      match self {
+         Dir::East => Dir::West,
+         Dir::West => Dir::East,
+         Dir::North => Dir::South,
+         Dir::South => Dir::North,
+         Dir::Up => Dir::Down,
+         Dir::Down => Dir::Up,
+      }
+   }
+
+   /// The cell one step from `i` in this direction, if there is a passage to it
+   fn step(self, grid: &Grid, i: usize) -> Option<usize> {
+      let cell = grid.cell(i);
+      match self {
+         Dir::East => cell.east_connected.then(|| grid.east(i)),
+         Dir::West => cell.west_connected.then(|| grid.west(i)),
+         Dir::North => cell.north_connected.then(|| grid.north(i)),
+         Dir::South => cell.south_connected.then(|| grid.south(i)),
+         Dir::Up => cell.up_connected.then(|| grid.up(i)),
+         Dir::Down => cell.down_connected.then(|| grid.down(i)),
+      }
+   }
+}
+
+/// Whether the canonical path can turn onto `turn` at `i`, having come from `prev` by moving `dir`. That is only the
+/// case for a turn onto an earlier axis if the path can't make the turn from `prev` instead.
+fn can_turn(grid: &Grid, prev: usize, i: usize, dir: Dir, turn: Dir) -> bool {
+   if turn.axis() > dir.axis() {
+      return true;
+   }
+   if turn.axis() == dir.axis() || turn.step(grid, i).is_none() {
+      return false;
+   }
+   turn.step(grid, prev).and_then(|x| dir.step(grid, x)).is_none()
+}
+
+/// Moves from `from` in `dir` until reaching a cell where the canonical path might turn, returning that cell and the
+/// number of steps it took to get there. A cell on the way is only worth stopping at if it is the goal, if the path
+/// is forced to turn there, or if turning onto a later axis there leads somewhere worth stopping at.
+fn jump(grid: &Grid, from: usize, dir: Dir, goal: usize) -> Option<(usize, usize)> {
+   let mut prev = from;
+   let mut steps = 0;
+   loop {
+      let i = dir.step(grid, prev)?;
+      steps += 1;
+      // all the way around a wrapped edge without finding anything
+      if i == from {
+         return None;
+      }
+      if i == goal {
+         return Some((i, steps));
+      }
+      for turn in DIRS {
+         let stop = if turn.axis() < dir.axis() {
+            can_turn(grid, prev, i, dir, turn)
+         } else {
+            turn.axis() > dir.axis() && jump(grid, i, turn, goal).is_some()
+         };
+         if stop {
+            return Some((i, steps));
+         }
+      }
+      prev = i;
+   }
+}
+
+/// A jump point on the open list, along with the direction it was jumped to in, which decides where the search can go
+/// from it. `path_len` counts the cells of the path that reached it, start and all.
+#[derive(Copy, Clone, PartialEq, Eq)]
+struct JumpNode {
+   priority: usize,
+   state: usize,
+   path_len: usize,
+}
+
+impl PartialOrd for JumpNode {
+   fn partial_cmp(&self, other: &JumpNode) -> Option<Ordering> {
+      Some(self.cmp(other))
+   }
+}
+
+impl Ord for JumpNode {
+   fn cmp(&self, other: &JumpNode) -> Ordering {
+      self
+         .priority
+         .cmp(&other.priority)
+         .then(self.path_len.cmp(&other.path_len))
+   }
+}
+
+/// A cell reached by jumping in each of `DIRS`, plus the start, which wasn't reached by jumping at all
+const STATES_PER_CELL: usize = DIRS.len() + 1;
+
+const NO_PARENT: usize = usize::MAX;
+
+/// The shortest path found so far to a state, and the state it came from
+#[derive(Copy, Clone)]
+struct Reached {
+   path_len: usize,
+   parent: usize,
+}
+
+/// Finds a shortest path, the same length as the one `algos::a_star` finds, as long as `h` never overestimates and is
+/// consistent (as `heuristics::manhattan_h` and the others are). `h` estimates the distance from a cell (first
+/// argument) to the goal (second argument).
+///
+/// Only jump points are counted as generated and expanded, and only they are marked in the `DiagMap`.
+pub fn jps<F>(grid: &Grid, h: F, start: usize, goal: usize) -> Option<PathData>
+where
+   F: Fn(usize, usize) -> usize,
+{
+   if !grid.is_enabled(start) || !grid.is_enabled(goal) {
+      return None;
+   }
+   let mut nodes_generated = 0;
+   let mut nodes_expanded = 0;
+   let mut diag_map = DiagMap::new(grid.size());
+   // a cell can be reached in several directions, and each leaves the search somewhere else to go, so they are
+   // searched as separate states. only jump points are ever reached, so they are kept in a map instead of taking
+   // room for every direction of every cell.
+   let mut reached: FxHashMap<usize, Reached> = FxHashMap::default();
+   let start_state = start * STATES_PER_CELL + DIRS.len();
+   reached.insert(
+      start_state,
+      Reached {
+         path_len: 1,
+         parent: NO_PARENT,
+      },
+   );
+   let mut open: BinaryHeap<Reverse<JumpNode>> = BinaryHeap::new();
+   open.push(Reverse(JumpNode {
+      priority: h(start, goal),
+      state: start_state,
+      path_len: 1,
+   }));
+   while let Some(Reverse(cur_node)) = open.pop() {
+      let cur = cur_node.state / STATES_PER_CELL;
+      if cur == goal {
+         return Some(PathData {
+            path: reconstruct_path(grid, &reached, cur_node.state),
+            diag: diag_map.into(),
+            nodes_generated,
+            nodes_expanded,
+         });
+      }
+      if reached[&cur_node.state].path_len < cur_node.path_len {
+         continue;
+      }
+
+      // Expand
+      let arrived = DIRS.get(cur_node.state % STATES_PER_CELL).copied();
+      for dir in DIRS {
+         let worth_trying = match arrived {
+            None => true,
+            Some(arrived) => {
+               dir == arrived || can_turn(grid, arrived.opposite().step(grid, cur).unwrap(), cur, arrived, dir)
+            }
+         };
+         let Some((i, steps)) = worth_trying.then(|| jump(grid, cur, dir, goal)).flatten() else {
+            continue;
+         };
+         let state = i * STATES_PER_CELL + dir as usize;
+         let path_len = cur_node.path_len + steps;
+         if reached.get(&state).is_some_and(|x| x.path_len <= path_len) {
+            continue;
+         }
+         reached.insert(
+            state,
+            Reached {
+               path_len,
+               parent: cur_node.state,
+            },
+         );
+         open.push(Reverse(JumpNode {
+            priority: path_len + h(i, goal),
+            state,
+            path_len,
+         }));
+         nodes_generated += 1;
+         diag_map.mark_generated(i);
+      }
+      nodes_expanded += 1;
+      diag_map.mark_expanded(cur);
+   }
+   None
+}
+
+/// Follows the parents in `reached` back from `state` to the start, filling in the cells jumped over on the way
+fn reconstruct_path(grid: &Grid, reached: &FxHashMap<usize, Reached>, state: usize) -> Box<[usize]> {
+   let mut path = vec![state / STATES_PER_CELL];
+   let mut state = state;
+   while reached[&state].parent != NO_PARENT {
+      let back = DIRS[state % STATES_PER_CELL].opposite();
+      let parent = reached[&state].parent;
+      let mut i = state / STATES_PER_CELL;
+      while i != parent / STATES_PER_CELL {
+         i = back.step(grid, i).unwrap();
+         path.push(i);
+      }
+      state = parent;
+   }
+   path.reverse();
+   path.into_boxed_slice()
+}
+
+#[cfg(test)]
+mod test {
+   use super::jps;
+   use crate::graph::MazeGraph;
+   use crate::grid::{Grid, Wrap};
+   use crate::mask::Mask;
+   use crate::mazegen;
+   use crate::pathfinding::algos::{a_star, djikstra};
+   use crate::pathfinding::heuristics::{manhattan_3d_h, manhattan_h, wrapping_manhattan_h};
+   use rand::SeedableRng;
+   use rand_xorshift::XorShiftRng;
+
+   fn assert_shortest<F: Fn(usize, usize) -> usize + Copy>(grid: &Grid, h: F, start: usize) {
+      let best_paths = djikstra(grid, start);
+      for goal in 0..grid.size() {
+         let jumped = jps(grid, h, start, goal);
+         if best_paths[goal] == usize::MAX {
+            assert!(jumped.is_none());
+            continue;
+         }
+         let jumped = jumped.unwrap();
+         assert_eq!(jumped.path.first(), Some(&start));
+         assert_eq!(jumped.path.last(), Some(&goal));
+         assert!(jumped.path.windows(2).all(|x| grid.is_linked(x[0], x[1])));
+         assert_eq!(jumped.path.len(), best_paths[goal], "from {} to {}", start, goal);
+         let shortest = a_star(grid, h, start, goal, false).unwrap();
+         assert_eq!(jumped.path.len(), shortest.path.len());
+      }
+   }
+
+   #[test]
+   fn finds_shortest_paths_in_braided_mazes() {
+      let mut rng = XorShiftRng::seed_from_u64(0);
+      let mut grid = Grid::new(16, 12);
+      for p in [0.0, 0.5, 1.0] {
+         grid.reset();
+         mazegen::recursive_backtracker(&mut grid, &mut rng);
+         mazegen::braid(&mut grid, &mut rng, p);
+         let h = |i, goal| manhattan_h(i, goal, grid.width);
+         assert_shortest(&grid, h, 0);
+         assert_shortest(&grid, h, 101);
+      }
+   }
+
+   #[test]
+   fn expands_fewer_cells_in_open_rooms() {
+      let mut grid = Grid::new(30, 20);
+      mazegen::empty(&mut grid);
+      // a wall with a gap in it
+      for row in 0..16 {
+         grid.disconnect_cell_east(row * grid.width + 15);
+      }
+      let h = |i, goal| manhattan_h(i, goal, grid.width);
+      assert_shortest(&grid, h, 0);
+      let goal = 2 * grid.width + 27;
+      let jumped = jps(&grid, h, 2 * grid.width + 5, goal).unwrap();
+      let shortest = a_star(&grid, h, 2 * grid.width + 5, goal, false).unwrap();
+      assert_eq!(jumped.path.len(), shortest.path.len());
+      assert!(jumped.nodes_expanded * 10 < shortest.nodes_expanded);
+      assert_eq!(jumped.diag.expanded_history.len(), jumped.nodes_expanded);
+
+      let mut masked = Grid::new_masked(Mask::from_ascii("......\n..XX..\n..XX..\n......\n").unwrap());
+      mazegen::empty(&mut masked);
+      assert_shortest(&masked, |i, goal| manhattan_h(i, goal, masked.width), 0);
+   }
+
+   #[test]
+   fn crosses_wrapped_edges_and_floors() {
+      let mut rng = XorShiftRng::seed_from_u64(1);
+      let mut grid = Grid::new_wrapping(12, 9, Wrap::TORUS);
+      mazegen::kruskal(&mut grid, &mut rng);
+      mazegen::braid(&mut grid, &mut rng, 1.0);
+      let (width, height) = (grid.width, grid.height);
+      let h = move |i, goal| wrapping_manhattan_h(i, goal, width, height, Wrap::TORUS);
+      assert_shortest(&grid, h, 0);
+      grid.reset();
+      mazegen::empty(&mut grid);
+      assert_shortest(&grid, h, 50);
+
+      let mut grid = Grid::new_3d(6, 5, 3);
+      mazegen::wilson(&mut grid, &mut rng);
+      mazegen::braid(&mut grid, &mut rng, 1.0);
+      let h = move |i, goal| manhattan_3d_h(i, goal, 6, 5);
+      assert_shortest(&grid, h, 0);
+      grid.reset();
+      mazegen::empty(&mut grid);
+      assert_shortest(&grid, h, 31);
+   }
+}
@@ -1,3 +1,6 @@
+pub mod agents;
 pub mod algos;
 pub mod diagnostic_map;
 pub mod heuristics;
+pub mod jps;
+pub mod solver;
@@ -0,0 +1,198 @@
+//! Picking a search from `algos` or `jps` by name, so the command line, the web front end and anything else that takes
+//! the name of a search from the user agree on what the names are.
+
+use super::algos::{self, BoundedPathData, PathData};
+use super::diagnostic_map::FinalizedDiagMap;
+use super::{heuristics, jps};
+use crate::grid::Grid;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Solver {
+   UniformCostSearch,
+   AStar,
+   GreedyBestFirst,
+   DepthFirstSearch,
+   BreadthFirstSearch,
+   BidirectionalBreadthFirstSearch,
+   JumpPointSearch,
+   IterativeDeepeningAStar,
+   BeamSearch,
+}
+
+pub const SOLVERS: [Solver; 9] = [
+   Solver::UniformCostSearch,
+   Solver::AStar,
+   Solver::GreedyBestFirst,
+   Solver::DepthFirstSearch,
+   Solver::BreadthFirstSearch,
+   Solver::BidirectionalBreadthFirstSearch,
+   Solver::JumpPointSearch,
+   Solver::IterativeDeepeningAStar,
+   Solver::BeamSearch,
+];
+
+/// How many cells of each layer `Solver::BeamSearch` keeps
+pub const BEAM_WIDTH: usize = 256;
+
+/// What `Solver::solve` finds. The memory-bounded searches don't keep track of what they explore, and marking every
+/// cell unexplored for them would take the memory they were meant to save, so they are left as `BoundedPathData`.
+pub enum Solution {
+   Full(PathData),
+   Bounded(BoundedPathData),
+}
+
+impl Solution {
+   pub fn path(&self) -> &[usize] {
+      match self {
+         Solution::Full(x) => &x.path,
+         Solution::Bounded(x) => &x.path,
+      }
+   }
+
+   pub fn nodes_generated(&self) -> usize {
+      match self {
+         Solution::Full(x) => x.nodes_generated,
+         Solution::Bounded(x) => x.nodes_generated,
+      }
+   }
+
+   pub fn nodes_expanded(&self) -> usize {
+      match self {
+         Solution::Full(x) => x.nodes_expanded,
+         Solution::Bounded(x) => x.nodes_expanded,
+      }
+   }
+
+   /// What the search explored, unless it is one of the memory-bounded searches
+   pub fn diag(&self) -> Option<&FinalizedDiagMap> {
+      match self {
+         Solution::Full(x) => Some(&x.diag),
+         Solution::Bounded(_) => None,
+      }
+   }
+
+   /// As `PathData`, which for the memory-bounded searches means allocating a `FinalizedDiagMap` of `size` cells
+   pub fn into_path_data(self, size: usize) -> PathData {
+      match self {
+         Solution::Full(x) => x,
+         Solution::Bounded(x) => x.into_path_data(size),
+      }
+   }
+}
+
+impl Solver {
+   /// The name `Solver::from_str` turns back into this search. Unlike `Display`, it never changes.
+   pub fn id(self) -> &'static str {
+      match self {
+         Solver::UniformCostSearch => "UniformCostSearch",
+         Solver::AStar => "AStar",
+         Solver::GreedyBestFirst => "GreedyBestFirst",
+         Solver::DepthFirstSearch => "DepthFirstSearch",
+         Solver::BreadthFirstSearch => "BreadthFirstSearch",
+         Solver::BidirectionalBreadthFirstSearch => "BidirectionalBreadthFirstSearch",
+         Solver::JumpPointSearch => "JumpPointSearch",
+         Solver::IterativeDeepeningAStar => "IterativeDeepeningAStar",
+         Solver::BeamSearch => "BeamSearch",
+      }
+   }
+
+   /// Finds a path from `start` to `goal`. `h` estimates the distance from a cell (first argument) to the goal (second
+   /// argument); uniform cost search and the breadth-first searches ignore it.
+   pub fn solve<F>(self, grid: &Grid, h: F, start: usize, goal: usize) -> Option<Solution>
+   where
+      F: Fn(usize, usize) -> usize,
+   {
+      let full = match self {
+         Solver::UniformCostSearch => algos::a_star(grid, heuristics::null_h, start, goal, false),
+         Solver::AStar => algos::a_star(grid, h, start, goal, false),
+         Solver::GreedyBestFirst => algos::a_star(grid, h, start, goal, true),
+         Solver::DepthFirstSearch => algos::dfs(grid, h, start, goal),
+         Solver::BreadthFirstSearch => algos::bfs(grid, start, goal),
+         Solver::BidirectionalBreadthFirstSearch => algos::bidirectional_bfs(grid, start, goal),
+         Solver::JumpPointSearch => jps::jps(grid, h, start, goal),
+         Solver::IterativeDeepeningAStar => {
+            return algos::ida_star(grid, h, start, goal).map(Solution::Bounded);
+         }
+         Solver::BeamSearch => {
+            return algos::beam_search(grid, h, start, goal, BEAM_WIDTH).map(Solution::Bounded);
+         }
+      };
+      full.map(Solution::Full)
+   }
+}
+
+impl Display for Solver {
+   fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+      f.write_str(match self {
+         Solver::UniformCostSearch => "Uniform Cost Search",
+         Solver::AStar => "A*",
+         Solver::GreedyBestFirst => "Greedy Best-First Search",
+         Solver::DepthFirstSearch => "Depth-First Search",
+         Solver::BreadthFirstSearch => "Breadth-First Search",
+         Solver::BidirectionalBreadthFirstSearch => "Bidirectional Breadth-First Search",
+         Solver::JumpPointSearch => "Jump Point Search",
+         Solver::IterativeDeepeningAStar => "Iterative Deepening A*",
+         Solver::BeamSearch => "Beam Search",
+      })
+   }
+}
+
+/// The name given isn't the `id` of any `Solver`
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownSolver(pub String);
+
+impl Display for UnknownSolver {
+   fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+      write!(f, "unknown solver {:?}", self.0)
+   }
+}
+
+impl Error for UnknownSolver {}
+
+impl FromStr for Solver {
+   type Err = UnknownSolver;
+
+   fn from_str(s: &str) -> Result<Solver, UnknownSolver> {
+      SOLVERS
+         .iter()
+         .copied()
+         .find(|x| x.id() == s)
+         .ok_or_else(|| UnknownSolver(s.to_string()))
+   }
+}
+
+#[cfg(test)]
+mod test {
+   use super::{SOLVERS, Solver, UnknownSolver};
+   use crate::grid::Grid;
+   use crate::pathfinding::heuristics;
+
+   #[test]
+   fn names_round_trip() {
+      for solver in SOLVERS {
+         assert_eq!(solver.id().parse::<Solver>(), Ok(solver));
+      }
+      assert_eq!("A*".parse::<Solver>(), Err(UnknownSolver("A*".to_string())));
+   }
+
+   #[test]
+   fn solvers_find_the_only_path() {
+      // a single corridor from the upper left, around to the lower left
+      let mut grid = Grid::new(3, 2);
+      grid.connect_cell_east(0);
+      grid.connect_cell_east(1);
+      grid.connect_cell_south(2);
+      grid.connect_cell_east(4);
+      grid.connect_cell_east(3);
+      let h = |i, goal| heuristics::manhattan_h(i, goal, grid.width);
+      for solver in SOLVERS {
+         let solution = solver.solve(&grid, h, 0, 3).unwrap();
+         assert_eq!(solution.path(), &[0, 1, 2, 5, 4, 3], "{}", solver);
+         let bounded = matches!(solver, Solver::IterativeDeepeningAStar | Solver::BeamSearch);
+         assert_eq!(solution.diag().is_none(), bounded, "{}", solver);
+      }
+   }
+}
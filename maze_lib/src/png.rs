@@ -0,0 +1,270 @@
+//! Just enough of a PNG encoder to write truecolor images a row at a time, without holding the whole image in memory.
+//!
+//! Every row is filtered against the row above it, so rows that repeat compress down to runs of zeros. The deflate
+//! stream is a single block with the fixed Huffman codes, and matches are found greedily with a single entry hash
+//! table, which is plenty for the repeating patterns of a maze.
+
+use std::io::{self, Write};
+
+const SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// Compressed data is written out in IDAT chunks of about this size
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// How far back a match can refer
+const WINDOW_SIZE: usize = 1 << 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+
+const LENGTH_BASE: [usize; 29] = [
+   3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u32; 29] = [
+   0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+const DISTANCE_BASE: [usize; 30] = [
+   1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+   8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u32; 30] = [
+   0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+   let mut table = [0; 256];
+   let mut n = 0;
+   while n < 256 {
+      let mut c = n as u32;
+      let mut k = 0;
+      while k < 8 {
+         c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+         k += 1;
+      }
+      table[n] = c;
+      n += 1;
+   }
+   table
+}
+
+fn crc32(parts: &[&[u8]]) -> u32 {
+   let mut c = 0xffff_ffff;
+   for part in parts {
+      for b in part.iter() {
+         c = CRC_TABLE[((c ^ u32::from(*b)) & 0xff) as usize] ^ (c >> 8);
+      }
+   }
+   c ^ 0xffff_ffff
+}
+
+fn write_chunk<W: Write>(dest: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+   dest.write_all(&(data.len() as u32).to_be_bytes())?;
+   dest.write_all(kind)?;
+   dest.write_all(data)?;
+   dest.write_all(&crc32(&[kind, data]).to_be_bytes())
+}
+
+pub(crate) struct PngEncoder<W: Write> {
+   dest: W,
+   width: usize,
+   /// Compressed bytes that haven't been written out yet
+   out: Vec<u8>,
+   bit_buf: u64,
+   bit_count: u32,
+   adler_a: u32,
+   adler_b: u32,
+   prev_row: Vec<u8>,
+   /// The uncompressed bytes that matches can still refer back to, followed by the bytes still to be compressed
+   window: Vec<u8>,
+   /// How many bytes have been dropped from the front of `window`
+   window_start: usize,
+   /// Where in the whole stream each hash of three bytes was last seen
+   last_seen: Box<[usize]>,
+}
+
+impl<W: Write> PngEncoder<W> {
+   /// Writes the header for an image of `width` by `height` pixels
+   pub fn new(mut dest: W, width: usize, height: usize) -> io::Result<PngEncoder<W>> {
+      let too_big = || io::Error::other("image is too big for PNG");
+      let mut header = Vec::with_capacity(13);
+      header.extend_from_slice(&u32::try_from(width).map_err(|_| too_big())?.to_be_bytes());
+      header.extend_from_slice(&u32::try_from(height).map_err(|_| too_big())?.to_be_bytes());
+      // 8 bits per channel, truecolor, deflate, adaptive filtering, no interlacing
+      header.extend_from_slice(&[8, 2, 0, 0, 0]);
+      dest.write_all(SIGNATURE)?;
+      write_chunk(&mut dest, b"IHDR", &header)?;
+
+      let mut encoder = PngEncoder {
+         dest,
+         width,
+         out: Vec::with_capacity(CHUNK_SIZE * 2),
+         bit_buf: 0,
+         bit_count: 0,
+         adler_a: 1,
+         adler_b: 0,
+         prev_row: vec![0; width * 3],
+         window: Vec::with_capacity(WINDOW_SIZE * 2 + width * 3 + 1),
+         window_start: 0,
+         last_seen: vec![usize::MAX; 1 << HASH_BITS].into_boxed_slice(),
+      };
+      // zlib header (deflate with a 32K window, no preset dictionary), then the start of the final, fixed Huffman block
+      encoder.out.extend_from_slice(&[0x78, 0x01]);
+      encoder.write_bits(1, 1);
+      encoder.write_bits(1, 2);
+      Ok(encoder)
+   }
+
+   /// Takes the next row of the image, as RGB triples from left to right
+   pub fn write_row(&mut self, row: &[u8]) -> io::Result<()> {
+      assert_eq!(row.len(), self.width * 3);
+      let start = self.window.len();
+      // filter type 2, "Up"
+      self.window.push(2);
+      self.window.extend(
+         row.iter()
+            .zip(self.prev_row.iter())
+            .map(|(x, above)| x.wrapping_sub(*above)),
+      );
+      self.prev_row.copy_from_slice(row);
+      for i in start..self.window.len() {
+         self.adler_a = (self.adler_a + u32::from(self.window[i])) % 65521;
+         self.adler_b = (self.adler_b + self.adler_a) % 65521;
+      }
+      self.compress(start);
+
+      // keep just enough of what came before for matches to refer back to
+      if self.window.len() > WINDOW_SIZE * 2 {
+         let dropped = self.window.len() - WINDOW_SIZE;
+         self.window.drain(..dropped);
+         self.window_start += dropped;
+      }
+      if self.out.len() >= CHUNK_SIZE {
+         write_chunk(&mut self.dest, b"IDAT", &self.out)?;
+         self.out.clear();
+      }
+      Ok(())
+   }
+
+   /// Ends the image. Every row must have been written.
+   pub fn finish(mut self) -> io::Result<W> {
+      self.write_symbol(256);
+      if self.bit_count > 0 {
+         self.out.push(self.bit_buf as u8);
+      }
+      let adler = (self.adler_b << 16) | self.adler_a;
+      self.out.extend_from_slice(&adler.to_be_bytes());
+      write_chunk(&mut self.dest, b"IDAT", &self.out)?;
+      write_chunk(&mut self.dest, b"IEND", &[])?;
+      Ok(self.dest)
+   }
+
+   /// Greedily encodes the window from `start` on, as the longest match it can find for each position or a literal
+   fn compress(&mut self, start: usize) {
+      let end = self.window.len();
+      let mut pos = start;
+      while pos < end {
+         let mut len = 0;
+         let mut distance = 0;
+         if pos + MIN_MATCH <= end {
+            let hash = self.hash(pos);
+            let candidate = self.last_seen[hash];
+            self.last_seen[hash] = self.window_start + pos;
+            if candidate != usize::MAX && self.window_start + pos - candidate <= WINDOW_SIZE {
+               let candidate = candidate - self.window_start;
+               let max_len = MAX_MATCH.min(end - pos);
+               len = (0..max_len)
+                  .take_while(|i| self.window[candidate + i] == self.window[pos + i])
+                  .count();
+               distance = pos - candidate;
+            }
+         }
+         if len >= MIN_MATCH {
+            self.write_match(len, distance);
+            for i in (pos + 1)..(pos + len).min(end + 1 - MIN_MATCH) {
+               let hash = self.hash(i);
+               self.last_seen[hash] = self.window_start + i;
+            }
+            pos += len;
+         } else {
+            self.write_symbol(u16::from(self.window[pos]));
+            pos += 1;
+         }
+      }
+   }
+
+   fn hash(&self, pos: usize) -> usize {
+      let bytes = &self.window[pos..pos + MIN_MATCH];
+      let x = (u32::from(bytes[0]) << 16) | (u32::from(bytes[1]) << 8) | u32::from(bytes[2]);
+      (x.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+   }
+
+   fn write_match(&mut self, len: usize, distance: usize) {
+      let code = LENGTH_BASE.iter().rposition(|x| *x <= len).unwrap();
+      self.write_symbol(257 + code as u16);
+      self.write_bits((len - LENGTH_BASE[code]) as u64, LENGTH_EXTRA_BITS[code]);
+      // distance codes are all 5 bits long in the fixed Huffman block
+      let code = DISTANCE_BASE.iter().rposition(|x| *x <= distance).unwrap();
+      self.write_bits(u64::from((code as u8).reverse_bits() >> 3), 5);
+      self.write_bits((distance - DISTANCE_BASE[code]) as u64, DISTANCE_EXTRA_BITS[code]);
+   }
+
+   /// Writes a literal/length symbol with its fixed Huffman code
+   fn write_symbol(&mut self, symbol: u16) {
+      let (code, len) = match symbol {
+         0..=143 => (0x30 + symbol, 8),
+         144..=255 => (0x190 + symbol - 144, 9),
+         256..=279 => (symbol - 256, 7),
+         _ => (0xc0 + symbol - 280, 8),
+      };
+      // Huffman codes are packed starting from their most significant bit
+      let reversed = code.reverse_bits() >> (16 - len);
+      self.write_bits(u64::from(reversed), len);
+   }
+
+   fn write_bits(&mut self, bits: u64, len: u32) {
+      self.bit_buf |= bits << self.bit_count;
+      self.bit_count += len;
+      while self.bit_count >= 8 {
+         self.out.push(self.bit_buf as u8);
+         self.bit_buf >>= 8;
+         self.bit_count -= 8;
+      }
+   }
+}
+
+#[cfg(test)]
+mod test {
+   use super::PngEncoder;
+
+   #[test]
+   fn crc() {
+      assert_eq!(super::crc32(&[b"1234", b"56789"]), 0xcbf4_3926);
+   }
+
+   #[test]
+   fn chunks_are_well_formed() {
+      let mut encoder = PngEncoder::new(Vec::new(), 300, 2).unwrap();
+      encoder.write_row(&[0x7f; 900]).unwrap();
+      encoder.write_row(&[0x7f; 900]).unwrap();
+      let png = encoder.finish().unwrap();
+
+      assert_eq!(&png[..8], super::SIGNATURE);
+      let mut pos = 8;
+      let mut kinds = Vec::new();
+      while pos < png.len() {
+         let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+         let kind = &png[pos + 4..pos + 8];
+         let data = &png[pos + 8..pos + 8 + len];
+         let crc = u32::from_be_bytes(png[pos + 8 + len..pos + 12 + len].try_into().unwrap());
+         assert_eq!(crc, super::crc32(&[kind, data]));
+         kinds.push(kind.to_vec());
+         pos += 12 + len;
+      }
+      assert_eq!(kinds, [b"IHDR".to_vec(), b"IDAT".to_vec(), b"IEND".to_vec()]);
+      // 1802 bytes of runs compress to a few dozen
+      assert!(png.len() < 100);
+   }
+}
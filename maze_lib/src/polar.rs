@@ -0,0 +1,283 @@
+use crate::graph::MazeGraph;
+use std::f64::consts::PI;
+use std::io::{self, Write};
+
+/// A cell in a ring of a `PolarGrid`.
+///
+/// Outward passages are not stored here; a cell is connected outward if any of the cells in the next ring that lie
+/// against it are connected inward.
+#[derive(Copy, Clone, Default)]
+pub struct PolarCell {
+   pub inward_connected: bool,
+   pub cw_connected: bool,
+   pub ccw_connected: bool,
+}
+
+/// A circular grid of concentric rings around a single center cell.
+///
+/// Rings further out are subdivided so that cells stay roughly square, meaning a cell can have one inward neighbor,
+/// a clockwise and counter-clockwise neighbor, and one or more outward neighbors. Cells are indexed ring by ring,
+/// starting with the center cell at index 0, and going clockwise within each ring.
+pub struct PolarGrid {
+   pub inner: Box<[PolarCell]>,
+   pub rows: usize,
+   /// The index of the first cell of each ring, with one extra entry at the end holding the total number of cells
+   row_starts: Box<[usize]>,
+}
+
+impl PolarGrid {
+   pub fn new(rows: usize) -> PolarGrid {
+      assert!(rows > 0);
+      let mut row_starts = Vec::with_capacity(rows + 1);
+      row_starts.push(0);
+      row_starts.push(1);
+      let row_height = 1.0 / rows as f64;
+      let mut previous_count = 1;
+      for row in 1..rows {
+         let radius = row as f64 / rows as f64;
+         let circumference = 2.0 * PI * radius;
+         let estimated_cell_width = circumference / previous_count as f64;
+         let ratio = (estimated_cell_width / row_height).round().max(1.0) as usize;
+         previous_count *= ratio;
+         row_starts.push(row_starts[row] + previous_count);
+      }
+      PolarGrid {
+         inner: vec![PolarCell::default(); row_starts[rows]].into_boxed_slice(),
+         rows,
+         row_starts: row_starts.into_boxed_slice(),
+      }
+   }
+
+   pub fn reset(&mut self) {
+      for x in self.inner.iter_mut() {
+         *x = PolarCell::default();
+      }
+   }
+
+   pub fn size(&self) -> usize {
+      self.inner.len()
+   }
+
+   /// The number of cells in the given ring
+   pub fn row_len(&self, row: usize) -> usize {
+      self.row_starts[row + 1] - self.row_starts[row]
+   }
+
+   pub fn row(&self, index: usize) -> usize {
+      self.row_starts.partition_point(|x| *x <= index) - 1
+   }
+
+   pub fn index(&self, row: usize, col: usize) -> usize {
+      self.row_starts[row] + col
+   }
+
+   pub fn has_neighbor_inward(&self, index: usize) -> bool {
+      index > 0
+   }
+
+   pub fn inward(&self, index: usize) -> usize {
+      let row = self.row(index);
+      let col = index - self.row_starts[row];
+      let ratio = self.row_len(row) / self.row_len(row - 1);
+      self.index(row - 1, col / ratio)
+   }
+
+   pub fn cw(&self, index: usize) -> usize {
+      let row = self.row(index);
+      let col = index - self.row_starts[row];
+      self.index(row, (col + 1) % self.row_len(row))
+   }
+
+   pub fn ccw(&self, index: usize) -> usize {
+      let row = self.row(index);
+      let col = index - self.row_starts[row];
+      let len = self.row_len(row);
+      self.index(row, (col + len - 1) % len)
+   }
+
+   /// The cells in the next ring out that lie against the given cell
+   pub fn outward(&self, index: usize) -> std::ops::Range<usize> {
+      let row = self.row(index);
+      if row + 1 == self.rows {
+         return 0..0;
+      }
+      let col = index - self.row_starts[row];
+      let ratio = self.row_len(row + 1) / self.row_len(row);
+      self.index(row + 1, col * ratio)..self.index(row + 1, (col + 1) * ratio)
+   }
+
+   pub fn neighbors(&self, index: usize, buf: &mut Vec<usize>) {
+      if self.has_neighbor_inward(index) {
+         buf.push(self.inward(index));
+         buf.push(self.cw(index));
+         buf.push(self.ccw(index));
+      }
+      buf.extend(self.outward(index));
+   }
+
+   pub fn num_connections(&self, index: usize) -> usize {
+      let cell = self.inner[index];
+      cell.inward_connected as usize
+         + cell.cw_connected as usize
+         + cell.ccw_connected as usize
+         + self.outward(index).filter(|x| self.inner[*x].inward_connected).count()
+   }
+
+   pub fn dead_ends(&self) -> impl Iterator<Item = usize> + '_ {
+      (0..self.size()).filter(|x| self.num_connections(*x) == 1)
+   }
+
+   pub fn check_if_neighbors_and_connected(&self, i1: usize, i2: usize) -> bool {
+      if i1 == i2 {
+         return false;
+      }
+      if self.has_neighbor_inward(i1) {
+         if self.inward(i1) == i2 {
+            return self.inner[i1].inward_connected;
+         }
+         if self.cw(i1) == i2 {
+            return self.inner[i1].cw_connected;
+         }
+         if self.ccw(i1) == i2 {
+            return self.inner[i1].ccw_connected;
+         }
+      }
+      self.outward(i1).contains(&i2) && self.inner[i2].inward_connected
+   }
+
+   /// If the cells are not neighbors, an incorrect connection will be made
+   pub fn connect_neighbors(&mut self, i1: usize, i2: usize) {
+      let (row_1, row_2) = (self.row(i1), self.row(i2));
+      if row_1 == row_2 {
+         if self.cw(i1) == i2 {
+            self.inner[i1].cw_connected = true;
+            self.inner[i2].ccw_connected = true;
+         } else {
+            self.inner[i1].ccw_connected = true;
+            self.inner[i2].cw_connected = true;
+         }
+      } else if row_1 < row_2 {
+         self.inner[i2].inward_connected = true;
+      } else {
+         self.inner[i1].inward_connected = true;
+      }
+   }
+
+   /// If the cells are not neighbors, an incorrect wall will be put up
+   pub fn disconnect_neighbors(&mut self, i1: usize, i2: usize) {
+      let (row_1, row_2) = (self.row(i1), self.row(i2));
+      if row_1 == row_2 {
+         if self.cw(i1) == i2 {
+            self.inner[i1].cw_connected = false;
+            self.inner[i2].ccw_connected = false;
+         } else {
+            self.inner[i1].ccw_connected = false;
+            self.inner[i2].cw_connected = false;
+         }
+      } else if row_1 < row_2 {
+         self.inner[i2].inward_connected = false;
+      } else {
+         self.inner[i1].inward_connected = false;
+      }
+   }
+
+   pub fn write_maze_as_svg<W: Write>(&self, dest: &mut W) -> io::Result<()> {
+      let center = (self.rows * 3) as f64;
+      let point = |radius: f64, theta: f64| (center + radius * theta.cos(), center + radius * theta.sin());
+      for i in 1..self.size() {
+         let row = self.row(i);
+         let col = i - self.row_starts[row];
+
+         let theta = 2.0 * PI / self.row_len(row) as f64;
+         let inner_radius = (row * 3) as f64;
+         let outer_radius = ((row + 1) * 3) as f64;
+         let theta_ccw = col as f64 * theta;
+         let theta_cw = (col + 1) as f64 * theta;
+
+         let (ax, ay) = point(inner_radius, theta_ccw);
+         let (bx, by) = point(inner_radius, theta_cw);
+         let (dx, dy) = point(outer_radius, theta_cw);
+
+         if !self.inner[i].inward_connected {
+            writeln!(
+               dest,
+               "<path d=\"M {:.3} {:.3} A {} {} 0 0 1 {:.3} {:.3}\" fill=\"none\"/>",
+               ax, ay, inner_radius, inner_radius, bx, by
+            )?;
+         }
+         if !self.inner[i].cw_connected {
+            writeln!(
+               dest,
+               "<line x1=\"{:.3}\" y1=\"{:.3}\" x2=\"{:.3}\" y2=\"{:.3}\"/>",
+               bx, by, dx, dy
+            )?;
+         }
+      }
+      // outer wall
+      writeln!(
+         dest,
+         "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\"/>",
+         center, center, center
+      )?;
+      Ok(())
+   }
+}
+
+impl MazeGraph for PolarGrid {
+   fn size(&self) -> usize {
+      PolarGrid::size(self)
+   }
+
+   fn neighbors(&self, index: usize, buf: &mut Vec<usize>) {
+      PolarGrid::neighbors(self, index, buf)
+   }
+
+   fn link(&mut self, i1: usize, i2: usize) {
+      self.connect_neighbors(i1, i2)
+   }
+
+   fn unlink(&mut self, i1: usize, i2: usize) {
+      self.disconnect_neighbors(i1, i2)
+   }
+
+   fn is_linked(&self, i1: usize, i2: usize) -> bool {
+      self.check_if_neighbors_and_connected(i1, i2)
+   }
+}
+
+#[cfg(test)]
+mod test {
+   use super::PolarGrid;
+   use crate::{graph, mazegen};
+   use rand::SeedableRng;
+   use rand_xorshift::XorShiftRng;
+
+   #[test]
+   fn rings_subdivide() {
+      let g = PolarGrid::new(5);
+      let lens: Vec<usize> = (0..5).map(|r| g.row_len(r)).collect();
+      assert_eq!(lens, [1, 6, 12, 24, 24]);
+      assert_eq!(g.inward(1), 0);
+      assert_eq!(g.outward(1), 7..9);
+      assert_eq!(g.inward(8), 1);
+      assert_eq!(g.cw(6), 1);
+      assert_eq!(g.ccw(1), 6);
+      assert_eq!(g.outward(g.size() - 1), 0..0);
+   }
+
+   #[test]
+   fn generators_carve_perfect_mazes() {
+      let mut rng = XorShiftRng::seed_from_u64(0);
+      let generators: [fn(&mut PolarGrid, &mut XorShiftRng); 4] = [
+         mazegen::recursive_backtracker,
+         mazegen::kruskal,
+         mazegen::wilson,
+         mazegen::aldous_broder,
+      ];
+      for generator in generators {
+         let mut g = PolarGrid::new(6);
+         generator(&mut g, &mut rng);
+         graph::assert_perfect(&g);
+      }
+   }
+}
@@ -0,0 +1,282 @@
+//! Drawing a `Grid` as a raster image, for mazes too big to draw as SVG.
+//!
+//! Images are produced a row of pixels at a time, so even the biggest mazes never have to fit in memory as pixels.
+
+use crate::bit_set::BitSet;
+use crate::grid::{Direction, Grid};
+use crate::heatmap::{self, ColorRamp, Heatmap};
+use crate::pathfinding::diagnostic_map::{DIAG_EXPANDED, DIAG_GENERATED, FinalizedDiagMap};
+use crate::png::PngEncoder;
+use std::io::{self, Write};
+
+pub type Rgb = [u8; 3];
+
+pub const WALL: Rgb = [0x00, 0x00, 0x00];
+pub const BACKGROUND: Rgb = [0xff, 0xff, 0xff];
+pub const PATH: Rgb = [0xff, 0x00, 0x00];
+pub const GENERATED: Rgb = [0xff, 0xff, 0x00];
+pub const EXPANDED: Rgb = [0xff, 0x8c, 0x00];
+
+/// A `Grid` along with the layers to draw on top of it. From the bottom up, these are
+/// - the distance of each cell from somewhere, as a heatmap
+/// - the cells a solver generated and expanded, colored like `write_diag_to_svg`
+/// - a path, colored like `write_path_to_svg`
+///
+/// Floors are drawn side by side like `Grid::write_maze_as_svg`.
+pub struct Raster<'a> {
+   pub grid: &'a Grid,
+   /// Width and height of the inside of a cell, in pixels
+   pub cell_size: usize,
+   /// Thickness of a wall, in pixels
+   pub wall_size: usize,
+   pub path: &'a [usize],
+   pub diag: Option<&'a FinalizedDiagMap>,
+   /// Cells that are `usize::MAX` away are left out of the heatmap
+   pub distances: Option<&'a [usize]>,
+   /// The colors of the heatmap
+   pub ramp: ColorRamp,
+}
+
+/// What is worked out once before drawing the pixels: the cells on the path, the passages it takes, and the heatmap
+struct Overlay<'a> {
+   path: BitSet,
+   path_south: BitSet,
+   path_east: BitSet,
+   heatmap: Option<Heatmap<'a>>,
+}
+
+impl<'a> Raster<'a> {
+   /// The bare maze, with cells of 4 pixels and walls of 1 pixel, and the green heatmap of the web front end
+   pub fn new(grid: &'a Grid) -> Raster<'a> {
+      Raster {
+         grid,
+         cell_size: 4,
+         wall_size: 1,
+         path: &[],
+         diag: None,
+         distances: None,
+         ramp: heatmap::green,
+      }
+   }
+
+   /// The width and height of the image, in pixels
+   pub fn size(&self) -> (usize, usize) {
+      let pitch = self.cell_size + self.wall_size;
+      (
+         self.grid.depth * (self.grid.width + 1) * pitch - pitch + self.wall_size,
+         self.grid.height * pitch + self.wall_size,
+      )
+   }
+
+   pub fn write_png<W: Write>(&self, dest: &mut W) -> io::Result<()> {
+      let (width, height) = self.size();
+      let mut encoder = PngEncoder::new(dest, width, height)?;
+      self.for_each_row(|row| encoder.write_row(row))?;
+      encoder.finish()?;
+      Ok(())
+   }
+
+   /// Writes the image as a binary PPM (`P6`)
+   pub fn write_ppm<W: Write>(&self, dest: &mut W) -> io::Result<()> {
+      let (width, height) = self.size();
+      write!(dest, "P6\n{} {}\n255\n", width, height)?;
+      self.for_each_row(|row| dest.write_all(row))
+   }
+
+   fn for_each_row<F: FnMut(&[u8]) -> io::Result<()>>(&self, mut f: F) -> io::Result<()> {
+      let overlay = self.overlay();
+      let (width, height) = self.size();
+      let mut row = vec![0; width * 3];
+      for y in 0..height {
+         self.fill_row(y, &overlay, &mut row);
+         f(&row)?;
+      }
+      Ok(())
+   }
+
+   fn overlay(&self) -> Overlay<'a> {
+      let mut overlay = Overlay {
+         path: BitSet::new(self.grid.size()),
+         path_south: BitSet::new(self.grid.size()),
+         path_east: BitSet::new(self.grid.size()),
+         heatmap: self.distances.map(|x| Heatmap::new(x, self.ramp)),
+      };
+      for i in self.path.iter() {
+         overlay.path.insert(*i);
+      }
+      for step in self.path.windows(2) {
+         match self.grid.direction_to(step[0], step[1]) {
+            Some(Direction::South) => overlay.path_south.insert(step[0]),
+            Some(Direction::North) => overlay.path_south.insert(step[1]),
+            Some(Direction::East) => overlay.path_east.insert(step[0]),
+            Some(Direction::West) => overlay.path_east.insert(step[1]),
+            _ => {}
+         }
+      }
+      overlay
+   }
+
+   fn cell_color(&self, index: usize, overlay: &Overlay) -> Rgb {
+      if overlay.path.get(index) {
+         PATH
+      } else {
+         self.base_color(index, overlay)
+      }
+   }
+
+   /// The color of the cell without the path, which is also the color of the passages out of the cell that the path
+   /// doesn't take
+   fn base_color(&self, index: usize, overlay: &Overlay) -> Rgb {
+      if !self.grid.is_enabled(index) {
+         return BACKGROUND;
+      }
+      match self.diag.map(|x| x.inner[index]) {
+         Some(DIAG_EXPANDED) => return EXPANDED,
+         Some(DIAG_GENERATED) => return GENERATED,
+         _ => {}
+      }
+      overlay
+         .heatmap
+         .as_ref()
+         .and_then(|x| x.color(index))
+         .unwrap_or(BACKGROUND)
+   }
+
+   fn fill_row(&self, y: usize, overlay: &Overlay, out: &mut [u8]) {
+      let grid = self.grid;
+      let pitch = self.cell_size + self.wall_size;
+      let row = y / pitch;
+      let in_horizontal_wall = y % pitch < self.wall_size;
+      let mut pixels = out.chunks_exact_mut(3);
+      let mut fill = |color: Rgb, len: usize| {
+         for pixel in pixels.by_ref().take(len) {
+            pixel.copy_from_slice(&color);
+         }
+      };
+      for floor in 0..grid.depth {
+         if floor > 0 {
+            // the gap between two floors
+            fill(BACKGROUND, self.cell_size);
+         }
+         let floor_start = floor * grid.floor_size();
+         for col in 0..=grid.width {
+            // the index of the cell below and to the right of the corner at `row`, `col`, if there is one
+            let cell = (row < grid.height && col < grid.width).then(|| floor_start + row * grid.width + col);
+            if in_horizontal_wall {
+               let (up, down, left, right) = grid.walls_at_corner(floor_start, row, col);
+               let corner = if up || down || left || right {
+                  WALL
+               } else {
+                  // the corner of an open area takes the color of the cell it belongs to
+                  let row = row.min(grid.height - 1);
+                  let col = col.min(grid.width - 1);
+                  self.base_color(floor_start + row * grid.width + col, overlay)
+               };
+               fill(corner, self.wall_size);
+               if col < grid.width {
+                  let above = row.checked_sub(1).map(|row| floor_start + row * grid.width + col);
+                  let wall = if grid.horizontal_wall(floor_start, row, col) {
+                     WALL
+                  } else if above.is_some_and(|i| overlay.path_south.get(i)) {
+                     PATH
+                  } else {
+                     self.base_color(above.or(cell).unwrap(), overlay)
+                  };
+                  fill(wall, self.cell_size);
+               }
+            } else {
+               let left = col.checked_sub(1).map(|col| floor_start + row * grid.width + col);
+               let wall = if grid.vertical_wall(floor_start, row, col) {
+                  WALL
+               } else if left.is_some_and(|i| overlay.path_east.get(i)) {
+                  PATH
+               } else {
+                  self.base_color(left.or(cell).unwrap(), overlay)
+               };
+               fill(wall, self.wall_size);
+               if let Some(cell) = cell {
+                  fill(self.cell_color(cell, overlay), self.cell_size);
+               }
+            }
+         }
+      }
+   }
+}
+
+#[cfg(test)]
+mod test {
+   use super::{BACKGROUND, PATH, Raster, WALL};
+   use crate::grid::Grid;
+   use crate::heatmap;
+   use crate::mask::Mask;
+   use crate::pathfinding::algos;
+   use crate::pathfinding::heuristics::null_h;
+
+   fn ppm_rows(raster: &Raster) -> Vec<Vec<[u8; 3]>> {
+      let (width, height) = raster.size();
+      let mut ppm = Vec::new();
+      raster.write_ppm(&mut ppm).unwrap();
+      let header = format!("P6\n{} {}\n255\n", width, height);
+      assert!(ppm.starts_with(header.as_bytes()));
+      let pixels: Vec<[u8; 3]> = ppm[header.len()..].chunks(3).map(|x| x.try_into().unwrap()).collect();
+      assert_eq!(pixels.len(), width * height);
+      pixels.chunks(width).map(|x| x.to_vec()).collect()
+   }
+
+   #[test]
+   fn layers() {
+      // the path runs east from the first cell, and the cell below the first is walled off
+      let mut grid = Grid::new(2, 2);
+      grid.connect_cell_east(0);
+      grid.connect_cell_south(1);
+      let pf_data = algos::a_star(&grid, null_h, 0, 1, false).unwrap();
+      let distances = algos::djikstra(&grid, 0);
+      let raster = Raster {
+         cell_size: 2,
+         path: &pf_data.path,
+         distances: Some(&distances),
+         ..Raster::new(&grid)
+      };
+      assert_eq!(raster.size(), (7, 7));
+
+      // cell 1 is halfway between the start and cell 3, the farthest
+      let (heat_1, heat_3) = (heatmap::green(0.5), heatmap::green(1.0));
+      let rows = ppm_rows(&raster);
+      assert!(rows[0].iter().all(|x| *x == WALL));
+      assert_eq!(rows[1], [WALL, PATH, PATH, PATH, PATH, PATH, WALL]);
+      // the passage south from the path isn't part of it
+      assert_eq!(rows[3], [WALL, WALL, WALL, WALL, heat_1, heat_1, WALL]);
+      assert_eq!(rows[4], [WALL, BACKGROUND, BACKGROUND, WALL, heat_3, heat_3, WALL]);
+      assert_eq!(rows[6], rows[0]);
+   }
+
+   #[test]
+   fn masked_cells_and_floors() {
+      let grid = Grid::new_masked(Mask::from_ascii("X.\n").unwrap());
+      let raster = Raster {
+         cell_size: 1,
+         ..Raster::new(&grid)
+      };
+      assert_eq!(ppm_rows(&raster)[1], [BACKGROUND, BACKGROUND, WALL, BACKGROUND, WALL]);
+
+      let grid = Grid::new_3d(1, 1, 2);
+      let raster = Raster {
+         cell_size: 1,
+         ..Raster::new(&grid)
+      };
+      // the floors are a cell apart
+      assert_eq!(
+         ppm_rows(&raster)[1],
+         [WALL, BACKGROUND, WALL, BACKGROUND, WALL, BACKGROUND, WALL]
+      );
+   }
+
+   #[test]
+   fn png_is_compact() {
+      let grid = Grid::new(100, 100);
+      let mut png = Vec::new();
+      Raster::new(&grid).write_png(&mut png).unwrap();
+      // 501 x 501 pixels, which would be 750K uncompressed, but it is the same few rows over and over
+      assert!(png.len() < 20_000);
+   }
+}
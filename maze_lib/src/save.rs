@@ -0,0 +1,487 @@
+//! A versioned binary file format for carved `Grid`s, so that exact mazes can be stored and loaded back.
+//!
+//! All numbers are little endian. A file is laid out as:
+//!
+//! | field    | size                   | contents                                                     |
+//! |----------|------------------------|--------------------------------------------------------------|
+//! | magic    | 4                      | `MAZE`                                                       |
+//! | version  | 1                      | `FORMAT_VERSION`                                             |
+//! | width    | 4                      |                                                              |
+//! | height   | 4                      |                                                              |
+//! | depth    | 4                      | number of floors                                             |
+//! | flags    | 1                      | wraps east-west, wraps north-south, has a mask, has metadata |
+//! | mask     | ⌈width × height / 8⌉   | one bit per cell of a floor, set if the cell is enabled      |
+//! | cells    | width × height × depth | one byte per cell: north, south, east, west, up, down bits   |
+//! | metadata | variable               | see `Metadata`                                               |
+//!
+//! The mask and metadata are only present if their flag is set.
+
+use crate::grid::{Cell, Grid, Wrap};
+use crate::mask::Mask;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Read, Write};
+
+pub const MAGIC: &[u8; 4] = b"MAZE";
+pub const FORMAT_VERSION: u8 = 1;
+
+const FLAG_WRAP_EAST_WEST: u8 = 1 << 0;
+const FLAG_WRAP_NORTH_SOUTH: u8 = 1 << 1;
+const FLAG_MASK: u8 = 1 << 2;
+const FLAG_METADATA: u8 = 1 << 3;
+
+const CELL_NORTH: u8 = 1 << 0;
+const CELL_SOUTH: u8 = 1 << 1;
+const CELL_EAST: u8 = 1 << 2;
+const CELL_WEST: u8 = 1 << 3;
+const CELL_UP: u8 = 1 << 4;
+const CELL_DOWN: u8 = 1 << 5;
+
+/// Optional information about how a maze came to be and how it is meant to be solved.
+///
+/// Each field is stored as a presence byte followed by its value if present: the algorithm as a `u16` length and UTF-8
+/// name, the seed, start and goal as `u64`s.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metadata {
+   /// The name of the algorithm that carved the maze
+   pub algorithm: Option<String>,
+   pub seed: Option<u64>,
+   pub start: Option<usize>,
+   pub goal: Option<usize>,
+}
+
+impl Metadata {
+   fn is_empty(&self) -> bool {
+      *self == Metadata::default()
+   }
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+   Io(io::Error),
+   /// The data does not start with `MAGIC`
+   BadMagic,
+   /// The data was written by a newer version of the format, or claims a version of 0, which was never written
+   UnsupportedVersion(u8),
+   /// The data ends before all the cells (or metadata) have been read
+   Truncated,
+   /// There is more data after the end of the maze
+   TrailingData,
+   /// The grid is empty, too big to fit in memory, or too small for its wrapped edges
+   BadDimensions,
+   /// A cell is connected to a cell that isn't its neighbor, or that isn't connected back
+   InconsistentCell {
+      index: usize,
+   },
+   BadMetadata(&'static str),
+}
+
+impl Display for LoadError {
+   fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+      match self {
+         LoadError::Io(e) => write!(f, "failed to read maze: {}", e),
+         LoadError::BadMagic => write!(f, "not a maze file"),
+         LoadError::UnsupportedVersion(version) => write!(
+            f,
+            "maze file has format version {}, but only versions up to {} are supported",
+            version, FORMAT_VERSION
+         ),
+         LoadError::Truncated => write!(f, "maze file is truncated"),
+         LoadError::TrailingData => write!(f, "unexpected data after the end of the maze"),
+         LoadError::BadDimensions => write!(f, "maze has invalid dimensions"),
+         LoadError::InconsistentCell { index } => write!(
+            f,
+            "cell {} is connected to a cell that is not its neighbor or is not connected back",
+            index
+         ),
+         LoadError::BadMetadata(reason) => write!(f, "bad metadata: {}", reason),
+      }
+   }
+}
+
+impl Error for LoadError {
+   fn source(&self) -> Option<&(dyn Error + 'static)> {
+      match self {
+         LoadError::Io(e) => Some(e),
+         _ => None,
+      }
+   }
+}
+
+impl From<io::Error> for LoadError {
+   fn from(e: io::Error) -> LoadError {
+      LoadError::Io(e)
+   }
+}
+
+pub fn save<W: Write>(grid: &Grid, metadata: &Metadata, dest: &mut W) -> io::Result<()> {
+   dest.write_all(MAGIC)?;
+   dest.write_all(&[FORMAT_VERSION])?;
+   for dimension in [grid.width, grid.height, grid.depth] {
+      let dimension = u32::try_from(dimension).map_err(|_| io::Error::other("grid is too big to save"))?;
+      dest.write_all(&dimension.to_le_bytes())?;
+   }
+
+   let mut flags = 0;
+   if grid.wrap.east_west {
+      flags |= FLAG_WRAP_EAST_WEST;
+   }
+   if grid.wrap.north_south {
+      flags |= FLAG_WRAP_NORTH_SOUTH;
+   }
+   if grid.mask.is_some() {
+      flags |= FLAG_MASK;
+   }
+   if !metadata.is_empty() {
+      flags |= FLAG_METADATA;
+   }
+   dest.write_all(&[flags])?;
+
+   if let Some(ref mask) = grid.mask {
+      let mut bits = vec![0u8; grid.floor_size().div_ceil(8)];
+      for i in 0..grid.floor_size() {
+         if mask.is_enabled(i) {
+            bits[i / 8] |= 1 << (i % 8);
+         }
+      }
+      dest.write_all(&bits)?;
+   }
+
+   let cells: Vec<u8> = (0..grid.size()).map(|i| cell_to_byte(grid.cell(i))).collect();
+   dest.write_all(&cells)?;
+
+   if !metadata.is_empty() {
+      write_metadata(metadata, dest)?;
+   }
+   Ok(())
+}
+
+/// Reads everything from `src` and parses it as a saved maze
+pub fn load<R: Read>(src: &mut R) -> Result<(Grid, Metadata), LoadError> {
+   let mut data = Vec::new();
+   src.read_to_end(&mut data)?;
+   from_bytes(&data)
+}
+
+pub fn from_bytes(data: &[u8]) -> Result<(Grid, Metadata), LoadError> {
+   let mut reader = Reader { data, pos: 0 };
+   if reader.take(MAGIC.len())? != MAGIC {
+      return Err(LoadError::BadMagic);
+   }
+   let version = reader.u8()?;
+   if version == 0 || version > FORMAT_VERSION {
+      return Err(LoadError::UnsupportedVersion(version));
+   }
+   let width = reader.u32()? as usize;
+   let height = reader.u32()? as usize;
+   let depth = reader.u32()? as usize;
+   let flags = reader.u8()?;
+   let wrap = Wrap {
+      east_west: flags & FLAG_WRAP_EAST_WEST != 0,
+      north_south: flags & FLAG_WRAP_NORTH_SOUTH != 0,
+   };
+   let size = width
+      .checked_mul(height)
+      .and_then(|x| x.checked_mul(depth))
+      .ok_or(LoadError::BadDimensions)?;
+   if size == 0 || (wrap.east_west && width < 3) || (wrap.north_south && height < 3) {
+      return Err(LoadError::BadDimensions);
+   }
+
+   let mask = if flags & FLAG_MASK != 0 {
+      let bits = reader.take((width * height).div_ceil(8))?;
+      let mut mask = Mask::new(width, height);
+      for i in 0..(width * height) {
+         mask.set_enabled(i, bits[i / 8] & (1 << (i % 8)) != 0);
+      }
+      Some(mask)
+   } else {
+      None
+   };
+
+   // read the cells before allocating the grid, so that a bogus header can't make us allocate a huge grid
+   let cells = reader.take(size)?;
+   let mut grid = Grid::new_3d(width, height, depth);
+   grid.wrap = wrap;
+   grid.mask = mask;
+   for i in 0..grid.size() {
+      check_cell(&grid, cells, i)?;
+   }
+   // the grid only stores the south, east and up connections, the others have been checked to match them
+   for (i, byte) in cells.iter().enumerate() {
+      if byte & CELL_SOUTH != 0 {
+         grid.connect_cell_south(i);
+      }
+      if byte & CELL_EAST != 0 {
+         grid.connect_cell_east(i);
+      }
+      if byte & CELL_UP != 0 {
+         grid.connect_cell_up(i);
+      }
+   }
+
+   let metadata = if flags & FLAG_METADATA != 0 {
+      read_metadata(&mut reader)?
+   } else {
+      Metadata::default()
+   };
+   for i in [metadata.start, metadata.goal].into_iter().flatten() {
+      if i >= grid.size() {
+         return Err(LoadError::BadMetadata("start or goal is outside the grid"));
+      }
+   }
+
+   if reader.pos != data.len() {
+      return Err(LoadError::TrailingData);
+   }
+   Ok((grid, metadata))
+}
+
+fn cell_to_byte(cell: Cell) -> u8 {
+   let mut byte = 0;
+   for (connected, bit) in [
+      (cell.north_connected, CELL_NORTH),
+      (cell.south_connected, CELL_SOUTH),
+      (cell.east_connected, CELL_EAST),
+      (cell.west_connected, CELL_WEST),
+      (cell.up_connected, CELL_UP),
+      (cell.down_connected, CELL_DOWN),
+   ] {
+      if connected {
+         byte |= bit;
+      }
+   }
+   byte
+}
+
+/// Every connection of the cell must lead to a neighbor that is connected back
+fn check_cell(grid: &Grid, cells: &[u8], index: usize) -> Result<(), LoadError> {
+   let connected = |i: usize, bit: u8| cells[i] & bit != 0;
+   let consistent = [
+      (
+         CELL_NORTH,
+         CELL_SOUTH,
+         grid.has_neighbor_north(index),
+         grid.north(index),
+      ),
+      (
+         CELL_SOUTH,
+         CELL_NORTH,
+         grid.has_neighbor_south(index),
+         grid.south(index),
+      ),
+      (CELL_EAST, CELL_WEST, grid.has_neighbor_east(index), grid.east(index)),
+      (CELL_WEST, CELL_EAST, grid.has_neighbor_west(index), grid.west(index)),
+      (
+         CELL_UP,
+         CELL_DOWN,
+         grid.has_neighbor_up(index),
+         index + grid.floor_size(),
+      ),
+      (
+         CELL_DOWN,
+         CELL_UP,
+         grid.has_neighbor_down(index),
+         index.wrapping_sub(grid.floor_size()),
+      ),
+   ]
+   .into_iter()
+   .all(|(bit, opposite, has_neighbor, neighbor)| {
+      !connected(index, bit) || (has_neighbor && connected(neighbor, opposite))
+   });
+   if consistent {
+      Ok(())
+   } else {
+      Err(LoadError::InconsistentCell { index })
+   }
+}
+
+fn write_metadata<W: Write>(metadata: &Metadata, dest: &mut W) -> io::Result<()> {
+   match metadata.algorithm {
+      Some(ref name) => {
+         let len = u16::try_from(name.len()).map_err(|_| io::Error::other("algorithm name is too long"))?;
+         dest.write_all(&[1])?;
+         dest.write_all(&len.to_le_bytes())?;
+         dest.write_all(name.as_bytes())?;
+      }
+      None => dest.write_all(&[0])?,
+   }
+   let numbers = [
+      metadata.seed,
+      metadata.start.map(|x| x as u64),
+      metadata.goal.map(|x| x as u64),
+   ];
+   for number in numbers {
+      match number {
+         Some(x) => {
+            dest.write_all(&[1])?;
+            dest.write_all(&x.to_le_bytes())?;
+         }
+         None => dest.write_all(&[0])?,
+      }
+   }
+   Ok(())
+}
+
+fn read_metadata(reader: &mut Reader) -> Result<Metadata, LoadError> {
+   let algorithm = if reader.present()? {
+      let len = u16::from_le_bytes(reader.array()?) as usize;
+      let name = reader.take(len)?;
+      let name = std::str::from_utf8(name).map_err(|_| LoadError::BadMetadata("algorithm name is not UTF-8"))?;
+      Some(name.to_owned())
+   } else {
+      None
+   };
+   let seed = reader.optional_u64()?;
+   let mut index = || -> Result<Option<usize>, LoadError> {
+      reader
+         .optional_u64()?
+         .map(|x| usize::try_from(x).map_err(|_| LoadError::BadMetadata("start or goal is outside the grid")))
+         .transpose()
+   };
+   let start = index()?;
+   let goal = index()?;
+   Ok(Metadata {
+      algorithm,
+      seed,
+      start,
+      goal,
+   })
+}
+
+struct Reader<'a> {
+   data: &'a [u8],
+   pos: usize,
+}
+
+impl<'a> Reader<'a> {
+   fn take(&mut self, len: usize) -> Result<&'a [u8], LoadError> {
+      let end = self.pos.checked_add(len).ok_or(LoadError::Truncated)?;
+      let bytes = self.data.get(self.pos..end).ok_or(LoadError::Truncated)?;
+      self.pos = end;
+      Ok(bytes)
+   }
+
+   fn array<const N: usize>(&mut self) -> Result<[u8; N], LoadError> {
+      Ok(self.take(N)?.try_into().unwrap())
+   }
+
+   fn u8(&mut self) -> Result<u8, LoadError> {
+      Ok(self.take(1)?[0])
+   }
+
+   fn u32(&mut self) -> Result<u32, LoadError> {
+      Ok(u32::from_le_bytes(self.array()?))
+   }
+
+   fn present(&mut self) -> Result<bool, LoadError> {
+      match self.u8()? {
+         0 => Ok(false),
+         1 => Ok(true),
+         _ => Err(LoadError::BadMetadata("presence byte must be 0 or 1")),
+      }
+   }
+
+   fn optional_u64(&mut self) -> Result<Option<u64>, LoadError> {
+      if self.present()? {
+         Ok(Some(u64::from_le_bytes(self.array()?)))
+      } else {
+         Ok(None)
+      }
+   }
+}
+
+#[cfg(test)]
+mod test {
+   use super::{LoadError, Metadata};
+   use crate::grid::{Grid, Wrap};
+   use crate::mask::Mask;
+   use crate::mazegen;
+   use rand::SeedableRng;
+   use rand_xorshift::XorShiftRng;
+
+   fn round_trip(grid: &Grid, metadata: &Metadata) -> (Grid, Metadata) {
+      let mut bytes = Vec::new();
+      super::save(grid, metadata, &mut bytes).unwrap();
+      super::load(&mut bytes.as_slice()).unwrap()
+   }
+
+   #[test]
+   fn round_trips() {
+      let mut rng = XorShiftRng::seed_from_u64(11);
+      let metadata = Metadata {
+         algorithm: Some("Wilson's".to_owned()),
+         seed: Some(11),
+         start: Some(0),
+         goal: Some(3),
+      };
+      let mut grids = [
+         Grid::new(9, 4),
+         Grid::new_3d(5, 4, 3),
+         Grid::new_wrapping(6, 5, Wrap::TORUS),
+         Grid::new_masked(Mask::from_ascii("X...X\n.....\n..X..\n").unwrap()),
+      ];
+      for grid in grids.iter_mut() {
+         mazegen::wilson(grid, &mut rng);
+         for metadata in [Metadata::default(), metadata.clone()] {
+            let (loaded, loaded_metadata) = round_trip(grid, &metadata);
+            assert_eq!(loaded_metadata, metadata);
+            assert_eq!(loaded.to_string(), grid.to_string());
+            assert_eq!(
+               (loaded.width, loaded.height, loaded.depth),
+               (grid.width, grid.height, grid.depth)
+            );
+            assert_eq!(loaded.wrap, grid.wrap);
+            assert_eq!(loaded.num_enabled(), grid.num_enabled());
+            for i in 0..grid.size() {
+               assert_eq!(super::cell_to_byte(loaded.cell(i)), super::cell_to_byte(grid.cell(i)));
+            }
+         }
+      }
+   }
+
+   #[test]
+   fn rejects_bad_data() {
+      let mut rng = XorShiftRng::seed_from_u64(4);
+      let mut grid = Grid::new(4, 4);
+      mazegen::recursive_backtracker(&mut grid, &mut rng);
+      let mut bytes = Vec::new();
+      super::save(&grid, &Metadata::default(), &mut bytes).unwrap();
+
+      assert!(matches!(super::from_bytes(b"MAZ"), Err(LoadError::Truncated)));
+      assert!(matches!(super::from_bytes(b"ZAME"), Err(LoadError::BadMagic)));
+      assert!(matches!(
+         super::from_bytes(&bytes[..bytes.len() - 1]),
+         Err(LoadError::Truncated)
+      ));
+
+      for version in [0, super::FORMAT_VERSION + 1] {
+         let mut unsupported = bytes.clone();
+         unsupported[4] = version;
+         assert!(matches!(
+            super::from_bytes(&unsupported),
+            Err(LoadError::UnsupportedVersion(x)) if x == version
+         ));
+      }
+
+      let mut trailing = bytes.clone();
+      trailing.push(0);
+      assert!(matches!(super::from_bytes(&trailing), Err(LoadError::TrailingData)));
+
+      // the cells start after the 18 byte header; knock out the first cell's connections but not its neighbors'
+      let mut inconsistent = bytes.clone();
+      inconsistent[18] = 0;
+      assert!(matches!(
+         super::from_bytes(&inconsistent),
+         Err(LoadError::InconsistentCell { .. })
+      ));
+
+      // a connection off the north edge
+      let mut off_edge = bytes.clone();
+      off_edge[18] |= super::CELL_NORTH;
+      assert!(matches!(
+         super::from_bytes(&off_edge),
+         Err(LoadError::InconsistentCell { index: 0 })
+      ));
+   }
+}
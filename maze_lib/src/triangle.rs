@@ -0,0 +1,242 @@
+use crate::graph::MazeGraph;
+use std::io::{self, Write};
+
+/// A cell in a `TriangleGrid`.
+///
+/// Every triangle has a west and east neighbor, plus a third neighbor across its base: to the south for a triangle
+/// pointing up, to the north for one pointing down.
+#[derive(Copy, Clone, Default)]
+pub struct TriangleCell {
+   pub west_connected: bool,
+   pub east_connected: bool,
+   pub base_connected: bool,
+}
+
+impl TriangleCell {
+   pub fn num_connections(self) -> u8 {
+      self.west_connected as u8 + self.east_connected as u8 + self.base_connected as u8
+   }
+}
+
+/// A grid of alternating upward and downward pointing triangles. Cells are indexed row by row, like `Grid`, and the
+/// cell in the top left corner points up.
+pub struct TriangleGrid {
+   pub inner: Box<[TriangleCell]>,
+   pub width: usize,
+   pub height: usize,
+}
+
+/// Length of a side of a triangle, in SVG units
+const TRIANGLE_SIZE: f64 = 3.0;
+
+impl TriangleGrid {
+   pub fn new(width: usize, height: usize) -> TriangleGrid {
+      TriangleGrid {
+         inner: vec![TriangleCell::default(); width * height].into_boxed_slice(),
+         width,
+         height,
+      }
+   }
+
+   pub fn reset(&mut self) {
+      for x in self.inner.iter_mut() {
+         *x = TriangleCell::default();
+      }
+   }
+
+   pub fn size(&self) -> usize {
+      self.inner.len()
+   }
+
+   pub fn is_upright(&self, index: usize) -> bool {
+      let row = index / self.width;
+      let col = index % self.width;
+      (row + col).is_multiple_of(2)
+   }
+
+   pub fn has_neighbor_west(&self, index: usize) -> bool {
+      !index.is_multiple_of(self.width)
+   }
+
+   pub fn has_neighbor_east(&self, index: usize) -> bool {
+      index % self.width != (self.width - 1)
+   }
+
+   pub fn has_neighbor_base(&self, index: usize) -> bool {
+      if self.is_upright(index) {
+         index < (self.width * (self.height - 1))
+      } else {
+         index >= self.width
+      }
+   }
+
+   /// The cell across the base of the triangle
+   pub fn base(&self, index: usize) -> usize {
+      if self.is_upright(index) {
+         index + self.width
+      } else {
+         index - self.width
+      }
+   }
+
+   pub fn neighbors(&self, index: usize, buf: &mut Vec<usize>) {
+      if self.has_neighbor_west(index) {
+         buf.push(index - 1);
+      }
+      if self.has_neighbor_east(index) {
+         buf.push(index + 1);
+      }
+      if self.has_neighbor_base(index) {
+         buf.push(self.base(index));
+      }
+   }
+
+   pub fn dead_ends(&self) -> impl Iterator<Item = &TriangleCell> {
+      self.inner.iter().filter(|x| x.num_connections() == 1)
+   }
+
+   pub fn check_if_neighbors_and_connected(&self, i1: usize, i2: usize) -> bool {
+      if i2 + 1 == i1 && self.has_neighbor_west(i1) {
+         self.inner[i1].west_connected
+      } else if i2 == i1 + 1 && self.has_neighbor_east(i1) {
+         self.inner[i1].east_connected
+      } else if self.has_neighbor_base(i1) && self.base(i1) == i2 {
+         self.inner[i1].base_connected
+      } else {
+         false
+      }
+   }
+
+   /// If the cells are not neighbors, an incorrect connection will be made
+   pub fn connect_neighbors(&mut self, i1: usize, i2: usize) {
+      if i2 + 1 == i1 {
+         self.inner[i1].west_connected = true;
+         self.inner[i2].east_connected = true;
+      } else if i2 == i1 + 1 {
+         self.inner[i1].east_connected = true;
+         self.inner[i2].west_connected = true;
+      } else {
+         self.inner[i1].base_connected = true;
+         self.inner[i2].base_connected = true;
+      }
+   }
+
+   /// If the cells are not neighbors, an incorrect wall will be put up
+   pub fn disconnect_neighbors(&mut self, i1: usize, i2: usize) {
+      if i2 + 1 == i1 {
+         self.inner[i1].west_connected = false;
+         self.inner[i2].east_connected = false;
+      } else if i2 == i1 + 1 {
+         self.inner[i1].east_connected = false;
+         self.inner[i2].west_connected = false;
+      } else {
+         self.inner[i1].base_connected = false;
+         self.inner[i2].base_connected = false;
+      }
+   }
+
+   /// The width and height of the maze as drawn by `write_maze_as_svg`
+   pub fn svg_size(&self) -> (f64, f64) {
+      let height = TRIANGLE_SIZE * 3f64.sqrt() / 2.0;
+      (
+         (self.width + 1) as f64 * TRIANGLE_SIZE / 2.0,
+         self.height as f64 * height,
+      )
+   }
+
+   pub fn write_maze_as_svg<W: Write>(&self, dest: &mut W) -> io::Result<()> {
+      let half_width = TRIANGLE_SIZE / 2.0;
+      let height = TRIANGLE_SIZE * 3f64.sqrt() / 2.0;
+      let half_height = height / 2.0;
+      for (i, cell) in self.inner.iter().enumerate() {
+         let row = i / self.width;
+         let col = i % self.width;
+
+         let cx = half_width + col as f64 * half_width;
+         let cy = half_height + row as f64 * height;
+
+         let west_x = cx - half_width;
+         let mid_x = cx;
+         let east_x = cx + half_width;
+         let (apex_y, base_y) = if self.is_upright(i) {
+            (cy - half_height, cy + half_height)
+         } else {
+            (cy + half_height, cy - half_height)
+         };
+
+         let mut line = |x1: f64, y1: f64, x2: f64, y2: f64| {
+            writeln!(
+               dest,
+               "<line x1=\"{:.3}\" y1=\"{:.3}\" x2=\"{:.3}\" y2=\"{:.3}\"/>",
+               x1, y1, x2, y2
+            )
+         };
+
+         // walls shared with another cell are drawn by only one of the two
+         if !self.has_neighbor_west(i) {
+            line(west_x, base_y, mid_x, apex_y)?;
+         }
+         if !cell.east_connected {
+            line(east_x, base_y, mid_x, apex_y)?;
+         }
+         let draw_base = if self.is_upright(i) {
+            !self.has_neighbor_base(i)
+         } else {
+            !cell.base_connected
+         };
+         if draw_base {
+            line(east_x, base_y, west_x, base_y)?;
+         }
+      }
+      Ok(())
+   }
+}
+
+impl MazeGraph for TriangleGrid {
+   fn size(&self) -> usize {
+      TriangleGrid::size(self)
+   }
+
+   fn neighbors(&self, index: usize, buf: &mut Vec<usize>) {
+      TriangleGrid::neighbors(self, index, buf)
+   }
+
+   fn link(&mut self, i1: usize, i2: usize) {
+      self.connect_neighbors(i1, i2)
+   }
+
+   fn unlink(&mut self, i1: usize, i2: usize) {
+      self.disconnect_neighbors(i1, i2)
+   }
+
+   fn is_linked(&self, i1: usize, i2: usize) -> bool {
+      self.check_if_neighbors_and_connected(i1, i2)
+   }
+}
+
+#[cfg(test)]
+mod test {
+   use super::TriangleGrid;
+   use crate::{graph, mazegen};
+   use rand::SeedableRng;
+   use rand_xorshift::XorShiftRng;
+
+   #[test]
+   fn generators_carve_perfect_mazes() {
+      let mut rng = XorShiftRng::seed_from_u64(0);
+      let generators: [fn(&mut TriangleGrid, &mut XorShiftRng); 7] = [
+         mazegen::aldous_broder,
+         mazegen::wilson,
+         mazegen::hunt_and_kill,
+         mazegen::recursive_backtracker,
+         mazegen::kruskal,
+         mazegen::prim_simplified,
+         mazegen::prim_true,
+      ];
+      for generator in generators {
+         let mut g = TriangleGrid::new(9, 7);
+         generator(&mut g, &mut rng);
+         graph::assert_perfect(&g);
+      }
+   }
+}
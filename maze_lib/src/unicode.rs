@@ -0,0 +1,206 @@
+//! Drawing a `Grid` with Unicode box-drawing characters, optionally marking a solution and what a solver explored.
+
+use crate::grid::Grid;
+use crate::pathfinding::diagnostic_map::{DIAG_EXPANDED, DIAG_GENERATED, FinalizedDiagMap};
+use std::fmt::{self, Display, Formatter};
+
+/// A `Grid` drawn like `impl Display for Grid`, but with box-drawing characters and proper junctions.
+///
+/// Inside each cell, the start and goal are marked `S` and `G` and cells along the path with `•`. Cells that the solver
+/// generated are shaded `░`, and cells it expanded `▒`. Stairs up and down are marked `↑`, `↓` or `↕`.
+pub struct UnicodeMaze<'a> {
+   pub grid: &'a Grid,
+   pub path: &'a [usize],
+   pub start: Option<usize>,
+   pub goal: Option<usize>,
+   pub diag: Option<&'a FinalizedDiagMap>,
+}
+
+impl<'a> UnicodeMaze<'a> {
+   /// The bare maze, without any overlay
+   pub fn new(grid: &'a Grid) -> UnicodeMaze<'a> {
+      UnicodeMaze {
+         grid,
+         path: &[],
+         start: None,
+         goal: None,
+         diag: None,
+      }
+   }
+
+   /// The junction at the top left corner of the cell at `row`, `col`
+   fn junction(&self, floor_start: usize, row: usize, col: usize) -> char {
+      match self.grid.walls_at_corner(floor_start, row, col) {
+         (false, false, false, false) => ' ',
+         (true, false, false, false) => '╵',
+         (false, true, false, false) => '╷',
+         (false, false, true, false) => '╴',
+         (false, false, false, true) => '╶',
+         (true, true, false, false) => '│',
+         (false, false, true, true) => '─',
+         (false, true, false, true) => '┌',
+         (false, true, true, false) => '┐',
+         (true, false, false, true) => '└',
+         (true, false, true, false) => '┘',
+         (true, true, false, true) => '├',
+         (true, true, true, false) => '┤',
+         (false, true, true, true) => '┬',
+         (true, false, true, true) => '┴',
+         (true, true, true, true) => '┼',
+      }
+   }
+
+   fn write_interior(&self, f: &mut Formatter, index: usize, on_path: &[bool]) -> fmt::Result {
+      let shade = match self.diag.map(|x| x.inner[index]) {
+         Some(DIAG_EXPANDED) => '▒',
+         Some(DIAG_GENERATED) => '░',
+         _ => ' ',
+      };
+      let cell = self.grid.cell(index);
+      let center = if self.start == Some(index) {
+         'S'
+      } else if self.goal == Some(index) {
+         'G'
+      } else if on_path[index] {
+         '•'
+      } else {
+         match (cell.up_connected, cell.down_connected) {
+            (true, true) => '↕',
+            (true, false) => '↑',
+            (false, true) => '↓',
+            (false, false) => shade,
+         }
+      };
+      write!(f, "{}{}{}", shade, center, shade)
+   }
+
+   fn fmt_floor(&self, f: &mut Formatter, floor: usize, on_path: &[bool]) -> fmt::Result {
+      let grid = self.grid;
+      let floor_start = floor * grid.floor_size();
+      for row in 0..=grid.height {
+         // the wall along the top of the row
+         for col in 0..=grid.width {
+            write!(f, "{}", self.junction(floor_start, row, col))?;
+            if col < grid.width {
+               f.write_str(if self.grid.horizontal_wall(floor_start, row, col) {
+                  "───"
+               } else {
+                  "   "
+               })?;
+            }
+         }
+         writeln!(f)?;
+         if row == grid.height {
+            break;
+         }
+         // the cells of the row
+         for col in 0..=grid.width {
+            write!(
+               f,
+               "{}",
+               if self.grid.vertical_wall(floor_start, row, col) {
+                  '│'
+               } else {
+                  ' '
+               }
+            )?;
+            if col < grid.width {
+               self.write_interior(f, floor_start + row * grid.width + col, on_path)?;
+            }
+         }
+         writeln!(f)?;
+      }
+      Ok(())
+   }
+}
+
+impl Display for UnicodeMaze<'_> {
+   fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+      let mut on_path = vec![false; self.grid.size()];
+      for i in self.path.iter() {
+         on_path[*i] = true;
+      }
+      for floor in 0..self.grid.depth {
+         if floor > 0 {
+            writeln!(f)?;
+         }
+         self.fmt_floor(f, floor, &on_path)?;
+      }
+      Ok(())
+   }
+}
+
+#[cfg(test)]
+mod test {
+   use super::UnicodeMaze;
+   use crate::grid::Grid;
+   use crate::mask::Mask;
+   use crate::pathfinding::algos;
+   use crate::pathfinding::heuristics::manhattan_h;
+
+   const MAZE: &str = "\
++---+---+---+---+
+|       |       |
++---+   +   +   +
+|       |   |   |
++   +---+   +   +
+|           |   |
++---+---+---+---+
+";
+
+   #[test]
+   fn junctions() {
+      let grid = Grid::from_ascii(MAZE).unwrap();
+      assert_eq!(
+         UnicodeMaze::new(&grid).to_string(),
+         "\
+┌───────┬───────┐
+│       │       │
+├───╴   │   ╷   │
+│       │   │   │
+│   ╶───┘   │   │
+│           │   │
+└───────────┴───┘
+"
+      );
+   }
+
+   #[test]
+   fn solution_overlay() {
+      let grid = Grid::from_ascii(MAZE).unwrap();
+      let h = |i, goal| manhattan_h(i, goal, grid.width);
+      let pf_data = algos::a_star(&grid, h, 0, 3, false).unwrap();
+      let maze = UnicodeMaze {
+         grid: &grid,
+         path: &pf_data.path,
+         start: Some(0),
+         goal: Some(3),
+         diag: Some(&pf_data.diag),
+      };
+      let drawing = maze.to_string();
+      let lines: Vec<&str> = drawing.lines().collect();
+      assert_eq!(lines[1], "│▒S▒ ▒•▒│▒•▒ ░G░│");
+      // every cell of the path is marked, along with the start and goal
+      assert_eq!(drawing.matches('•').count(), pf_data.path.len() - 2);
+   }
+
+   #[test]
+   fn masked_cells_are_blank() {
+      let mut grid = Grid::new_masked(Mask::from_ascii("X..\n...\n").unwrap());
+      for i in 1..grid.size() {
+         if grid.has_neighbor_east(i) {
+            grid.connect_cell_east(i);
+         }
+      }
+      grid.connect_cell_south(1);
+      assert_eq!(
+         UnicodeMaze::new(&grid).to_string(),
+         "    ┌───────┐
+    │       │
+┌───┘   ╶───┤
+│           │
+└───────────┘
+"
+      );
+   }
+}
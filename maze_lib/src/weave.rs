@@ -0,0 +1,360 @@
+use crate::graph::MazeGraph;
+use crate::grid::{Direction, Grid};
+use crate::mask::Mask;
+use std::io::{self, Write};
+
+/// A passage that tunnels beneath a cell of a `WeaveGrid`, joining the two cells on either side of it.
+#[derive(Copy, Clone)]
+pub struct UnderCell {
+   /// The cell the passage runs beneath
+   pub over: usize,
+   /// Whether the passage runs north to south, rather than east to west
+   pub vertical: bool,
+}
+
+/// A `Grid` where a passage can tunnel beneath a neighboring cell, as long as that cell is a straight corridor running
+/// across the tunnel.
+///
+/// Tunnels are part of the graph as under-cells, which are numbered after the cells of the grid: under-cell `k` has
+/// index `grid.size() + k`. Under-cells are never neighbors of anything (they share no walls), but they are linked to
+/// the two cells at either end of their tunnel. Tunnels are carved by linking two cells that lie two apart in a row or
+/// column; `neighbors` offers those cells whenever the cell in between can be tunneled beneath.
+///
+/// Since under-cells are created as the maze is carved, only generators that don't rely on the size of the graph
+/// staying put (the Recursive Backtracker, and `mazegen::kruskal_weave`) will carve tunnels. The edges of a weave grid
+/// never wrap around, which is why the grid can only be read from outside.
+pub struct WeaveGrid {
+   grid: Grid,
+   under: Vec<UnderCell>,
+   /// For each cell of the grid, the under-cell beneath it (if any), as an index into `under`
+   under_index: Box<[Option<usize>]>,
+}
+
+impl WeaveGrid {
+   pub fn new(width: usize, height: usize) -> WeaveGrid {
+      WeaveGrid::from_grid(Grid::new(width, height))
+   }
+
+   pub fn new_masked(mask: Mask) -> WeaveGrid {
+      WeaveGrid::from_grid(Grid::new_masked(mask))
+   }
+
+   fn from_grid(grid: Grid) -> WeaveGrid {
+      WeaveGrid {
+         under: Vec::new(),
+         under_index: vec![None; grid.size()].into_boxed_slice(),
+         grid,
+      }
+   }
+
+   pub fn grid(&self) -> &Grid {
+      &self.grid
+   }
+
+   pub fn reset(&mut self) {
+      self.grid.reset();
+      self.under.clear();
+      for x in self.under_index.iter_mut() {
+         *x = None;
+      }
+   }
+
+   /// The number of cells, including under-cells
+   pub fn size(&self) -> usize {
+      self.grid.size() + self.under.len()
+   }
+
+   pub fn under_cells(&self) -> &[UnderCell] {
+      &self.under
+   }
+
+   /// The index of the under-cell beneath the given cell of the grid, if there is one
+   pub fn under(&self, index: usize) -> Option<usize> {
+      self.under_index[index].map(|k| self.grid.size() + k)
+   }
+
+   /// The cell of the grid at the same position as the given cell. This is the cell itself, except for under-cells,
+   /// which sit beneath a cell of the grid.
+   pub fn position(&self, index: usize) -> usize {
+      if index < self.grid.size() {
+         index
+      } else {
+         self.under[index - self.grid.size()].over
+      }
+   }
+
+   /// The two cells joined by an under-cell
+   fn ends(&self, under: UnderCell) -> (usize, usize) {
+      if under.vertical {
+         (under.over - self.grid.width, under.over + self.grid.width)
+      } else {
+         (under.over - 1, under.over + 1)
+      }
+   }
+
+   fn step(&self, index: usize, direction: Direction) -> Option<usize> {
+      match direction {
+         Direction::North if self.grid.has_neighbor_north(index) => Some(index - self.grid.width),
+         Direction::South if self.grid.has_neighbor_south(index) => Some(index + self.grid.width),
+         Direction::East if self.grid.has_neighbor_east(index) => Some(index + 1),
+         Direction::West if self.grid.has_neighbor_west(index) => Some(index - 1),
+         _ => None,
+      }
+   }
+
+   /// Whether a tunnel running in the given direction can pass beneath the cell
+   fn can_tunnel_under(&self, index: usize, direction: Direction) -> bool {
+      let cell = self.grid.cell(index);
+      let (across, along) = if direction.is_vertical() {
+         (
+            cell.east_connected && cell.west_connected,
+            cell.north_connected || cell.south_connected,
+         )
+      } else {
+         (
+            cell.north_connected && cell.south_connected,
+            cell.east_connected || cell.west_connected,
+         )
+      };
+      across && !along && self.under_index[index].is_none()
+   }
+
+   /// The cell beneath which a tunnel between the two cells would pass, if they lie two apart in a row or column
+   fn middle(&self, i1: usize, i2: usize) -> Option<(usize, bool)> {
+      let width = self.grid.width;
+      if i1 >= self.grid.size() || i2 >= self.grid.size() {
+         None
+      } else if i1.abs_diff(i2) == 2 * width {
+         Some((i1.min(i2) + width, true))
+      } else if i1.abs_diff(i2) == 2 && i1 / width == i2 / width {
+         Some((i1.min(i2) + 1, false))
+      } else {
+         None
+      }
+   }
+
+   pub fn neighbors(&self, index: usize, buf: &mut Vec<usize>) {
+      if index >= self.grid.size() {
+         return;
+      }
+      self.grid.neighbors(index, buf);
+      for direction in Direction::ALL {
+         let Some(middle) = self.step(index, direction) else {
+            continue;
+         };
+         if let Some(far) = self.step(middle, direction)
+            && self.can_tunnel_under(middle, direction)
+         {
+            buf.push(far);
+         }
+      }
+   }
+
+   pub fn links(&self, index: usize, buf: &mut Vec<usize>) {
+      if index >= self.grid.size() {
+         let (a, b) = self.ends(self.under[index - self.grid.size()]);
+         buf.push(a);
+         buf.push(b);
+         return;
+      }
+      MazeGraph::links(&self.grid, index, buf);
+      for direction in Direction::ALL {
+         if let Some(middle) = self.step(index, direction)
+            && let Some(k) = self.under_index[middle]
+            && self.under[k].vertical == direction.is_vertical()
+         {
+            buf.push(self.grid.size() + k);
+         }
+      }
+   }
+
+   pub fn check_if_neighbors_and_connected(&self, i1: usize, i2: usize) -> bool {
+      let size = self.grid.size();
+      match (i1 >= size, i2 >= size) {
+         (false, false) => self.grid.check_if_neighbors_and_connected(i1, i2),
+         (true, false) => {
+            let (a, b) = self.ends(self.under[i1 - size]);
+            i2 == a || i2 == b
+         }
+         (false, true) => self.check_if_neighbors_and_connected(i2, i1),
+         (true, true) => false,
+      }
+   }
+
+   /// Linking two cells that lie two apart in a row or column carves a tunnel beneath the cell between them.
+   /// Otherwise, if the cells are not neighbors, an incorrect connection will be made
+   pub fn connect_neighbors(&mut self, i1: usize, i2: usize) {
+      if let Some((middle, vertical)) = self.middle(i1, i2) {
+         self.under_index[middle] = Some(self.under.len());
+         self.under.push(UnderCell { over: middle, vertical });
+      } else if i1 < self.grid.size() && i2 < self.grid.size() {
+         self.grid.connect_neighbors(i1, i2);
+      }
+   }
+
+   /// Removing a tunnel renumbers the last under-cell to take its place.
+   /// Otherwise, if the cells are not neighbors, an incorrect wall will be put up
+   pub fn disconnect_neighbors(&mut self, i1: usize, i2: usize) {
+      let size = self.grid.size();
+      if let Some((middle, _)) = self.middle(i1, i2) {
+         if let Some(k) = self.under_index[middle] {
+            self.remove_under(k);
+         }
+      } else if i1 >= size {
+         self.remove_under(i1 - size);
+      } else if i2 >= size {
+         self.remove_under(i2 - size);
+      } else {
+         self.grid.disconnect_neighbors(i1, i2);
+      }
+   }
+
+   fn remove_under(&mut self, k: usize) {
+      let removed = self.under.swap_remove(k);
+      self.under_index[removed.over] = None;
+      if let Some(moved) = self.under.get(k) {
+         self.under_index[moved.over] = Some(k);
+      }
+   }
+
+   pub fn write_maze_as_svg<W: Write>(&self, dest: &mut W) -> io::Result<()> {
+      const INSET: f64 = 0.6;
+      let mut line = |x1: f64, y1: f64, x2: f64, y2: f64| {
+         writeln!(dest, "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>", x1, y1, x2, y2)
+      };
+      let mut links = Vec::with_capacity(4);
+      for i in (0..self.grid.size()).filter(|i| self.grid.is_enabled(*i)) {
+         let row = i / self.grid.width;
+         let col = i % self.grid.width;
+
+         // the outer and inset edges of the cell, from west to east and north to south
+         let x1 = (col * 3) as f64;
+         let x2 = x1 + INSET;
+         let x3 = x1 + 3.0 - INSET;
+         let x4 = x1 + 3.0;
+         let y1 = (row * 3) as f64;
+         let y2 = y1 + INSET;
+         let y3 = y1 + 3.0 - INSET;
+         let y4 = y1 + 3.0;
+
+         links.clear();
+         self.links(i, &mut links);
+         let open = |direction: Direction| {
+            self
+               .step(i, direction)
+               .is_some_and(|n| links.contains(&n) || self.under(n).is_some_and(|under| links.contains(&under)))
+         };
+         let under_vertical = self.under_index[i].map(|k| self.under[k].vertical);
+
+         if open(Direction::North) || under_vertical == Some(true) {
+            line(x2, y1, x2, y2)?;
+            line(x3, y1, x3, y2)?;
+         }
+         if !open(Direction::North) {
+            line(x2, y2, x3, y2)?;
+         }
+         if open(Direction::South) || under_vertical == Some(true) {
+            line(x2, y3, x2, y4)?;
+            line(x3, y3, x3, y4)?;
+         }
+         if !open(Direction::South) {
+            line(x2, y3, x3, y3)?;
+         }
+         if open(Direction::West) || under_vertical == Some(false) {
+            line(x1, y2, x2, y2)?;
+            line(x1, y3, x2, y3)?;
+         }
+         if !open(Direction::West) {
+            line(x2, y2, x2, y3)?;
+         }
+         if open(Direction::East) || under_vertical == Some(false) {
+            line(x3, y2, x4, y2)?;
+            line(x3, y3, x4, y3)?;
+         }
+         if !open(Direction::East) {
+            line(x3, y2, x3, y3)?;
+         }
+      }
+      Ok(())
+   }
+}
+
+impl MazeGraph for WeaveGrid {
+   fn size(&self) -> usize {
+      WeaveGrid::size(self)
+   }
+
+   fn neighbors(&self, index: usize, buf: &mut Vec<usize>) {
+      WeaveGrid::neighbors(self, index, buf)
+   }
+
+   fn link(&mut self, i1: usize, i2: usize) {
+      self.connect_neighbors(i1, i2)
+   }
+
+   fn unlink(&mut self, i1: usize, i2: usize) {
+      self.disconnect_neighbors(i1, i2)
+   }
+
+   fn is_linked(&self, i1: usize, i2: usize) -> bool {
+      self.check_if_neighbors_and_connected(i1, i2)
+   }
+
+   fn links(&self, index: usize, buf: &mut Vec<usize>) {
+      WeaveGrid::links(self, index, buf)
+   }
+
+   fn is_enabled(&self, index: usize) -> bool {
+      index >= self.grid.size() || self.grid.is_enabled(index)
+   }
+
+   fn num_enabled(&self) -> usize {
+      self.grid.num_enabled() + self.under.len()
+   }
+}
+
+#[cfg(test)]
+mod test {
+   use super::WeaveGrid;
+   use crate::graph::{self, MazeGraph};
+   use crate::mazegen;
+   use crate::pathfinding::algos::a_star;
+   use crate::pathfinding::heuristics::manhattan_h;
+   use rand::SeedableRng;
+   use rand_xorshift::XorShiftRng;
+
+   #[test]
+   fn generators_carve_tunnels() {
+      let mut rng = XorShiftRng::seed_from_u64(0);
+      let mut g = WeaveGrid::new(20, 20);
+      for i in 0..10 {
+         g.reset();
+         if i % 2 == 0 {
+            mazegen::recursive_backtracker(&mut g, &mut rng);
+         } else {
+            mazegen::kruskal_weave(&mut g, &mut rng, 0.3);
+         }
+         assert!(!g.under_cells().is_empty());
+         graph::assert_perfect(&g);
+
+         let goal = g.grid.size() - 1;
+         let h = |i, goal| manhattan_h(g.position(i), goal, g.grid.width);
+         let path = a_star(&g, h, 0, goal, false).unwrap().path;
+         assert!(path.windows(2).all(|x| g.is_linked(x[0], x[1])));
+      }
+   }
+
+   #[test]
+   fn tunnels_can_be_removed() {
+      let mut g = WeaveGrid::new(3, 3);
+      g.link(3, 4);
+      g.link(4, 5);
+      g.link(1, 7);
+      assert_eq!(g.size(), 9 + 1);
+      assert_eq!(g.under(4), Some(9));
+      assert!(g.is_linked(1, 9) && g.is_linked(9, 7));
+      g.unlink(9, 7);
+      assert_eq!(g.size(), 9);
+      assert_eq!(g.under(4), None);
+   }
+}
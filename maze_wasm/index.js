@@ -1,4 +1,4 @@
-import { change_grid, carve_maze, app_init, pathfind, djikstra, default as init } from './pkg/maze_wasm.js';
+import { change_grid, carve_maze, carve_maze_steps, carve_maze_from_code, carve_maze_steps_from_code, maze_code_size, app_init, pathfind, solve_by_hand, djikstra, default as init } from './pkg/maze_wasm.js';
 
 let initWasm = false;
 let startNode = null;
@@ -18,9 +18,42 @@ let animState = {
    lastTimestamp: null,
 };
 let cached_pf_data = null;
+let cached_agent_steps = null;
+let agentState = {
+   index: 0,
+   delay: 0,
+   acc: 0,
+   id: null,
+   path: null,
+   kinds: null,
+   cells: null,
+   lastTimestamp: null,
+};
+let carveState = {
+   index: 0,
+   delay: 0,
+   acc: 0,
+   id: null,
+   steps: null,
+   kinds: null,
+   cells: null,
+   otherCells: null,
+   lastTimestamp: null,
+};
+
+const CARVE_CONNECT = 0;
+const CARVE_DISCONNECT = 1;
+const CARVE_VISIT = 2;
+const CARVE_FRONTIER_ADD = 3;
+const CARVE_FRONTIER_REMOVE = 4;
+
+const AGENT_MOVE = 0;
+const AGENT_MARK = 1;
+const AGENT_FILL = 2;
 
 function cleanupPathData() {
    window.cancelAnimationFrame(animState.id);
+   window.cancelAnimationFrame(agentState.id);
    let cells = document.getElementsByClassName('cell');
    for (let i = 0; i < cells.length; i++) {
       cells[i].setAttribute('class', 'cell');
@@ -95,17 +128,80 @@ function animateOrPaint(pf_data) {
    }
 }
 
+function applyAgentStep(index) {
+   let cell = agentState.cells[index];
+   switch (agentState.kinds[index]) {
+      case AGENT_MOVE:
+         document.getElementById(cell).setAttribute('class', 'cell expanded');
+         break;
+      case AGENT_MARK:
+         // the move that follows shows where the mark went
+         break;
+      case AGENT_FILL:
+         document.getElementById(cell).setAttribute('class', 'cell generated');
+         break;
+   }
+}
+
+function finishAgentAnim() {
+   for (let i = 0; i < agentState.path.length; i++) {
+      document.getElementById(agentState.path[i]).setAttribute('class', 'cell path');
+   }
+   document.getElementById(startNode).setAttribute('class', 'cell selected');
+   document.getElementById(endNode).setAttribute('class', 'cell selected');
+}
+
+function advanceAgentAnim(timestamp) {
+   let now = performance.now();
+   agentState.acc += now - agentState.lastTimestamp;
+   agentState.lastTimestamp = now;
+   while (agentState.acc >= agentState.delay) {
+      if (agentState.index >= agentState.kinds.length) {
+         finishAgentAnim();
+         return;
+      }
+      applyAgentStep(agentState.index);
+      agentState.index += 1;
+
+      agentState.acc -= agentState.delay;
+   }
+   document.getElementById(startNode).setAttribute('class', 'cell selected');
+   document.getElementById(endNode).setAttribute('class', 'cell selected');
+   agentState.id = window.requestAnimationFrame(advanceAgentAnim);
+}
+
+function replayOrPaint(agent_steps) {
+   let delay = document.getElementById('anim-delay').valueAsNumber;
+   agentState.path = agent_steps.path();
+   agentState.kinds = agent_steps.kinds();
+   agentState.cells = agent_steps.cells();
+   agentState.index = 0;
+   if (delay > 0) {
+      agentState.delay = delay;
+      agentState.acc = 0;
+      agentState.lastTimestamp = performance.now();
+      agentState.id = window.requestAnimationFrame(advanceAgentAnim);
+   } else {
+      for (let i = 0; i < agentState.kinds.length; i++) {
+         applyAgentStep(i);
+      }
+      finishAgentAnim();
+   }
+}
+
 function maybePathfind() {
    if (startNode == null || endNode == null || !initWasm) {
       return;
    }
    let pf_algo_ele = document.getElementById("pathfinding-algo");
-   let pf_algo = pf_algo_ele.options[pf_algo_ele.selectedIndex].value;
+   let pf_option = pf_algo_ele.options[pf_algo_ele.selectedIndex];
+   let pf_algo = pf_option.value;
    if (pf_algo == "None") {
       return;
    }
    cleanupPathData();
    cached_pf_data = null;
+   cached_agent_steps = null;
    if (startNode == endNode) {
       // special, do djikstra gradient visualization
       let cell_colors = djikstra(startNode);
@@ -118,6 +214,14 @@ function maybePathfind() {
       document.getElementById(startNode).style.setProperty('stroke', '');
       return;
    }
+   if (pf_option.parentElement.id == "agent-algos") {
+      // agents don't generate nodes, so every step taken is counted instead
+      cached_agent_steps = solve_by_hand(parseInt(startNode), parseInt(endNode), pf_algo);
+      document.getElementById('nodes-generated').innerHTML = cached_agent_steps.kinds().length;
+      document.getElementById('nodes-expanded').innerHTML = cached_agent_steps.num_moves;
+      replayOrPaint(cached_agent_steps);
+      return;
+   }
    cached_pf_data = pathfind(parseInt(startNode), parseInt(endNode), pf_algo);
    document.getElementById('nodes-generated').innerHTML = cached_pf_data.nodes_generated;
    document.getElementById('nodes-expanded').innerHTML = cached_pf_data.nodes_expanded;
@@ -130,6 +234,7 @@ window.pathfindChange = function pathfindChange(event) {
    if (pf_algo == "None") {
       cleanupPathData();
       cached_pf_data = null;
+      cached_agent_steps = null;
    } else {
       maybePathfind();
    }
@@ -137,6 +242,10 @@ window.pathfindChange = function pathfindChange(event) {
 
 window.animDelayChange = function animDelayChange(event) {
    cleanupPathData();
+   if (cached_agent_steps != null) {
+      replayOrPaint(cached_agent_steps);
+      return;
+   }
    if (cached_pf_data == null) {
       return;
    }
@@ -190,13 +299,69 @@ function maybeUpdateGrid(width, height) {
    }
 }
 
-window.genSetMaze = async function genSetMaze() {
-   // init
+function wallBetween(a, b) {
+   let lower = Math.min(a, b);
+   if (Math.abs(a - b) == 1) {
+      return document.getElementById('we' + lower);
+   }
+   return document.getElementById('ws' + lower);
+}
+
+function finishCarveAnim() {
+   let cells = document.getElementsByClassName('cell');
+   for (let i = 0; i < cells.length; i++) {
+      cells[i].setAttribute('class', 'cell');
+   }
+   document.getElementById("g_maze").remove();
+   document.getElementById("g_skele").insertAdjacentHTML('afterend', carveState.steps.maze_svg());
+   carveState.steps = null;
+   maybePathfind();
+}
+
+function advanceCarveAnim(timestamp) {
+   let now = performance.now();
+   carveState.acc += now - carveState.lastTimestamp;
+   carveState.lastTimestamp = now;
+   while (carveState.acc >= carveState.delay) {
+      if (carveState.index >= carveState.kinds.length) {
+         finishCarveAnim();
+         return;
+      }
+
+      let cell = carveState.cells[carveState.index];
+      let otherCell = carveState.otherCells[carveState.index];
+      switch (carveState.kinds[carveState.index]) {
+         case CARVE_CONNECT:
+            wallBetween(cell, otherCell).style.setProperty('display', 'none');
+            break;
+         case CARVE_DISCONNECT:
+            wallBetween(cell, otherCell).style.setProperty('display', '');
+            break;
+         case CARVE_VISIT:
+         case CARVE_FRONTIER_REMOVE:
+            document.getElementById(cell).setAttribute('class', 'cell visited');
+            break;
+         case CARVE_FRONTIER_ADD:
+            document.getElementById(cell).setAttribute('class', 'cell frontier');
+            break;
+      }
+      carveState.index += 1;
+
+      carveState.acc -= carveState.delay;
+   }
+   carveState.id = window.requestAnimationFrame(advanceCarveAnim);
+}
+
+async function ensureInit() {
    if (!initWasm) {
       await init('./pkg/maze_wasm_bg.wasm');
       app_init();
       initWasm = true;
    }
+}
+
+window.genSetMaze = async function genSetMaze() {
+   await ensureInit();
    // update grid skeleton
    let width = document.getElementById("maze_width").valueAsNumber;
    let height = document.getElementById("maze_height").valueAsNumber;
@@ -209,17 +374,67 @@ window.genSetMaze = async function genSetMaze() {
    let mazegen_algo = mazegen_algo_ele.options[mazegen_algo_ele.selectedIndex].value;
    let mazegen_seed_ele = document.getElementById("mazegen-seed");
    let mazegen_seed = mazegen_seed_ele.value;
-   let maze_data = carve_maze(mazegen_algo, mazegen_seed);
-   document.getElementById('num-deadends').innerHTML = maze_data.num_deadends;
-   let maze_lines_svg = maze_data.maze_svg();
+   let carve_delay = document.getElementById('carve-delay').valueAsNumber;
+   window.cancelAnimationFrame(carveState.id);
+   let maze_data;
+   if (carve_delay > 0) {
+      cleanupPathData();
+      maze_data = carve_maze_steps(mazegen_algo, mazegen_seed);
+   } else {
+      maze_data = carve_maze(mazegen_algo, mazegen_seed);
+   }
    mazegen_seed_ele.value = "";
+   showCarvedMaze(maze_data, carve_delay);
+};
+
+window.loadMazeCode = async function loadMazeCode() {
+   await ensureInit();
+   let code = document.getElementById("maze-code-input").value;
+   let size;
+   try {
+      size = maze_code_size(code);
+   } catch (e) {
+      document.getElementById('maze-code').textContent = e;
+      return;
+   }
+   document.getElementById("maze_width").value = size[0];
+   document.getElementById("maze_height").value = size[1];
+   maybeUpdateGrid(size[0], size[1]);
+   let carve_delay = document.getElementById('carve-delay').valueAsNumber;
+   window.cancelAnimationFrame(carveState.id);
+   let maze_data;
+   if (carve_delay > 0) {
+      cleanupPathData();
+      maze_data = carve_maze_steps_from_code(code);
+   } else {
+      maze_data = carve_maze_from_code(code);
+   }
+   showCarvedMaze(maze_data, carve_delay);
+};
+
+function showCarvedMaze(maze_data, carve_delay) {
+   let maze_lines_svg = carve_delay > 0 ? maze_data.walls_svg() : maze_data.maze_svg();
+   document.getElementById('num-deadends').innerHTML = maze_data.num_deadends;
+   document.getElementById('maze-code').innerHTML = maze_data.code();
    let maze_lines_ele = document.getElementById("g_maze");
    if (maze_lines_ele != null) {
       maze_lines_ele.remove();
    }
    document.getElementById("g_skele").insertAdjacentHTML('afterend', maze_lines_svg);
-   maybePathfind();
-};
+   if (carve_delay > 0) {
+      carveState.steps = maze_data;
+      carveState.kinds = maze_data.kinds();
+      carveState.cells = maze_data.cells();
+      carveState.otherCells = maze_data.other_cells();
+      carveState.index = 0;
+      carveState.delay = carve_delay;
+      carveState.acc = 0;
+      carveState.lastTimestamp = performance.now();
+      carveState.id = window.requestAnimationFrame(advanceCarveAnim);
+   } else {
+      maybePathfind();
+   }
+}
 
 window.addEventListener('DOMContentLoaded', (event) => {
    genSetMaze();
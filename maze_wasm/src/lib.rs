@@ -1,8 +1,13 @@
-use maze_lib::grid::Grid;
-use maze_lib::{mazegen, pathfinding};
-use rand::SeedableRng;
-use rand_xorshift::XorShiftRng;
+use maze_lib::code::{self, MazeCode};
+use maze_lib::grid::{Grid, Wrap};
+use maze_lib::heatmap::{self, Heatmap};
+use maze_lib::mazegen::{self, Algo};
+use maze_lib::pathfinding::agents::{Agent, AgentOutcome, AgentRun, AgentStep};
+use maze_lib::pathfinding::algos::PathData;
+use maze_lib::pathfinding::diagnostic_map::FinalizedDiagMap;
+use maze_lib::pathfinding::{self, solver::Solver};
 use std::io::Write;
+use std::ops::RangeInclusive;
 use std::sync::Mutex;
 
 use wasm_bindgen::prelude::*;
@@ -42,6 +47,29 @@ impl PfDataWasm {
    }
 }
 
+/// Marks the cells of the path on top of the statuses of the search that found it
+impl From<PathData> for PfDataWasm {
+   fn from(pf_data: PathData) -> PfDataWasm {
+      // as with `FinalizedDiagMap`, taken apart so that a new field doesn't compile until it is handed on
+      let PathData {
+         path,
+         diag,
+         nodes_generated,
+         nodes_expanded,
+      } = pf_data;
+      let mut diag = FinalizedDiagMapWasm::from(diag);
+      for i in path.iter().copied() {
+         diag.inner[i] = DIAG_PATH;
+      }
+      PfDataWasm {
+         path,
+         diag,
+         nodes_generated,
+         nodes_expanded,
+      }
+   }
+}
+
 #[wasm_bindgen]
 #[derive(Clone)]
 pub struct FinalizedDiagMapWasm {
@@ -70,25 +98,111 @@ impl FinalizedDiagMapWasm {
    }
 }
 
+impl From<FinalizedDiagMap> for FinalizedDiagMapWasm {
+   fn from(diag: FinalizedDiagMap) -> FinalizedDiagMapWasm {
+      let inner = diag.status_bytes();
+      // taken apart field by field, so a field added to `FinalizedDiagMap` doesn't compile until it is handed on
+      let FinalizedDiagMap {
+         inner: _,
+         generated_history,
+         expanded_history,
+         num_generated_history,
+      } = diag;
+      FinalizedDiagMapWasm {
+         inner,
+         generated_history,
+         expanded_history,
+         num_generated_history,
+      }
+   }
+}
+
 #[wasm_bindgen]
-pub fn pathfind(start: usize, goal: usize, pathfind_algo: &str) -> PfDataWasm {
+pub fn pathfind(start: usize, goal: usize, pathfind_algo: &str) -> Result<PfDataWasm, JsValue> {
+   let solver: Solver = pathfind_algo.parse().map_err(to_js_error)?;
    let app_lock = MAZE_APP.lock().unwrap();
    let app = app_lock.as_ref().unwrap();
-   let pf_data = match pathfind_algo {
-      "UniformCostSearch" => pathfinding::algos::a_star(&app.grid, pathfinding::heuristics::null_h, start, goal, false),
-      "AStar" => pathfinding::algos::a_star(&app.grid, pathfinding::heuristics::manhattan_h, start, goal, false),
-      "GreedyBestFirst" => {
-         pathfinding::algos::a_star(&app.grid, pathfinding::heuristics::manhattan_h, start, goal, true)
-      }
-      "DepthFirstSearch" => pathfinding::algos::dfs(&app.grid, start, goal),
-      _ => panic!("Got a bad pathfinding algo from JS"),
+   let manhattan_h = |i, goal| pathfinding::heuristics::manhattan_h(i, goal, app.grid.width);
+   let solution = solver.solve(&app.grid, manhattan_h, start, goal).unwrap();
+   // every cell is colored by what the search did with it, so the memory-bounded searches get a map with every cell
+   // unexplored. the grids drawn here are small enough for that not to matter.
+   Ok(PfDataWasm::from(solution.into_path_data(app.grid.size())))
+}
+
+const AGENT_MOVE: u8 = 0;
+const AGENT_MARK: u8 = 1;
+const AGENT_FILL: u8 = 2;
+
+/// Every step an `Agent` took, flattened into parallel arrays for JS the same way as `MazeCarveSteps`.
+/// `other_cells` is only meaningful for mark steps. `path` is empty unless the agent reached the goal.
+#[wasm_bindgen]
+pub struct AgentStepsWasm {
+   kinds: Box<[u8]>,
+   cells: Box<[usize]>,
+   other_cells: Box<[usize]>,
+   path: Box<[usize]>,
+   pub num_moves: usize,
+   pub looped: bool,
+}
+
+#[wasm_bindgen]
+impl AgentStepsWasm {
+   pub fn kinds(&self) -> Box<[u8]> {
+      self.kinds.clone()
    }
-   .unwrap();
-   let mut pf_data_wasm: PfDataWasm = unsafe { std::mem::transmute(pf_data) };
-   for i in pf_data_wasm.path.iter().copied() {
-      pf_data_wasm.diag.inner[i] = DIAG_PATH;
+
+   pub fn cells(&self) -> Box<[usize]> {
+      self.cells.clone()
+   }
+
+   pub fn other_cells(&self) -> Box<[usize]> {
+      self.other_cells.clone()
+   }
+
+   pub fn path(&self) -> Box<[usize]> {
+      self.path.clone()
    }
-   pf_data_wasm
+}
+
+impl From<AgentRun> for AgentStepsWasm {
+   fn from(run: AgentRun) -> AgentStepsWasm {
+      let num_moves = run.moves().count();
+      let mut kinds = Vec::with_capacity(run.steps.len());
+      let mut cells = Vec::with_capacity(run.steps.len());
+      let mut other_cells = Vec::with_capacity(run.steps.len());
+      for step in run.steps.iter().copied() {
+         let (kind, cell, other_cell) = match step {
+            AgentStep::Move(a) => (AGENT_MOVE, a, a),
+            AgentStep::Mark(a, b) => (AGENT_MARK, a, b),
+            AgentStep::Fill(a) => (AGENT_FILL, a, a),
+         };
+         kinds.push(kind);
+         cells.push(cell);
+         other_cells.push(other_cell);
+      }
+      let (path, looped) = match run.outcome {
+         AgentOutcome::Solved(path) => (path, false),
+         AgentOutcome::Looped => (Box::default(), true),
+         AgentOutcome::Unreachable => (Box::default(), false),
+      };
+      AgentStepsWasm {
+         kinds: kinds.into_boxed_slice(),
+         cells: cells.into_boxed_slice(),
+         other_cells: other_cells.into_boxed_slice(),
+         path,
+         num_moves,
+         looped,
+      }
+   }
+}
+
+/// Walks from `start` to `goal` with one of the `Agent`s, which only see the cell they're in, unlike `pathfind`
+#[wasm_bindgen]
+pub fn solve_by_hand(start: usize, goal: usize, agent: &str) -> Result<AgentStepsWasm, JsValue> {
+   let agent: Agent = agent.parse().map_err(to_js_error)?;
+   let app_lock = MAZE_APP.lock().unwrap();
+   let app = app_lock.as_ref().unwrap();
+   Ok(AgentStepsWasm::from(agent.run(&app.grid, start, goal)))
 }
 
 #[wasm_bindgen]
@@ -96,13 +210,13 @@ pub fn djikstra(start: usize) -> Box<[u32]> {
    let app_lock = MAZE_APP.lock().unwrap();
    let app = app_lock.as_ref().unwrap();
    let best_paths = pathfinding::algos::djikstra(&app.grid, start);
-   let longest_path = *best_paths.iter().max().unwrap();
+   let heatmap = Heatmap::new(&best_paths, heatmap::green);
+   // cells that can't be reached (i.e. masked off) are left black
    let mut rgb_data = vec![0u32; best_paths.len()].into_boxed_slice();
-   for (rgb, path_len) in rgb_data.iter_mut().zip(best_paths.iter()) {
-      let intensity = (longest_path - *path_len) as f64 / longest_path as f64;
-      let dark = (255.0 * intensity).round() as u32;
-      let bright = 128 + (127.0 * intensity) as u32;
-      *rgb = dark << 16 | bright << 8 | dark;
+   for (i, rgb) in rgb_data.iter_mut().enumerate() {
+      if let Some([r, g, b]) = heatmap.color(i) {
+         *rgb = u32::from(r) << 16 | u32::from(g) << 8 | u32::from(b);
+      }
    }
    rgb_data
 }
@@ -130,6 +244,7 @@ pub fn change_grid(width: usize, height: usize) -> String {
 #[wasm_bindgen]
 pub struct MazeCarveResults {
    maze_svg: String,
+   code: String,
    pub num_deadends: usize,
 }
 
@@ -138,41 +253,269 @@ impl MazeCarveResults {
    pub fn maze_svg(&self) -> String {
       self.maze_svg.clone()
    }
+
+   /// The maze code that carves this maze again
+   pub fn code(&self) -> String {
+      self.code.clone()
+   }
 }
 
-#[wasm_bindgen]
-pub fn carve_maze(mazegen_algo: &str, seed_string: String) -> MazeCarveResults {
-   let mut app_lock = MAZE_APP.lock().unwrap();
-   let app = app_lock.as_mut().unwrap();
-   let algo = match mazegen_algo {
-      "BinaryTree" => mazegen::Algo::BinaryTree,
-      "Sidewinder" => mazegen::Algo::Sidewinder,
-      "AldousBroder" => mazegen::Algo::AldousBroder,
-      "Wilson" => mazegen::Algo::Wilson,
-      "HuntAndKill" => mazegen::Algo::HuntAndKill,
-      "RecursiveBacktracker" => mazegen::Algo::RecursiveBacktracker,
-      "Kruskal" => mazegen::Algo::Kruskal,
-      "RecursiveDivision" => mazegen::Algo::RecursiveDivision,
-      "Eller" => mazegen::Algo::Eller,
-      "PrimSimplified" => mazegen::Algo::PrimSimplified,
-      "PrimTrue" => mazegen::Algo::PrimTrue,
-      "Empty" => mazegen::Algo::Empty,
-      _ => panic!("Got a bad mazegen algo from JS"),
-   };
-   let mut result = Vec::new();
-   app.grid.reset();
-   let mut rng = if seed_string.is_empty() {
-      XorShiftRng::from_os_rng()
+fn to_js_error<E: std::error::Error>(e: E) -> JsValue {
+   JsValue::from_str(&e.to_string())
+}
+
+/// A code for a maze the size of the current grid, seeded from the text the same way as `maze_bin --seed`, or randomly
+/// if there is no text
+fn new_code(app: &MazeApp, mazegen_algo: &str, seed_string: &str) -> Result<MazeCode, JsValue> {
+   let algo: Algo = mazegen_algo.parse().map_err(to_js_error)?;
+   let seed = if seed_string.is_empty() {
+      rand::random()
    } else {
-      let seed_u64 = fxhash::hash64(&seed_string);
-      XorShiftRng::seed_from_u64(seed_u64)
+      code::seed_from_text(seed_string)
    };
-   mazegen::carve_maze(&mut app.grid, &mut rng, algo);
+   Ok(MazeCode::new(algo, app.grid.width, app.grid.height, seed))
+}
+
+/// The widths and heights allowed by the size inputs in index.html
+const MAZE_SIZES: RangeInclusive<usize> = 2..=100;
+
+/// Only the codes of mazes that can be drawn on a single, flat skeleton, and that fit the size inputs, are accepted.
+/// A pasted code is untrusted, so this is what keeps a huge size from reaching `change_grid`.
+fn parse_code(code: &str) -> Result<MazeCode, JsValue> {
+   let code = MazeCode::decode(code).map_err(to_js_error)?;
+   if code.depth != 1 {
+      return Err(JsValue::from_str("mazes with several floors can't be shown here"));
+   }
+   if code.wrap != Wrap::NONE {
+      return Err(JsValue::from_str("mazes that wrap around can't be shown here"));
+   }
+   if !MAZE_SIZES.contains(&code.width) || !MAZE_SIZES.contains(&code.height) {
+      return Err(JsValue::from_str(&format!(
+         "mazes must be between {} and {} cells wide and tall to be shown here",
+         MAZE_SIZES.start(),
+         MAZE_SIZES.end()
+      )));
+   }
+   Ok(code)
+}
+
+/// The width and height of the maze a code carves, to set up the grid with `change_grid` before carving it
+#[wasm_bindgen]
+pub fn maze_code_size(code: &str) -> Result<Box<[usize]>, JsValue> {
+   let code = parse_code(code)?;
+   Ok(Box::new([code.width, code.height]))
+}
+
+fn maze_svg(grid: &Grid) -> String {
+   let mut result = Vec::new();
    writeln!(result, "<g id=\"g_maze\">").unwrap();
-   app.grid.write_maze_as_svg(&mut result).unwrap();
+   grid.write_maze_as_svg(&mut result).unwrap();
    writeln!(result, "</g>").unwrap();
+   unsafe { String::from_utf8_unchecked(result) }
+}
+
+fn carve_code(app: &mut MazeApp, code: MazeCode) -> MazeCarveResults {
+   app.grid = code.carve();
    MazeCarveResults {
-      maze_svg: unsafe { String::from_utf8_unchecked(result) },
+      maze_svg: maze_svg(&app.grid),
+      code: code.encode(),
       num_deadends: app.grid.dead_ends().count(),
    }
 }
+
+#[wasm_bindgen]
+pub fn carve_maze(mazegen_algo: &str, seed_string: String) -> Result<MazeCarveResults, JsValue> {
+   let mut app_lock = MAZE_APP.lock().unwrap();
+   let app = app_lock.as_mut().unwrap();
+   let code = new_code(app, mazegen_algo, &seed_string)?;
+   Ok(carve_code(app, code))
+}
+
+#[wasm_bindgen]
+pub fn carve_maze_from_code(code: &str) -> Result<MazeCarveResults, JsValue> {
+   let code = parse_code(code)?;
+   let mut app_lock = MAZE_APP.lock().unwrap();
+   Ok(carve_code(app_lock.as_mut().unwrap(), code))
+}
+
+const CARVE_CONNECT: u8 = 0;
+const CARVE_DISCONNECT: u8 = 1;
+const CARVE_VISIT: u8 = 2;
+const CARVE_FRONTIER_ADD: u8 = 3;
+const CARVE_FRONTIER_REMOVE: u8 = 4;
+
+/// Every step of carving a maze, flattened into parallel arrays for JS.
+/// `other_cells` is only meaningful for connect and disconnect steps.
+#[wasm_bindgen]
+pub struct MazeCarveSteps {
+   maze_svg: String,
+   walls_svg: String,
+   kinds: Box<[u8]>,
+   cells: Box<[usize]>,
+   other_cells: Box<[usize]>,
+   code: String,
+   pub num_deadends: usize,
+}
+
+#[wasm_bindgen]
+impl MazeCarveSteps {
+   pub fn maze_svg(&self) -> String {
+      self.maze_svg.clone()
+   }
+
+   /// Every wall of the uncarved grid, with the east and south wall of cell `i` having the ids `we{i}` and `ws{i}`
+   pub fn walls_svg(&self) -> String {
+      self.walls_svg.clone()
+   }
+
+   pub fn kinds(&self) -> Box<[u8]> {
+      self.kinds.clone()
+   }
+
+   pub fn cells(&self) -> Box<[usize]> {
+      self.cells.clone()
+   }
+
+   pub fn other_cells(&self) -> Box<[usize]> {
+      self.other_cells.clone()
+   }
+
+   pub fn code(&self) -> String {
+      self.code.clone()
+   }
+}
+
+#[wasm_bindgen]
+pub fn carve_maze_steps(mazegen_algo: &str, seed_string: String) -> Result<MazeCarveSteps, JsValue> {
+   let mut app_lock = MAZE_APP.lock().unwrap();
+   let app = app_lock.as_mut().unwrap();
+   let code = new_code(app, mazegen_algo, &seed_string)?;
+   Ok(carve_code_steps(app, code))
+}
+
+#[wasm_bindgen]
+pub fn carve_maze_steps_from_code(code: &str) -> Result<MazeCarveSteps, JsValue> {
+   let code = parse_code(code)?;
+   let mut app_lock = MAZE_APP.lock().unwrap();
+   Ok(carve_code_steps(app_lock.as_mut().unwrap(), code))
+}
+
+fn carve_code_steps(app: &mut MazeApp, code: MazeCode) -> MazeCarveSteps {
+   let mut steps: Vec<mazegen::CarveEvent> = Vec::new();
+   app.grid = code.carve_observed(&mut steps);
+   let mut kinds = Vec::with_capacity(steps.len());
+   let mut cells = Vec::with_capacity(steps.len());
+   let mut other_cells = Vec::with_capacity(steps.len());
+   for step in steps {
+      let (kind, cell, other_cell) = match step {
+         mazegen::CarveEvent::Connect(a, b) => (CARVE_CONNECT, a, b),
+         mazegen::CarveEvent::Disconnect(a, b) => (CARVE_DISCONNECT, a, b),
+         mazegen::CarveEvent::Visit(a) => (CARVE_VISIT, a, a),
+         mazegen::CarveEvent::FrontierAdd(a) => (CARVE_FRONTIER_ADD, a, a),
+         mazegen::CarveEvent::FrontierRemove(a) => (CARVE_FRONTIER_REMOVE, a, a),
+      };
+      kinds.push(kind);
+      cells.push(cell);
+      other_cells.push(other_cell);
+   }
+
+   let mut walls_svg = Vec::new();
+   let (width, height) = (app.grid.width, app.grid.height);
+   writeln!(walls_svg, "<g id=\"g_maze\">").unwrap();
+   writeln!(walls_svg, "<line x1=\"0\" y1=\"0\" x2=\"{}\" y2=\"0\"/>", width * 3).unwrap();
+   writeln!(walls_svg, "<line x1=\"0\" y1=\"0\" x2=\"0\" y2=\"{}\"/>", height * 3).unwrap();
+   for i in 0..app.grid.size() {
+      let x = (i % width) * 3;
+      let y = (i / width) * 3;
+      writeln!(
+         walls_svg,
+         "<line id=\"we{}\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>",
+         i,
+         x + 3,
+         y,
+         x + 3,
+         y + 3
+      )
+      .unwrap();
+      writeln!(
+         walls_svg,
+         "<line id=\"ws{}\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>",
+         i,
+         x,
+         y + 3,
+         x + 3,
+         y + 3
+      )
+      .unwrap();
+   }
+   writeln!(walls_svg, "</g>").unwrap();
+
+   MazeCarveSteps {
+      maze_svg: maze_svg(&app.grid),
+      walls_svg: unsafe { String::from_utf8_unchecked(walls_svg) },
+      kinds: kinds.into_boxed_slice(),
+      cells: cells.into_boxed_slice(),
+      other_cells: other_cells.into_boxed_slice(),
+      code: code.encode(),
+      num_deadends: app.grid.dead_ends().count(),
+   }
+}
+
+#[cfg(test)]
+mod test {
+   use super::{AGENT_FILL, AGENT_MOVE, AgentStepsWasm, DIAG_PATH, PfDataWasm};
+   use maze_lib::grid::Grid;
+   use maze_lib::mazegen::{self, Algo};
+   use maze_lib::pathfinding::agents::{self, Hand};
+   use maze_lib::pathfinding::{algos, heuristics};
+   use rand::SeedableRng;
+   use rand::rngs::StdRng;
+
+   #[test]
+   fn path_data_converts_field_for_field() {
+      let mut grid = Grid::new(6, 5);
+      mazegen::carve_maze(&mut grid, &mut StdRng::seed_from_u64(0), Algo::Kruskal);
+      let pf_data = algos::a_star(&grid, heuristics::null_h, 0, grid.size() - 1, false).unwrap();
+      let statuses = pf_data.diag.status_bytes();
+      let path = pf_data.path.clone();
+      let generated_history = pf_data.diag.generated_history.clone();
+      let expanded_history = pf_data.diag.expanded_history.clone();
+      let num_generated_history = pf_data.diag.num_generated_history.clone();
+      let nodes = (pf_data.nodes_generated, pf_data.nodes_expanded);
+
+      let wasm = PfDataWasm::from(pf_data);
+      assert_eq!(wasm.path, path);
+      assert_eq!((wasm.nodes_generated, wasm.nodes_expanded), nodes);
+      assert_eq!(wasm.diag.generated_history, generated_history);
+      assert_eq!(wasm.diag.expanded_history, expanded_history);
+      assert_eq!(wasm.diag.num_generated_history, num_generated_history);
+      for (i, status) in statuses.iter().enumerate() {
+         let expected = if path.contains(&i) { DIAG_PATH } else { *status };
+         assert_eq!(wasm.diag.inner[i], expected);
+      }
+      // the same bytes the web front end colors cells by
+      assert!(statuses.iter().all(|x| [0x00, 0x01, 0x03].contains(x)));
+      assert!(statuses.contains(&0x03));
+   }
+
+   #[test]
+   fn agent_steps_line_up() {
+      let mut grid = Grid::new(6, 5);
+      mazegen::carve_maze(&mut grid, &mut StdRng::seed_from_u64(0), Algo::Kruskal);
+      let goal = grid.size() - 1;
+      let run = agents::wall_follower(&grid, 0, goal, Hand::Left);
+      let moves: Vec<usize> = run.moves().collect();
+      let wasm = AgentStepsWasm::from(run);
+      assert!(!wasm.looped);
+      assert_eq!(wasm.num_moves, moves.len());
+      assert_eq!(wasm.path.last(), Some(&goal));
+      assert!(wasm.kinds.iter().all(|x| *x == AGENT_MOVE));
+      assert_eq!(&*wasm.cells, &*moves);
+
+      let wasm = AgentStepsWasm::from(agents::dead_end_filling(&grid, 0, goal));
+      assert_eq!(wasm.num_moves, 0);
+      assert_eq!(wasm.kinds.len() + wasm.path.len(), grid.size());
+      assert!(wasm.kinds.iter().all(|x| *x == AGENT_FILL));
+      assert!(wasm.cells.iter().all(|x| !wasm.path.contains(x)));
+   }
+}